@@ -0,0 +1,527 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path, Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+use crate::video_url::parse_video_url;
+use crate::worker::{JobEvents, JobId, WorkerCommand, WorkerEvent, WorkerHandle};
+
+const TOKEN_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSettings {
+    /// The API is off unless explicitly enabled.
+    pub enabled: bool,
+    /// Port on 127.0.0.1; the API never listens on other interfaces.
+    pub port: u16,
+    /// Bearer token every request must present. Generated on first start if empty.
+    pub token: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8917,
+            token: String::new(),
+        }
+    }
+}
+
+pub fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    FetchingInfo,
+    Downloading,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn is_done(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Finished | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiJob {
+    pub id: u64,
+    pub url: String,
+    pub status: JobStatus,
+    pub title: Option<String>,
+    /// Percentage of the video stream downloaded so far.
+    pub progress: f32,
+    pub downloaded_bytes: f64,
+    pub path: Option<PathBuf>,
+    pub error: Option<String>,
+    /// Worker job fetching and downloading the video, to cancel it.
+    #[serde(skip)]
+    download_job: Option<JobId>,
+}
+
+#[derive(Default)]
+struct JobList {
+    jobs: Vec<ApiJob>,
+    next_id: u64,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    jobs: Arc<Mutex<JobList>>,
+    job_queued: Arc<Notify>,
+    token: Arc<String>,
+    worker: WorkerHandle,
+}
+
+impl ApiState {
+    fn update_job(&self, id: u64, update: impl FnOnce(&mut ApiJob)) {
+        let mut job_list = self.jobs.lock().unwrap();
+        if let Some(job) = job_list.jobs.iter_mut().find(|job| job.id == id) {
+            update(job);
+        }
+    }
+
+    fn job_status(&self, id: u64) -> Option<JobStatus> {
+        let job_list = self.jobs.lock().unwrap();
+        job_list
+            .jobs
+            .iter()
+            .find(|job| job.id == id)
+            .map(|job| job.status)
+    }
+
+    fn next_queued_job(&self) -> Option<ApiJob> {
+        let job_list = self.jobs.lock().unwrap();
+        job_list
+            .jobs
+            .iter()
+            .find(|job| job.status == JobStatus::Queued)
+            .cloned()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EnqueueRequest {
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+fn error_response(status: StatusCode, error: impl ToString) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Serves the control API on localhost and runs the submitted jobs one after another
+/// on the worker shared with the app.
+///
+/// - `POST /jobs` with `{"url": "..."}` queues a download
+/// - `GET /jobs` and `GET /jobs/{id}` report status and progress
+/// - `DELETE /jobs/{id}` or `POST /jobs/{id}/cancel` cancels a job
+///
+/// Every request needs an `Authorization: Bearer <token>` header.
+pub async fn serve(settings: ApiSettings, worker: WorkerHandle) -> anyhow::Result<()> {
+    let state = ApiState {
+        jobs: Arc::new(Mutex::new(JobList::default())),
+        job_queued: Arc::new(Notify::new()),
+        token: Arc::new(settings.token),
+        worker,
+    };
+
+    let app = router(state.clone());
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, settings.port));
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    info!("Control API listening on http://{}", address);
+
+    tokio::spawn(run_jobs(state));
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/jobs", get(list_jobs).post(enqueue_job))
+        .route("/jobs/:id", get(get_job).delete(cancel_job))
+        .route("/jobs/:id/cancel", post(cancel_job))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state)
+}
+
+async fn require_token(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if token == state.token.as_str() => next.run(request).await,
+        _ => error_response(StatusCode::UNAUTHORIZED, "Missing or invalid token"),
+    }
+}
+
+async fn list_jobs(State(state): State<ApiState>) -> Json<Vec<ApiJob>> {
+    Json(state.jobs.lock().unwrap().jobs.clone())
+}
+
+async fn get_job(State(state): State<ApiState>, Path(id): Path<u64>) -> Response {
+    let job_list = state.jobs.lock().unwrap();
+    match job_list.jobs.iter().find(|job| job.id == id) {
+        Some(job) => Json(job.clone()).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, format!("No job {}", id)),
+    }
+}
+
+async fn enqueue_job(
+    State(state): State<ApiState>,
+    Json(request): Json<EnqueueRequest>,
+) -> Response {
+    let url = match parse_video_url(&request.url) {
+        Ok(video_url) => video_url.normalized(),
+        Err(e) => return error_response(StatusCode::UNPROCESSABLE_ENTITY, e),
+    };
+
+    let job = {
+        let mut job_list = state.jobs.lock().unwrap();
+        job_list.next_id += 1;
+        let job = ApiJob {
+            id: job_list.next_id,
+            url,
+            status: JobStatus::Queued,
+            title: None,
+            progress: 0.0,
+            downloaded_bytes: 0.0,
+            path: None,
+            error: None,
+            download_job: None,
+        };
+        job_list.jobs.push(job.clone());
+        job
+    };
+    debug!("API queued job {}: {}", job.id, job.url);
+    state.job_queued.notify_one();
+    (StatusCode::CREATED, Json(job)).into_response()
+}
+
+async fn cancel_job(State(state): State<ApiState>, Path(id): Path<u64>) -> Response {
+    let download_job = {
+        let mut job_list = state.jobs.lock().unwrap();
+        let job = match job_list.jobs.iter_mut().find(|job| job.id == id) {
+            Some(job) => job,
+            None => return error_response(StatusCode::NOT_FOUND, format!("No job {}", id)),
+        };
+        if job.status.is_done() {
+            return error_response(StatusCode::CONFLICT, format!("Job {} already ended", id));
+        }
+        job.status = JobStatus::Cancelled;
+        job.download_job
+    };
+    // Jobs still queued never reach the worker. A cancel arriving while the info is
+    // fetched stops the download as soon as it starts.
+    if let Some(download_job) = download_job {
+        state.worker.send(WorkerCommand::Cancel(download_job));
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn run_jobs(state: ApiState) {
+    loop {
+        let job = match state.next_queued_job() {
+            Some(job) => job,
+            None => {
+                state.job_queued.notified().await;
+                continue;
+            }
+        };
+        if let Err(e) = run_job(&state, &job).await {
+            warn!("API job {} failed: {}", job.id, e);
+            state.update_job(job.id, |job| {
+                if job.status != JobStatus::Cancelled {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e);
+                }
+            });
+        }
+    }
+}
+
+async fn run_job(state: &ApiState, job: &ApiJob) -> Result<(), String> {
+    info!("Running API job {}: {}", job.id, job.url);
+    // One self-contained worker job, so the download never touches the video and
+    // formats selected in the app.
+    let mut events = state.worker.start(WorkerCommand::DownloadUrl {
+        url: job.url.clone(),
+        video_format: None,
+        audio_format: None,
+        output_dir: None,
+        check_disk_space: true,
+    });
+    state.update_job(job.id, |job| {
+        job.status = JobStatus::FetchingInfo;
+        job.download_job = Some(events.job_id);
+    });
+    // The job may have been cancelled before the worker job id was recorded.
+    if state.job_status(job.id) == Some(JobStatus::Cancelled) {
+        state.worker.send(WorkerCommand::Cancel(events.job_id));
+    }
+
+    loop {
+        match next_event(&mut events).await? {
+            WorkerEvent::InfoFetched(video_info) => {
+                state.update_job(job.id, |job| {
+                    if job.status != JobStatus::Cancelled {
+                        job.status = JobStatus::Downloading;
+                    }
+                    job.title = Some(video_info.title.clone());
+                });
+            }
+            WorkerEvent::InfoFetchFailed(reason) => return Err(reason),
+            WorkerEvent::Progress {
+                downloaded_bytes,
+                percent,
+            } => {
+                state.update_job(job.id, |job| {
+                    job.downloaded_bytes = downloaded_bytes;
+                    job.progress = percent;
+                });
+            }
+            WorkerEvent::VideoDownloaded(path) => {
+                state.update_job(job.id, |job| {
+                    if job.status != JobStatus::Cancelled {
+                        job.status = JobStatus::Finished;
+                    }
+                    job.progress = 100.0;
+                    job.path = Some(path);
+                });
+                return Ok(());
+            }
+            WorkerEvent::DownloadFailed(reason) => return Err(reason),
+            WorkerEvent::InsufficientDiskSpace(shortage) => return Err(shortage.to_string()),
+            _ => {}
+        }
+    }
+}
+
+async fn next_event(events: &mut JobEvents) -> Result<WorkerEvent, String> {
+    events
+        .next()
+        .await
+        .ok_or_else(|| "Worker stopped".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header::CONTENT_TYPE, Method};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::worker::{EventSender, FakeWorker};
+
+    const TOKEN: &str = "secret-token";
+    const VIDEO_URL: &str = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+
+    fn test_state() -> (ApiState, FakeWorker) {
+        let (worker, fake_worker) = FakeWorker::new();
+        let state = ApiState {
+            jobs: Arc::new(Mutex::new(JobList::default())),
+            job_queued: Arc::new(Notify::new()),
+            token: Arc::new(TOKEN.to_string()),
+            worker,
+        };
+        (state, fake_worker)
+    }
+
+    fn add_job(state: &ApiState, status: JobStatus, download_job: Option<JobId>) -> ApiJob {
+        let mut job_list = state.jobs.lock().unwrap();
+        job_list.next_id += 1;
+        let job = ApiJob {
+            id: job_list.next_id,
+            url: VIDEO_URL.to_string(),
+            status,
+            title: None,
+            progress: 0.0,
+            downloaded_bytes: 0.0,
+            path: None,
+            error: None,
+            download_job,
+        };
+        job_list.jobs.push(job.clone());
+        job
+    }
+
+    fn request(method: Method, uri: &str, token: Option<&str>, body: Option<&str>) -> Request {
+        let mut builder = axum::http::Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            builder = builder.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => builder
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        };
+        request.unwrap()
+    }
+
+    async fn send(state: &ApiState, request: Request) -> Response {
+        router(state.clone()).oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn requests_need_the_token() {
+        let (state, _fake_worker) = test_state();
+
+        let missing = send(&state, request(Method::GET, "/jobs", None, None)).await;
+        let wrong = send(&state, request(Method::GET, "/jobs", Some("wrong"), None)).await;
+        let valid = send(&state, request(Method::GET, "/jobs", Some(TOKEN), None)).await;
+
+        assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(valid.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn enqueue_rejects_invalid_urls() {
+        let (state, _fake_worker) = test_state();
+
+        let response = send(
+            &state,
+            request(
+                Method::POST,
+                "/jobs",
+                Some(TOKEN),
+                Some(r#"{"url": "not a video"}"#),
+            ),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(state.jobs.lock().unwrap().jobs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn enqueue_queues_a_job() {
+        let (state, _fake_worker) = test_state();
+
+        let response = send(
+            &state,
+            request(
+                Method::POST,
+                "/jobs",
+                Some(TOKEN),
+                Some(&serde_json::json!({ "url": VIDEO_URL }).to_string()),
+            ),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let job: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(job["id"], 1);
+        assert_eq!(job["status"], "queued");
+        assert_eq!(state.job_status(1), Some(JobStatus::Queued));
+    }
+
+    #[tokio::test]
+    async fn cancel_rejects_unknown_and_ended_jobs() {
+        let (state, _fake_worker) = test_state();
+        let finished = add_job(&state, JobStatus::Finished, Some(7));
+
+        let unknown = send(
+            &state,
+            request(Method::DELETE, "/jobs/42", Some(TOKEN), None),
+        )
+        .await;
+        let ended = send(
+            &state,
+            request(
+                Method::POST,
+                &format!("/jobs/{}/cancel", finished.id),
+                Some(TOKEN),
+                None,
+            ),
+        )
+        .await;
+
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+        assert_eq!(ended.status(), StatusCode::CONFLICT);
+        assert_eq!(state.job_status(finished.id), Some(JobStatus::Finished));
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_the_worker_job() {
+        let (state, mut fake_worker) = test_state();
+        let running = add_job(&state, JobStatus::Downloading, Some(7));
+
+        let response = send(
+            &state,
+            request(
+                Method::DELETE,
+                &format!("/jobs/{}", running.id),
+                Some(TOKEN),
+                None,
+            ),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(state.job_status(running.id), Some(JobStatus::Cancelled));
+        assert_eq!(
+            fake_worker.jobs.try_recv().unwrap().command,
+            WorkerCommand::Cancel(7)
+        );
+    }
+
+    #[tokio::test]
+    async fn download_finishing_after_cancel_stays_cancelled() {
+        let (state, mut fake_worker) = test_state();
+        let job = add_job(&state, JobStatus::Queued, None);
+        let run = tokio::spawn({
+            let state = state.clone();
+            let job = job.clone();
+            async move { run_job(&state, &job).await }
+        });
+
+        let worker_job = fake_worker.jobs.recv().await.unwrap();
+        state.update_job(job.id, |job| job.status = JobStatus::Cancelled);
+        EventSender::new(worker_job.id, fake_worker.events.clone())
+            .send(WorkerEvent::VideoDownloaded(PathBuf::from("video.mp4")));
+
+        assert_eq!(run.await.unwrap(), Ok(()));
+        assert_eq!(state.job_status(job.id), Some(JobStatus::Cancelled));
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+pub mod fake;
+mod model;
+pub mod yt_dlp_backend;
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+
+use crate::live::RecordingMode;
+
+pub use model::{Caption, Format, Thumbnail, Video};
+
+pub type ProgressCallback = dyn Fn(u64, u64) + Send + Sync;
+
+/// Everything the downloader needs from an extractor. The worker, batch runner and
+/// UI only talk to this trait, so the extractor can be swapped, e.g. for a fake in
+/// tests.
+#[async_trait]
+pub trait MediaBackend: std::fmt::Debug + Send + Sync {
+    async fn fetch_info(&self, url: &str) -> anyhow::Result<Video>;
+
+    /// Formats that can be downloaded, excluding storyboards.
+    fn list_formats<'a>(&self, video: &'a Video) -> Vec<&'a Format> {
+        video
+            .formats
+            .iter()
+            .filter(|format| !format.is_storyboard())
+            .collect()
+    }
+
+    /// Downloads a single stream to `path`, reporting `(downloaded, total)` bytes.
+    async fn download_stream(
+        &self,
+        url: &str,
+        path: &Path,
+        progress: &ProgressCallback,
+    ) -> anyhow::Result<()>;
+
+    /// Downloads the default thumbnail of the video at `video_url` to `path`.
+    async fn download_thumbnail(&self, video_url: &str, path: &Path) -> anyhow::Result<PathBuf>;
+
+    /// Muxes separately downloaded video and audio streams into `output_path`.
+    async fn merge(
+        &self,
+        video_path: &Path,
+        audio_path: &Path,
+        output_path: &Path,
+    ) -> anyhow::Result<PathBuf>;
+
+    /// When the upcoming stream at `video_url` is scheduled to start, if the site says.
+    async fn scheduled_start(&self, video_url: &str) -> anyhow::Result<Option<SystemTime>>;
+
+    /// Starts recording the live stream at `video_url` into `dir`, as `recording.<ext>`.
+    async fn record_live(
+        &self,
+        video_url: &str,
+        mode: RecordingMode,
+        dir: &Path,
+    ) -> anyhow::Result<Box<dyn LiveRecording>>;
+}
+
+/// A running live stream recording.
+#[async_trait]
+pub trait LiveRecording: Send {
+    /// Waits until the recording ends on its own, usually because the stream did.
+    async fn wait(&mut self) -> anyhow::Result<()>;
+
+    /// Stops recording and waits until what was recorded so far is finalized.
+    async fn stop(&mut self) -> anyhow::Result<()>;
+}
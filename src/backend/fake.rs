@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+
+use super::{LiveRecording, MediaBackend, ProgressCallback, Video};
+use crate::live::RecordingMode;
+
+/// In-memory backend for tests: serves registered videos and stream bytes and
+/// records what was requested.
+#[derive(Debug, Default)]
+pub struct FakeBackend {
+    videos: HashMap<String, Video>,
+    streams: HashMap<String, Vec<u8>>,
+    stalled_streams: HashSet<String>,
+    scheduled_starts: HashMap<String, SystemTime>,
+    live_streams: HashMap<String, Vec<u8>>,
+    pub downloaded_urls: Mutex<Vec<String>>,
+    pub merges: Mutex<Vec<PathBuf>>,
+    pub recordings: Mutex<Vec<(String, RecordingMode)>>,
+}
+
+impl FakeBackend {
+    pub fn with_video(mut self, url: &str, video: Video) -> Self {
+        self.videos.insert(url.to_string(), video);
+        self
+    }
+
+    pub fn with_stream(mut self, url: &str, content: &[u8]) -> Self {
+        self.streams.insert(url.to_string(), content.to_vec());
+        self
+    }
+
+    /// A stream whose download never finishes, to keep the worker busy.
+    pub fn with_stalled_stream(mut self, url: &str) -> Self {
+        self.stalled_streams.insert(url.to_string());
+        self
+    }
+
+    pub fn with_scheduled_start(mut self, url: &str, scheduled_start: SystemTime) -> Self {
+        self.scheduled_starts
+            .insert(url.to_string(), scheduled_start);
+        self
+    }
+
+    /// A live stream whose recording writes `content` at once and then runs until
+    /// stopped.
+    pub fn with_live_stream(mut self, url: &str, content: &[u8]) -> Self {
+        self.live_streams.insert(url.to_string(), content.to_vec());
+        self
+    }
+}
+
+#[derive(Debug)]
+struct FakeRecording;
+
+#[async_trait]
+impl LiveRecording for FakeRecording {
+    async fn wait(&mut self) -> anyhow::Result<()> {
+        std::future::pending().await
+    }
+
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MediaBackend for FakeBackend {
+    async fn fetch_info(&self, url: &str) -> anyhow::Result<Video> {
+        self.videos
+            .get(url)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unsupported URL: {}", url))
+    }
+
+    async fn download_stream(
+        &self,
+        url: &str,
+        path: &Path,
+        progress: &ProgressCallback,
+    ) -> anyhow::Result<()> {
+        if self.stalled_streams.contains(url) {
+            std::future::pending::<()>().await;
+        }
+        let content = self
+            .streams
+            .get(url)
+            .ok_or_else(|| anyhow::anyhow!("HTTP 404 Not Found for {}", url))?;
+        std::fs::write(path, content)?;
+        progress(content.len() as u64, content.len() as u64);
+        self.downloaded_urls.lock().unwrap().push(url.to_string());
+        Ok(())
+    }
+
+    async fn download_thumbnail(&self, video_url: &str, path: &Path) -> anyhow::Result<PathBuf> {
+        let video = self.fetch_info(video_url).await?;
+        self.download_stream(&video.thumbnail, path, &|_, _| {})
+            .await?;
+        Ok(path.to_path_buf())
+    }
+
+    /// Concatenates the streams, which is enough to check both ended up in the output.
+    async fn merge(
+        &self,
+        video_path: &Path,
+        audio_path: &Path,
+        output_path: &Path,
+    ) -> anyhow::Result<PathBuf> {
+        let mut content = std::fs::read(video_path)?;
+        content.extend(std::fs::read(audio_path)?);
+        std::fs::write(output_path, content)?;
+        self.merges.lock().unwrap().push(output_path.to_path_buf());
+        Ok(output_path.to_path_buf())
+    }
+
+    async fn scheduled_start(&self, video_url: &str) -> anyhow::Result<Option<SystemTime>> {
+        Ok(self.scheduled_starts.get(video_url).copied())
+    }
+
+    async fn record_live(
+        &self,
+        video_url: &str,
+        mode: RecordingMode,
+        dir: &Path,
+    ) -> anyhow::Result<Box<dyn LiveRecording>> {
+        let content = self
+            .live_streams
+            .get(video_url)
+            .ok_or_else(|| anyhow::anyhow!("{} is not live", video_url))?;
+        std::fs::write(dir.join("recording.mp4"), content)?;
+        self.recordings
+            .lock()
+            .unwrap()
+            .push((video_url.to_string(), mode));
+        Ok(Box::new(FakeRecording))
+    }
+}
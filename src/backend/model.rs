@@ -0,0 +1,225 @@
+//! Video metadata as the app uses it. Backends convert their extractor's output into
+//! these types, so nothing outside the backend depends on the extractor crate.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Video {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub channel: String,
+    pub channel_id: String,
+    pub channel_url: String,
+    /// URL of the default thumbnail, empty when the site has none.
+    pub thumbnail: String,
+    pub thumbnails: Vec<Thumbnail>,
+    pub formats: Vec<Format>,
+    /// Automatic captions by language code, e.g. `en` or `de-orig`.
+    pub automatic_captions: HashMap<String, Vec<Caption>>,
+    /// yt-dlp's live status, e.g. `is_live`, `is_upcoming` or `not_live`.
+    pub live_status: String,
+    /// Extractor that handled the URL, e.g. `youtube`, and its name, e.g. `Youtube`.
+    pub extractor: String,
+    pub extractor_key: String,
+}
+
+impl Video {
+    /// Best video-only format: highest quality, then resolution, frame rate and bitrate.
+    pub fn best_video_format(&self) -> Option<&Format> {
+        self.formats
+            .iter()
+            .filter(|format| format.is_video())
+            .max_by(|left, right| {
+                let key = |format: &Format| {
+                    [
+                        format.quality.unwrap_or(0.0),
+                        format.height.unwrap_or(0) as f64,
+                        format.fps.unwrap_or(0.0),
+                        format.video_bitrate.or(format.total_bitrate).unwrap_or(0.0),
+                    ]
+                };
+                compare_keys(&key(left), &key(right))
+            })
+    }
+
+    /// Best audio-only format: highest quality, then bitrate and sample rate.
+    pub fn best_audio_format(&self) -> Option<&Format> {
+        self.formats
+            .iter()
+            .filter(|format| format.is_audio())
+            .max_by(|left, right| {
+                let key = |format: &Format| {
+                    [
+                        format.quality.unwrap_or(0.0),
+                        format.audio_bitrate.or(format.total_bitrate).unwrap_or(0.0),
+                        format.sample_rate.unwrap_or(0) as f64,
+                    ]
+                };
+                compare_keys(&key(left), &key(right))
+            })
+    }
+}
+
+fn compare_keys(left: &[f64], right: &[f64]) -> Ordering {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| left.total_cmp(right))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Format {
+    pub format_id: String,
+    pub format_note: Option<String>,
+    /// URL the stream is downloaded from.
+    pub url: String,
+    pub container: Option<String>,
+    /// Codecs as the site reports them: `None` when unknown, `"none"` when the format
+    /// has no such stream.
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub video_ext: Option<String>,
+    pub audio_ext: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    pub quality: Option<f64>,
+    pub filesize: Option<u64>,
+    pub filesize_approx: Option<u64>,
+    /// Bitrates in kbit/s.
+    pub total_bitrate: Option<f64>,
+    pub video_bitrate: Option<f64>,
+    pub audio_bitrate: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub audio_channels: Option<u32>,
+    pub dynamic_range: Option<String>,
+    pub language: Option<String>,
+    pub has_drm: bool,
+}
+
+impl Format {
+    pub fn has_video(&self) -> bool {
+        is_codec(&self.video_codec)
+    }
+
+    pub fn has_audio(&self) -> bool {
+        is_codec(&self.audio_codec)
+    }
+
+    /// A video stream without audio, to be merged with an audio stream.
+    pub fn is_video(&self) -> bool {
+        self.has_video() && !self.has_audio()
+    }
+
+    /// An audio stream without video.
+    pub fn is_audio(&self) -> bool {
+        self.has_audio() && !self.has_video()
+    }
+
+    pub fn is_storyboard(&self) -> bool {
+        self.format_note.as_deref() == Some("storyboard")
+    }
+}
+
+fn is_codec(codec: &Option<String>) -> bool {
+    codec
+        .as_deref()
+        .is_some_and(|codec| !codec.is_empty() && codec != "none")
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Thumbnail {
+    pub id: String,
+    pub url: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Caption {
+    pub url: String,
+    /// File extension of the caption format, e.g. `vtt` or `srt`.
+    pub extension: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(format_id: &str, video_codec: &str, audio_codec: &str) -> Format {
+        Format {
+            format_id: format_id.to_string(),
+            video_codec: Some(video_codec.to_string()),
+            audio_codec: Some(audio_codec.to_string()),
+            ..Format::default()
+        }
+    }
+
+    #[test]
+    fn stream_kinds_follow_the_codecs() {
+        assert!(format("137", "avc1", "none").is_video());
+        assert!(format("140", "none", "mp4a.40.2").is_audio());
+        let progressive = format("18", "avc1", "mp4a.40.2");
+        assert!(!progressive.is_video() && !progressive.is_audio());
+        let unknown = Format::default();
+        assert!(!unknown.has_video() && !unknown.has_audio());
+    }
+
+    #[test]
+    fn best_formats_prefer_quality_then_resolution_and_bitrate() {
+        let video = Video {
+            formats: vec![
+                Format {
+                    height: Some(2160),
+                    quality: Some(8.0),
+                    ..format("313", "vp9", "none")
+                },
+                Format {
+                    height: Some(1080),
+                    quality: Some(9.0),
+                    ..format("137", "avc1", "none")
+                },
+                Format {
+                    height: Some(1080),
+                    quality: Some(9.0),
+                    fps: Some(60.0),
+                    ..format("299", "avc1", "none")
+                },
+                Format {
+                    quality: Some(3.0),
+                    total_bitrate: Some(129.5),
+                    ..format("140", "none", "mp4a.40.2")
+                },
+                Format {
+                    quality: Some(3.0),
+                    audio_bitrate: Some(160.0),
+                    ..format("251", "none", "opus")
+                },
+                Format {
+                    quality: Some(10.0),
+                    ..format("18", "avc1", "mp4a.40.2")
+                },
+            ],
+            ..Video::default()
+        };
+
+        assert_eq!(
+            video
+                .best_video_format()
+                .map(|format| format.format_id.as_str()),
+            Some("299")
+        );
+        assert_eq!(
+            video
+                .best_audio_format()
+                .map(|format| format.format_id.as_str()),
+            Some("251")
+        );
+        assert_eq!(Video::default().best_video_format(), None);
+    }
+}
@@ -0,0 +1,321 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+use yt_dlp::{fetcher::deps::Libraries, Youtube};
+
+use super::{Caption, Format, LiveRecording, MediaBackend, ProgressCallback, Thumbnail, Video};
+use crate::live::RecordingMode;
+use crate::retry::error_for_status;
+
+// A server that stops sending data would otherwise hang the download forever.
+const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+// Time yt-dlp gets to finalize a recording after being interrupted.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Backend built on the yt-dlp crate: metadata and thumbnails come from the yt-dlp
+/// executable, streams are fetched directly and merged with ffmpeg.
+#[derive(Debug, Clone)]
+pub struct YtDlpBackend {
+    yt_dlp_executable_path: PathBuf,
+    ffmpeg_executable_path: PathBuf,
+    libraries: Libraries,
+    fetcher: Youtube,
+    stall_timeout: Duration,
+}
+
+impl YtDlpBackend {
+    pub fn new(
+        yt_dlp_executable_path: PathBuf,
+        ffmpeg_executable_path: PathBuf,
+    ) -> anyhow::Result<Self> {
+        let libraries = Libraries::new(
+            yt_dlp_executable_path.clone(),
+            ffmpeg_executable_path.clone(),
+        );
+        let fetcher = Youtube::new(libraries.clone(), std::env::temp_dir())?;
+        Ok(Self {
+            yt_dlp_executable_path,
+            ffmpeg_executable_path,
+            libraries,
+            fetcher,
+            stall_timeout: DEFAULT_STALL_TIMEOUT,
+        })
+    }
+
+    /// How long a stream download may wait for the server before giving up.
+    pub fn with_stall_timeout(mut self, stall_timeout: Duration) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    async fn within_stall_timeout<T>(
+        &self,
+        url: &str,
+        future: impl std::future::Future<Output = reqwest::Result<T>>,
+    ) -> anyhow::Result<T> {
+        match tokio::time::timeout(self.stall_timeout, future).await {
+            Ok(result) => Ok(result?),
+            Err(_) => anyhow::bail!(
+                "Download of {} timed out after {:.1}s without data",
+                url,
+                self.stall_timeout.as_secs_f64()
+            ),
+        }
+    }
+
+    /// The yt-dlp crate works on file names inside its output dir, so file
+    /// operations get a fetcher pointed at the directory of their target.
+    fn fetcher_for(&self, path: &Path) -> anyhow::Result<(Youtube, String)> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let file_name = path
+            .file_name()
+            .with_context(|| format!("{} has no file name", path.display()))?
+            .to_string_lossy()
+            .to_string();
+        Ok((
+            Youtube::new(self.libraries.clone(), dir.to_path_buf())?,
+            file_name,
+        ))
+    }
+}
+
+#[async_trait]
+impl MediaBackend for YtDlpBackend {
+    async fn fetch_info(&self, url: &str) -> anyhow::Result<Video> {
+        let video = self.fetcher.fetch_video_infos(url.to_string()).await?;
+        Ok(convert_video(video))
+    }
+
+    async fn download_stream(
+        &self,
+        url: &str,
+        path: &Path,
+        progress: &ProgressCallback,
+    ) -> anyhow::Result<()> {
+        let response = self.within_stall_timeout(url, reqwest::get(url)).await?;
+        let mut response = error_for_status(url, response)?;
+        let total = response.content_length().unwrap_or(0);
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut downloaded: u64 = 0;
+        while let Some(chunk) = self.within_stall_timeout(url, response.chunk()).await? {
+            file.write_all(&chunk).await?;
+            downloaded += chunk.len() as u64;
+            progress(downloaded, total);
+        }
+        file.flush().await?;
+        if total > 0 && downloaded < total {
+            // An I/O error so the retry policy treats it like a dropped connection.
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("Stream ended after {} of {} bytes", downloaded, total),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    async fn download_thumbnail(&self, video_url: &str, path: &Path) -> anyhow::Result<PathBuf> {
+        let (fetcher, file_name) = self.fetcher_for(path)?;
+        Ok(fetcher
+            .download_thumbnail_from_url(video_url.to_string(), file_name)
+            .await?)
+    }
+
+    async fn merge(
+        &self,
+        video_path: &Path,
+        audio_path: &Path,
+        output_path: &Path,
+    ) -> anyhow::Result<PathBuf> {
+        let (fetcher, output_file_name) = self.fetcher_for(output_path)?;
+        // The fetcher joins the inputs onto its own dir, which only leaves absolute
+        // paths alone.
+        let absolute = |path: &Path| {
+            std::fs::canonicalize(path)
+                .with_context(|| format!("Failed to resolve {}", path.display()))
+        };
+        Ok(fetcher
+            .combine_audio_and_video(
+                absolute(video_path)?.display().to_string(),
+                absolute(audio_path)?.display().to_string(),
+                output_file_name,
+            )
+            .await?)
+    }
+
+    /// The video model does not carry `release_timestamp`, so it is asked from yt-dlp
+    /// directly.
+    async fn scheduled_start(&self, video_url: &str) -> anyhow::Result<Option<SystemTime>> {
+        let output = tokio::process::Command::new(&self.yt_dlp_executable_path)
+            .args([
+                "--skip-download",
+                "--no-warnings",
+                "--print",
+                "release_timestamp",
+            ])
+            .arg(video_url)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .context("Failed to run yt-dlp")?;
+        // yt-dlp prints `NA` when the site does not announce a start time.
+        let timestamp = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<u64>();
+        Ok(timestamp
+            .ok()
+            .map(|timestamp| UNIX_EPOCH + Duration::from_secs(timestamp)))
+    }
+
+    /// Recording is left to yt-dlp, which handles the HLS/DASH segment playlists.
+    async fn record_live(
+        &self,
+        video_url: &str,
+        mode: RecordingMode,
+        dir: &Path,
+    ) -> anyhow::Result<Box<dyn LiveRecording>> {
+        let mut command = tokio::process::Command::new(&self.yt_dlp_executable_path);
+        command
+            .arg("--ffmpeg-location")
+            .arg(&self.ffmpeg_executable_path)
+            .args(["--format", "bv*+ba/b", "--no-part", "--quiet"])
+            .arg("--output")
+            .arg(dir.join("recording.%(ext)s"))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true);
+        if mode == RecordingMode::FromStart {
+            command.arg("--live-from-start");
+        }
+        command.arg(video_url);
+        let child = command.spawn().context("Failed to start yt-dlp")?;
+        Ok(Box::new(YtDlpRecording { child }))
+    }
+}
+
+struct YtDlpRecording {
+    child: tokio::process::Child,
+}
+
+#[async_trait]
+impl LiveRecording for YtDlpRecording {
+    async fn wait(&mut self) -> anyhow::Result<()> {
+        let exit_status = self.child.wait().await?;
+        debug!("yt-dlp exited with {}", exit_status);
+        Ok(())
+    }
+
+    /// Interrupts yt-dlp like Ctrl+C would, so it stops fetching segments and
+    /// finalizes what it has recorded so far.
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        #[cfg(unix)]
+        if let Some(pid) = self.child.id() {
+            use nix::sys::signal::{kill, Signal};
+            use nix::unistd::Pid;
+
+            if let Err(e) = kill(Pid::from_raw(pid as i32), Signal::SIGINT) {
+                warn!("Failed to interrupt yt-dlp: {}", e);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = self.child.start_kill();
+
+        match tokio::time::timeout(STOP_GRACE_PERIOD, self.wait()).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!("yt-dlp did not stop in time, killing it.");
+                self.child.kill().await?;
+                self.wait().await
+            }
+        }
+    }
+}
+
+fn convert_video(video: yt_dlp::model::Video) -> Video {
+    Video {
+        thumbnails: video.thumbnails.iter().map(convert_thumbnail).collect(),
+        formats: video.formats.iter().map(convert_format).collect(),
+        automatic_captions: video
+            .automatic_captions
+            .iter()
+            .map(|(language, captions)| {
+                let captions = captions
+                    .iter()
+                    .map(|caption| Caption {
+                        url: caption.url.clone(),
+                        extension: caption.extension.to_string(),
+                    })
+                    .collect();
+                (language.clone(), captions)
+            })
+            .collect(),
+        id: video.id,
+        title: video.title,
+        description: video.description,
+        channel: video.channel,
+        channel_id: video.channel_id,
+        channel_url: video.channel_url,
+        thumbnail: video.thumbnail,
+        live_status: video.live_status,
+        extractor: video.extractor_info.extractor,
+        extractor_key: video.extractor_info.extractor_key,
+    }
+}
+
+fn convert_format(format: &yt_dlp::model::format::Format) -> Format {
+    let file_size = |size: Option<i64>| size.and_then(|size| u64::try_from(size).ok());
+    Format {
+        format_id: format.format_id.clone(),
+        format_note: format.format_note.clone(),
+        url: format.download_info.url.clone(),
+        container: format
+            .container
+            .as_ref()
+            .map(|container| enum_value(container, "Container")),
+        video_codec: format.codec_info.video_codec.clone(),
+        audio_codec: format.codec_info.audio_codec.clone(),
+        video_ext: Some(enum_value(&format.codec_info.video_ext, "Extension")),
+        audio_ext: Some(enum_value(&format.codec_info.audio_ext, "Extension")),
+        width: format.video_resolution.width,
+        height: format.video_resolution.height,
+        fps: format.video_resolution.fps.map(Into::into),
+        quality: format.quality_info.quality.map(Into::into),
+        filesize: file_size(format.file_info.filesize),
+        filesize_approx: file_size(format.file_info.filesize_approx),
+        total_bitrate: format.rates_info.total_rate.map(Into::into),
+        video_bitrate: format.rates_info.video_rate.map(Into::into),
+        audio_bitrate: format.rates_info.audio_rate.map(Into::into),
+        sample_rate: format.codec_info.asr.map(|sample_rate| sample_rate as u32),
+        audio_channels: format
+            .codec_info
+            .audio_channels
+            .map(|audio_channels| audio_channels as u32),
+        dynamic_range: format.quality_info.dynamic_range.clone(),
+        language: format.language.clone(),
+        has_drm: format.has_drm.unwrap_or(false),
+    }
+}
+
+fn convert_thumbnail(thumbnail: &yt_dlp::model::thumbnail::Thumbnail) -> Thumbnail {
+    Thumbnail {
+        id: thumbnail.id.clone(),
+        url: thumbnail.url.clone(),
+        width: thumbnail.width.map(|width| width as u32),
+        height: thumbnail.height.map(|height| height as u32),
+    }
+}
+
+/// The crate's containers and extensions print as `Name(value)`, only the value is
+/// wanted.
+fn enum_value(value: &impl std::fmt::Display, name: &str) -> String {
+    value
+        .to_string()
+        .replace(&format!("{}(", name), "")
+        .replace(')', "")
+}
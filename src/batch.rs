@@ -0,0 +1,539 @@
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, warn};
+
+use crate::disk_space::check_disk_space;
+use crate::downloader::{
+    auto_select_formats, download_video, get_video_info, sanitize_filename, VideoDownloader,
+};
+use crate::format_policy::FormatPolicy;
+use crate::hook::run_post_download_hook;
+use crate::video_url::parse_video_url;
+use crate::worker::{CancelCheck, EventSender};
+
+#[derive(Debug, Clone)]
+pub struct BatchEntry {
+    pub line: usize,
+    pub url: String,
+    pub format: FormatChoice,
+    pub output_name: Option<String>,
+    pub output_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatChoice {
+    Auto,
+    Ids(Option<String>, Option<String>),
+    Policy(FormatPolicy),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatchStage {
+    Validation,
+    Metadata,
+    Download,
+}
+
+impl Display for BatchStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchStage::Validation => write!(f, "invalid entry"),
+            BatchStage::Metadata => write!(f, "metadata extraction failed"),
+            BatchStage::Download => write!(f, "download failed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchFailure {
+    pub line: usize,
+    pub url: String,
+    pub stage: BatchStage,
+    pub reason: String,
+}
+
+impl Display for BatchFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}: {} - {}: {}",
+            self.line, self.url, self.stage, self.reason
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BatchReport {
+    pub total: usize,
+    pub downloaded: Vec<PathBuf>,
+    pub failed: Vec<BatchFailure>,
+    /// Cancelled before all entries were tried.
+    pub cancelled: bool,
+}
+
+impl Display for BatchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Batch {}: {} of {} downloaded, {} failed.",
+            match self.cancelled {
+                true => "cancelled",
+                false => "finished",
+            },
+            self.downloaded.len(),
+            self.total,
+            self.failed.len()
+        )
+    }
+}
+
+pub fn is_batch_file(path: &Path) -> bool {
+    matches!(
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .as_deref(),
+        Some("txt") | Some("csv")
+    )
+}
+
+pub fn parse_batch_file(path: &Path) -> anyhow::Result<(Vec<BatchEntry>, Vec<BatchFailure>)> {
+    let content = fs::read_to_string(path)?;
+    let is_csv = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false);
+    Ok(parse_batch(&content, is_csv))
+}
+
+pub fn parse_batch(content: &str, is_csv: bool) -> (Vec<BatchEntry>, Vec<BatchFailure>) {
+    let mut entries = Vec::new();
+    let mut failures = Vec::new();
+
+    for (index, raw_line) in content.lines().enumerate() {
+        let line = index + 1;
+        let trimmed_line = raw_line.trim();
+        if trimmed_line.is_empty() || trimmed_line.starts_with('#') {
+            continue;
+        }
+
+        let columns = match is_csv {
+            true => split_csv_line(trimmed_line),
+            false => vec![trimmed_line.to_string()],
+        };
+        let url = columns[0].trim().to_string();
+        if is_csv && entries.is_empty() && failures.is_empty() && url.eq_ignore_ascii_case("url") {
+            continue;
+        }
+
+        let column = |position: usize| {
+            columns
+                .get(position)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        let parsed_entry = parse_video_url(&url)
+            .map_err(|reason| reason.to_string())
+            .and_then(|video_url| {
+                let format = match column(1) {
+                    Some(format) => parse_format_column(&format)?,
+                    None => FormatChoice::Auto,
+                };
+                Ok(BatchEntry {
+                    line,
+                    url: video_url.normalized(),
+                    format,
+                    output_name: column(2),
+                    output_dir: column(3).map(PathBuf::from),
+                })
+            });
+        match parsed_entry {
+            Ok(entry) => entries.push(entry),
+            Err(reason) => failures.push(BatchFailure {
+                line,
+                url,
+                stage: BatchStage::Validation,
+                reason,
+            }),
+        }
+    }
+
+    (entries, failures)
+}
+
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut columns = Vec::new();
+    let mut current_column = String::new();
+    let mut in_quotes = false;
+    let mut characters = line.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if in_quotes && characters.peek() == Some(&'"') => {
+                current_column.push('"');
+                characters.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => columns.push(std::mem::take(&mut current_column)),
+            _ => current_column.push(character),
+        }
+    }
+    columns.push(current_column);
+
+    columns
+}
+
+/// Parses a per-line format column: either format ids such as `137+140` or a format
+/// policy such as `height<=1080,hdr`. `best` falls back to the automatic selection.
+fn parse_format_column(format: &str) -> Result<FormatChoice, String> {
+    if format.eq_ignore_ascii_case("best") {
+        return Ok(FormatChoice::Auto);
+    }
+    if FormatPolicy::looks_like_policy(format) {
+        return FormatPolicy::parse(format).map(FormatChoice::Policy);
+    }
+    let mut format_ids = format
+        .split('+')
+        .map(|format_id| format_id.trim().to_string())
+        .filter(|format_id| !format_id.is_empty());
+    Ok(FormatChoice::Ids(format_ids.next(), format_ids.next()))
+}
+
+pub async fn run_batch(
+    video_downloader: &VideoDownloader,
+    entries: Vec<BatchEntry>,
+    invalid_entries: Vec<BatchFailure>,
+    events: &EventSender,
+    refresh: bool,
+    cancel_check: &mut CancelCheck<'_>,
+) -> BatchReport {
+    let mut report = BatchReport {
+        total: entries.len() + invalid_entries.len(),
+        downloaded: Vec::new(),
+        failed: invalid_entries,
+        cancelled: false,
+    };
+    let entry_count = entries.len();
+
+    for (position, entry) in entries.into_iter().enumerate() {
+        if cancel_check.is_cancelled() {
+            debug!("Batch cancelled before line {}", entry.line);
+            report.cancelled = true;
+            break;
+        }
+        debug!(
+            "Batch entry {} (line {}): {}",
+            position + 1,
+            entry.line,
+            entry.url
+        );
+        events.status(format!(
+            "Batch [{}/{}]: fetching info for {}",
+            position + 1,
+            entry_count,
+            entry.url
+        ));
+
+        let mut job_downloader = video_downloader.clone();
+        job_downloader.video_url = entry.url.clone();
+
+        let video_info = match get_video_info(&mut job_downloader, events, refresh).await {
+            Ok(video_info) => video_info,
+            Err(e) => {
+                warn!("Batch entry on line {} failed: {}", entry.line, e);
+                report.failed.push(BatchFailure {
+                    line: entry.line,
+                    url: entry.url,
+                    stage: BatchStage::Metadata,
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        job_downloader.output_dir_override = entry.output_dir.clone();
+        if let Some(output_name) = &entry.output_name {
+            job_downloader.output_file_name = sanitize_filename(output_name).trim().to_string();
+        }
+
+        let (video_format_id, audio_format_id) = match &entry.format {
+            FormatChoice::Auto => auto_select_formats(&video_info),
+            FormatChoice::Ids(video_format_id, audio_format_id) => {
+                (video_format_id.clone(), audio_format_id.clone())
+            }
+            FormatChoice::Policy(policy) => policy.select_formats(&video_info),
+        };
+        job_downloader.selected_video_format = video_format_id;
+        job_downloader.selected_audio_format = audio_format_id;
+        job_downloader.selected_audio_video_format = None;
+
+        if let Ok(Some(shortage)) = check_disk_space(&job_downloader) {
+            warn!("Batch entry on line {} skipped: {}", entry.line, shortage);
+            report.failed.push(BatchFailure {
+                line: entry.line,
+                url: entry.url,
+                stage: BatchStage::Download,
+                reason: shortage.to_string(),
+            });
+            continue;
+        }
+
+        events.status(format!(
+            "Batch [{}/{}]: downloading {}",
+            position + 1,
+            entry_count,
+            video_info.title
+        ));
+        match download_video(&mut job_downloader, events).await {
+            Ok(path) => {
+                if let Some(post_download_hook) = &job_downloader.post_download_hook {
+                    let hook_status = match run_post_download_hook(
+                        post_download_hook,
+                        &path,
+                        &video_info.id,
+                        &video_info.title,
+                    )
+                    .await
+                    {
+                        Ok(outcome) => outcome.to_string(),
+                        Err(e) => format!("Post-download hook failed: {:#}", e),
+                    };
+                    events.status(format!(
+                        "Batch [{}/{}]: {}",
+                        position + 1,
+                        entry_count,
+                        hook_status
+                    ));
+                }
+                report.downloaded.push(path)
+            }
+            Err(e) => {
+                warn!("Batch entry on line {} failed: {}", entry.line, e);
+                report.failed.push(BatchFailure {
+                    line: entry.line,
+                    url: entry.url,
+                    stage: BatchStage::Download,
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    use tokio::sync::{broadcast, mpsc};
+
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use crate::worker::{Job, WorkerCommand};
+
+    const BATCH_CSV: &str = r#"url,format,name,dir
+# Comments and blank lines are skipped.
+
+https://youtu.be/dQw4w9WgXcQ,137+140,"My ""Best"" Video",videos
+"https://www.youtube.com/watch?v=9bZkp7q19f0&t=10","height<=1080,hdr",,
+ftp://example.com/video,,,
+https://www.youtube.com/watch?v=jNQXAC9IVRw,best
+https://www.youtube.com/watch?v=kJQP7kiw5Fk,height<=abc
+"#;
+
+    #[test]
+    fn parses_csv_columns() {
+        let (entries, _) = parse_batch(BATCH_CSV, true);
+
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.line, entry.url.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (4, "https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+                (5, "https://www.youtube.com/watch?v=9bZkp7q19f0"),
+                (7, "https://www.youtube.com/watch?v=jNQXAC9IVRw"),
+            ]
+        );
+        assert_eq!(
+            entries[0].format,
+            FormatChoice::Ids(Some("137".to_string()), Some("140".to_string()))
+        );
+        assert_eq!(entries[0].output_name.as_deref(), Some("My \"Best\" Video"));
+        assert_eq!(entries[0].output_dir, Some(PathBuf::from("videos")));
+        assert_eq!(
+            entries[1].format,
+            FormatChoice::Policy(FormatPolicy::parse("height<=1080,hdr").unwrap())
+        );
+        assert_eq!(entries[1].output_name, None);
+        assert_eq!(entries[1].output_dir, None);
+        assert_eq!(entries[2].format, FormatChoice::Auto);
+    }
+
+    #[test]
+    fn reports_invalid_lines_with_their_line_number() {
+        let (_, failures) = parse_batch(BATCH_CSV, true);
+
+        let summary: Vec<_> = failures
+            .iter()
+            .map(|failure| (failure.line, failure.url.as_str(), failure.stage))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (6, "ftp://example.com/video", BatchStage::Validation),
+                (
+                    8,
+                    "https://www.youtube.com/watch?v=kJQP7kiw5Fk",
+                    BatchStage::Validation
+                ),
+            ]
+        );
+        assert_eq!(
+            failures[0].to_string(),
+            "line 6: ftp://example.com/video - invalid entry: Not a valid URL: unsupported scheme ftp"
+        );
+        assert_eq!(
+            failures[1].reason,
+            "invalid number in format policy clause 'height<=abc'"
+        );
+    }
+
+    #[test]
+    fn header_is_only_skipped_in_the_first_csv_line() {
+        let (entries, failures) = parse_batch("https://youtu.be/dQw4w9WgXcQ\nurl\n", true);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].line, 2);
+
+        // Text files have no header, and commas are part of the URL.
+        let (entries, failures) = parse_batch("url\n  https://youtu.be/dQw4w9WgXcQ  \n", false);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].line, 2);
+        assert_eq!(entries[0].format, FormatChoice::Auto);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].line, 1);
+    }
+
+    #[test]
+    fn splits_quoted_csv_fields() {
+        let cases = [
+            ("a,b,c", vec!["a", "b", "c"]),
+            (r#"a,"b,c","d ""e""","#, vec!["a", "b,c", "d \"e\"", ""]),
+            (r#""""#, vec![""]),
+            ("", vec![""]),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(split_csv_line(line), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn parses_format_columns() {
+        let ids = |video: Option<&str>, audio: Option<&str>| {
+            FormatChoice::Ids(video.map(str::to_string), audio.map(str::to_string))
+        };
+        let cases = [
+            ("best", FormatChoice::Auto),
+            ("BEST", FormatChoice::Auto),
+            ("22", ids(Some("22"), None)),
+            (" 137 + 140 ", ids(Some("137"), Some("140"))),
+            (
+                "hdr",
+                FormatChoice::Policy(FormatPolicy::parse("hdr").unwrap()),
+            ),
+            (
+                "vcodec=av01|vp9",
+                FormatChoice::Policy(FormatPolicy::parse("vcodec=av01|vp9").unwrap()),
+            ),
+        ];
+        for (format, expected) in cases {
+            assert_eq!(parse_format_column(format), Ok(expected), "{}", format);
+        }
+        assert!(parse_format_column("fps>=fast").is_err());
+    }
+
+    fn entry(line: usize, url: &str) -> BatchEntry {
+        BatchEntry {
+            line,
+            url: url.to_string(),
+            format: FormatChoice::Auto,
+            output_name: None,
+            output_dir: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn stops_between_entries_once_cancelled() {
+        let dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-batch-cancel-{}",
+            std::process::id()
+        ));
+        let mut video_downloader =
+            VideoDownloader::new("libs", &dir.join("output").display().to_string(), "")
+                .unwrap()
+                .with_backend(Arc::new(FakeBackend::default()));
+        video_downloader.metadata_cache.cache_dir = dir.join("cache");
+        video_downloader.retry_policy.max_attempts = 1;
+        let (events_tx, _events_rx) = broadcast::channel(16);
+        let events = EventSender::new(1, events_tx.clone());
+        let (commands_tx, mut commands) = mpsc::unbounded_channel();
+        let mut deferred = VecDeque::new();
+        let entries = vec![
+            entry(1, "https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            entry(2, "https://www.youtube.com/watch?v=9bZkp7q19f0"),
+        ];
+
+        let mut cancel_check = CancelCheck::new(&mut commands, &mut deferred, &events_tx, 1);
+        let report = run_batch(
+            &video_downloader,
+            entries.clone(),
+            Vec::new(),
+            &events,
+            true,
+            &mut cancel_check,
+        )
+        .await;
+        assert!(!report.cancelled);
+        assert_eq!(
+            report.failed.len(),
+            2,
+            "unknown videos fail, the batch goes on"
+        );
+
+        for (id, command) in [
+            (2, WorkerCommand::FetchThumbnail),
+            (3, WorkerCommand::Cancel(1)),
+        ] {
+            commands_tx.send(Job { id, command }).unwrap();
+        }
+        let mut cancel_check = CancelCheck::new(&mut commands, &mut deferred, &events_tx, 1);
+        let report = run_batch(
+            &video_downloader,
+            entries,
+            Vec::new(),
+            &events,
+            true,
+            &mut cancel_check,
+        )
+        .await;
+
+        assert!(report.cancelled);
+        assert_eq!(report.total, 2);
+        assert!(report.downloaded.is_empty() && report.failed.is_empty());
+        assert_eq!(
+            report.to_string(),
+            "Batch cancelled: 0 of 2 downloaded, 0 failed."
+        );
+        assert_eq!(deferred.len(), 1, "other commands wait for the batch");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,246 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::backend::Video;
+
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Stream URLs that expire within this window are treated as already expired,
+// so a download started from cached info does not fail halfway through.
+const STREAM_EXPIRY_MARGIN: Duration = Duration::from_secs(30 * 60);
+
+// Bumped when the cached video model changes, so older entries are fetched again.
+const CACHE_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct CachedVideo {
+    #[serde(default)]
+    version: u32,
+    fetched_at: u64,
+    video: Video,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataCache {
+    pub cache_dir: PathBuf,
+    pub ttl: Duration,
+}
+
+impl Default for MetadataCache {
+    fn default() -> Self {
+        Self::new("cache/metadata", DEFAULT_CACHE_TTL)
+    }
+}
+
+impl MetadataCache {
+    pub fn new(cache_dir: &str, ttl: Duration) -> Self {
+        Self {
+            cache_dir: PathBuf::from(cache_dir),
+            ttl,
+        }
+    }
+
+    fn entry_path(&self, video_id: &str) -> PathBuf {
+        self.cache_dir.join(format!("{}.json", video_id))
+    }
+
+    pub fn get(&self, video_id: &str) -> Option<Video> {
+        self.get_at(video_id, unix_now())
+    }
+
+    /// [`MetadataCache::get`] at `now`, in seconds since the Unix epoch.
+    fn get_at(&self, video_id: &str, now: u64) -> Option<Video> {
+        let entry_path = self.entry_path(video_id);
+        let content = fs::read_to_string(&entry_path).ok()?;
+        let cached_video: CachedVideo = match serde_json::from_str(&content) {
+            Ok(cached_video) => cached_video,
+            Err(e) => {
+                warn!(
+                    "Discarding unreadable cache entry {}: {}",
+                    entry_path.display(),
+                    e
+                );
+                self.invalidate(video_id);
+                return None;
+            }
+        };
+
+        if cached_video.version != CACHE_VERSION {
+            debug!("Cache entry for {} has an old format", video_id);
+            self.invalidate(video_id);
+            return None;
+        }
+
+        if now.saturating_sub(cached_video.fetched_at) > self.ttl.as_secs() {
+            debug!("Cache entry for {} is older than the TTL", video_id);
+            self.invalidate(video_id);
+            return None;
+        }
+
+        if streams_expired(&cached_video.video, now) {
+            debug!("Cached stream URLs for {} have expired", video_id);
+            self.invalidate(video_id);
+            return None;
+        }
+
+        Some(cached_video.video)
+    }
+
+    pub fn put(&self, video: &Video) -> anyhow::Result<()> {
+        self.put_at(video, unix_now())
+    }
+
+    fn put_at(&self, video: &Video, fetched_at: u64) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.cache_dir)?;
+        let cached_video = CachedVideo {
+            version: CACHE_VERSION,
+            fetched_at,
+            video: video.clone(),
+        };
+        fs::write(
+            self.entry_path(&video.id),
+            serde_json::to_string(&cached_video)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn invalidate(&self, video_id: &str) {
+        let _ = fs::remove_file(self.entry_path(video_id));
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// YouTube stream URLs carry their expiry time as an `expire` query parameter.
+fn streams_expired(video: &Video, now: u64) -> bool {
+    video
+        .formats
+        .iter()
+        .filter_map(|format| Url::parse(&format.url).ok())
+        .filter_map(|stream_url| {
+            stream_url
+                .query_pairs()
+                .find(|(key, _)| key == "expire")
+                .and_then(|(_, value)| value.parse::<u64>().ok())
+        })
+        .any(|expire| expire <= now + STREAM_EXPIRY_MARGIN.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Format;
+
+    const VIDEO_ID: &str = "dQw4w9WgXcQ";
+    const NOW: u64 = 1_700_000_000;
+
+    fn test_cache(name: &str) -> MetadataCache {
+        let cache_dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-cache-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&cache_dir);
+        MetadataCache {
+            cache_dir,
+            ttl: Duration::from_secs(60 * 60),
+        }
+    }
+
+    fn video_with_stream(url: &str) -> Video {
+        Video {
+            id: VIDEO_ID.to_string(),
+            title: "Cached Video".to_string(),
+            formats: vec![Format {
+                format_id: "137".to_string(),
+                url: url.to_string(),
+                ..Format::default()
+            }],
+            ..Video::default()
+        }
+    }
+
+    #[test]
+    fn entries_are_served_within_the_ttl() {
+        let cache = test_cache("ttl-hit");
+        let video = video_with_stream("https://example.invalid/stream");
+        cache.put_at(&video, NOW - 59 * 60).unwrap();
+
+        assert_eq!(cache.get_at(VIDEO_ID, NOW), Some(video));
+        let _ = fs::remove_dir_all(&cache.cache_dir);
+    }
+
+    #[test]
+    fn entries_older_than_the_ttl_are_dropped() {
+        let cache = test_cache("ttl-miss");
+        cache
+            .put_at(
+                &video_with_stream("https://example.invalid/stream"),
+                NOW - 61 * 60,
+            )
+            .unwrap();
+
+        assert_eq!(cache.get_at(VIDEO_ID, NOW), None);
+        assert!(!cache.entry_path(VIDEO_ID).exists());
+        let _ = fs::remove_dir_all(&cache.cache_dir);
+    }
+
+    #[test]
+    fn entries_with_expired_streams_are_dropped() {
+        let cache = test_cache("expired-streams");
+        let stream_url = format!("https://example.invalid/videoplayback?expire={}", NOW - 1);
+        cache.put_at(&video_with_stream(&stream_url), NOW).unwrap();
+
+        assert_eq!(cache.get_at(VIDEO_ID, NOW), None);
+        assert!(!cache.entry_path(VIDEO_ID).exists());
+        let _ = fs::remove_dir_all(&cache.cache_dir);
+    }
+
+    #[test]
+    fn entries_of_an_older_format_are_dropped() {
+        let cache = test_cache("old-version");
+        fs::create_dir_all(&cache.cache_dir).unwrap();
+        let cached_video = CachedVideo {
+            version: CACHE_VERSION - 1,
+            fetched_at: NOW,
+            video: video_with_stream("https://example.invalid/stream"),
+        };
+        fs::write(
+            cache.entry_path(VIDEO_ID),
+            serde_json::to_string(&cached_video).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(cache.get_at(VIDEO_ID, NOW), None);
+        let _ = fs::remove_dir_all(&cache.cache_dir);
+    }
+
+    #[test]
+    fn streams_expire_within_the_margin() {
+        let video = |expire: u64| {
+            video_with_stream(&format!(
+                "https://example.invalid/videoplayback?expire={}&id=1",
+                expire
+            ))
+        };
+        let margin = STREAM_EXPIRY_MARGIN.as_secs();
+
+        assert!(streams_expired(&video(NOW - 60), NOW));
+        assert!(streams_expired(&video(NOW + margin), NOW));
+        assert!(!streams_expired(&video(NOW + margin + 1), NOW));
+        assert!(!streams_expired(
+            &video_with_stream("https://example.invalid/stream"),
+            NOW
+        ));
+    }
+}
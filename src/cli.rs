@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+
+use crate::batch::BatchReport;
+use crate::downloader::VideoDownloader;
+use crate::native_host::manifest;
+use crate::notifications::NotificationSettings;
+use crate::settings::Settings;
+use crate::subscriptions::SUBSCRIPTIONS_FILE;
+use crate::temp_dir::cleanup_orphaned_temp_dirs;
+use crate::worker::{self, JobEvents, WorkerCommand, WorkerEvent, WorkerHandle};
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Download videos with yt-dlp",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Video URLs to queue in the app
+    #[arg(value_name = "URL")]
+    pub urls: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Download every URL listed in a text file (one per line) or a CSV file
+    /// with `url,format,output_name,output_dir` columns. Lines starting with `#` are ignored.
+    Batch {
+        file: PathBuf,
+        #[arg(long, default_value = "libs")]
+        executables_dir: String,
+        /// Defaults to the output dir from the settings file
+        #[arg(long)]
+        output_dir: Option<String>,
+        /// Ignore cached video info and fetch it again
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Periodically check subscribed channels and download their new uploads.
+    /// Subscriptions are managed in the app and stored in `subscriptions.json`.
+    Watch {
+        #[arg(long, default_value_t = 60)]
+        interval_minutes: u64,
+        /// Check once and exit instead of running until interrupted
+        #[arg(long)]
+        once: bool,
+        #[arg(long, default_value = "libs")]
+        executables_dir: String,
+        /// Defaults to the output dir from the settings file
+        #[arg(long)]
+        output_dir: Option<String>,
+    },
+    /// Print the native-messaging host manifest for the browser extension, to be
+    /// installed as `com.youtube_downloader.host.json` in the browser's host directory.
+    NativeHostManifest {
+        extension_id: String,
+        /// Write a Firefox manifest instead of a Chrome/Chromium one
+        #[arg(long)]
+        firefox: bool,
+    },
+}
+
+pub fn run_command(command: Command) -> i32 {
+    match command {
+        Command::Batch {
+            file,
+            executables_dir,
+            output_dir,
+            refresh,
+        } => match run_batch_command(&file, &executables_dir, output_dir.as_deref(), refresh) {
+            Ok(report) => {
+                println!("{}", report);
+                for failure in &report.failed {
+                    println!("  {}", failure);
+                }
+                match report.failed.is_empty() {
+                    true => 0,
+                    false => 1,
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                1
+            }
+        },
+        Command::Watch {
+            interval_minutes,
+            once,
+            executables_dir,
+            output_dir,
+        } => match run_watch_command(
+            Duration::from_secs(interval_minutes.max(1) * 60),
+            once,
+            &executables_dir,
+            output_dir.as_deref(),
+        ) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("Failed to watch subscriptions: {}", e);
+                1
+            }
+        },
+        Command::NativeHostManifest {
+            extension_id,
+            firefox,
+        } => match manifest(&extension_id, firefox) {
+            Ok(manifest) => {
+                println!("{}", manifest);
+                0
+            }
+            Err(e) => {
+                eprintln!("Failed to create native host manifest: {}", e);
+                1
+            }
+        },
+    }
+}
+
+pub fn run_batch_command(
+    file: &Path,
+    executables_dir: &str,
+    output_dir: Option<&str>,
+    refresh: bool,
+) -> anyhow::Result<BatchReport> {
+    let worker = cli_worker(executables_dir, output_dir)?;
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async move {
+            let mut events = worker.start(WorkerCommand::ImportBatch {
+                path: file.to_path_buf(),
+                refresh,
+            });
+            loop {
+                match print_status_until_result(&mut events).await? {
+                    WorkerEvent::BatchFinished(report) => return Ok(report),
+                    WorkerEvent::Failed(reason) => anyhow::bail!(reason),
+                    _ => {}
+                }
+            }
+        })
+}
+
+pub fn run_watch_command(
+    interval: Duration,
+    once: bool,
+    executables_dir: &str,
+    output_dir: Option<&str>,
+) -> anyhow::Result<()> {
+    let worker = cli_worker(executables_dir, output_dir)?;
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async move {
+            loop {
+                let mut events = worker.start(WorkerCommand::CheckSubscriptions(PathBuf::from(
+                    SUBSCRIPTIONS_FILE,
+                )));
+                let report = loop {
+                    if let WorkerEvent::SubscriptionsChecked(report) =
+                        print_status_until_result(&mut events).await?
+                    {
+                        break report;
+                    }
+                };
+                println!("{}", report);
+                for failure in &report.failed {
+                    println!("  {}", failure);
+                }
+                if once {
+                    return Ok(());
+                }
+                println!("Next check in {} minute(s).", interval.as_secs() / 60);
+                tokio::time::sleep(interval).await;
+            }
+        })
+}
+
+/// Prints the status messages of the job, which is all the progress the CLI shows,
+/// and returns its next other event.
+async fn print_status_until_result(events: &mut JobEvents) -> anyhow::Result<WorkerEvent> {
+    loop {
+        match events.next().await {
+            Some(WorkerEvent::Status(status_message)) => println!("{}", status_message),
+            Some(WorkerEvent::Progress { .. }) => {}
+            Some(event) => return Ok(event),
+            None => anyhow::bail!("Worker stopped"),
+        }
+    }
+}
+
+/// The CLI runs its jobs on the same worker as the app, without desktop notifications.
+fn cli_worker(executables_dir: &str, output_dir: Option<&str>) -> anyhow::Result<WorkerHandle> {
+    Ok(worker::spawn(
+        cli_video_downloader(executables_dir, output_dir)?,
+        NotificationSettings {
+            enabled: false,
+            ..NotificationSettings::default()
+        },
+    ))
+}
+
+fn cli_video_downloader(
+    executables_dir: &str,
+    output_dir: Option<&str>,
+) -> anyhow::Result<VideoDownloader> {
+    let settings = Settings::load();
+    cleanup_orphaned_temp_dirs(&settings.temp_dir);
+    let output_dir = output_dir
+        .map(str::to_string)
+        .unwrap_or_else(|| settings.output_dir.display().to_string());
+    let mut video_downloader = VideoDownloader::new(executables_dir, &output_dir, "")?;
+    video_downloader.organize_by_extractor = settings.organize_by_extractor;
+    video_downloader.temp_root = settings.temp_dir;
+    video_downloader.post_download_hook = settings.post_download_hook;
+    Ok(video_downloader)
+}
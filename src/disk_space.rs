@@ -0,0 +1,285 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use tracing::debug;
+
+use crate::downloader::VideoDownloader;
+use crate::file_size::{format_bytes, FileSize};
+use crate::ui::state::FormatListItem;
+
+// Separate video and audio streams and the file they are muxed into all sit in the
+// job's temp dir at the same time.
+const MUXING_FACTOR: u64 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskSpaceShortage {
+    /// The directory lacking space: the temp dir or the download's target dir.
+    pub dir: PathBuf,
+    pub required: FileSize,
+    pub available: u64,
+}
+
+impl Display for DiskSpaceShortage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Not enough free space in {}: the download needs {} but only {} is available.",
+            self.dir.display(),
+            self.required,
+            format_bytes(self.available)
+        )
+    }
+}
+
+/// Space a download needs while it runs in the temp dir and for the finished file in
+/// the target dir.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequiredSpace {
+    pub temp: FileSize,
+    pub target: FileSize,
+}
+
+/// Space needed for the selected formats, including room for muxing separate streams.
+/// Returns `None` when none of the selected formats has a known or estimated size.
+pub fn required_space(video_downloader: &VideoDownloader) -> Option<RequiredSpace> {
+    let estimated_size = |format_id: &String| {
+        video_downloader
+            .video_info
+            .formats
+            .iter()
+            .find(|format| &format.format_id == format_id)
+            .and_then(|format| {
+                FormatListItem::for_video(format, &video_downloader.video_info.formats)
+                    .estimated_size()
+            })
+    };
+
+    if let Some(format_id) = &video_downloader.selected_audio_video_format {
+        return estimated_size(format_id).map(|size| RequiredSpace {
+            temp: size,
+            target: size,
+        });
+    }
+
+    let stream_sizes: Vec<FileSize> = [
+        &video_downloader.selected_video_format,
+        &video_downloader.selected_audio_format,
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(estimated_size)
+    .collect();
+    match stream_sizes.is_empty() {
+        true => None,
+        false => {
+            let stream_size: FileSize = stream_sizes.into_iter().sum();
+            Some(RequiredSpace {
+                temp: FileSize {
+                    bytes: stream_size.bytes * MUXING_FACTOR,
+                    approximate: stream_size.approximate,
+                },
+                target: stream_size,
+            })
+        }
+    }
+}
+
+/// Closest existing ancestor of `path`, which may not have been created yet.
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+/// Free space on the filesystem holding `path`, looking at the closest existing
+/// ancestor when the directory has not been created yet.
+pub fn available_space(path: &Path) -> anyhow::Result<u64> {
+    let existing_path = existing_ancestor(path);
+    fs2::available_space(existing_path)
+        .with_context(|| format!("Failed to query free space of {}", existing_path.display()))
+}
+
+#[cfg(unix)]
+fn same_device(left: &Path, right: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = |path: &Path| {
+        std::fs::metadata(existing_ancestor(path))
+            .map(|metadata| metadata.dev())
+            .ok()
+    };
+    match (device(left), device(right)) {
+        (Some(left), Some(right)) => left == right,
+        // Assume the worst when either device is unknown.
+        _ => true,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_device(left: &Path, right: &Path) -> bool {
+    use std::path::Component;
+
+    let prefix = |path: &Path| {
+        std::fs::canonicalize(existing_ancestor(path))
+            .ok()
+            .and_then(|path| match path.components().next() {
+                Some(Component::Prefix(prefix)) => Some(prefix.as_os_str().to_owned()),
+                _ => None,
+            })
+    };
+    match (prefix(left), prefix(right)) {
+        (Some(left), Some(right)) => left == right,
+        _ => true,
+    }
+}
+
+fn shortage(
+    dir: PathBuf,
+    required: FileSize,
+    available_space: &impl Fn(&Path) -> anyhow::Result<u64>,
+) -> anyhow::Result<Option<DiskSpaceShortage>> {
+    let available = available_space(&dir)?;
+    debug!(
+        "Disk space check: {} required, {} available in {}",
+        required,
+        format_bytes(available),
+        dir.display()
+    );
+    match required.bytes > available {
+        true => Ok(Some(DiskSpaceShortage {
+            dir,
+            required,
+            available,
+        })),
+        false => Ok(None),
+    }
+}
+
+/// Checks the temp dir has room for the streams and the merged file and the target dir
+/// has room for the finished file. When both live on the same device the finished file
+/// is renamed into place, so the download needs only the larger of the two there.
+pub fn check_disk_space(
+    video_downloader: &VideoDownloader,
+) -> anyhow::Result<Option<DiskSpaceShortage>> {
+    check_disk_space_with(video_downloader, available_space)
+}
+
+/// [`check_disk_space`] with the free space of a directory given by `available_space`.
+fn check_disk_space_with(
+    video_downloader: &VideoDownloader,
+    available_space: impl Fn(&Path) -> anyhow::Result<u64>,
+) -> anyhow::Result<Option<DiskSpaceShortage>> {
+    let required = match required_space(video_downloader) {
+        Some(required) => required,
+        None => {
+            debug!("Sizes of the selected formats are unknown, skipping disk space check.");
+            return Ok(None);
+        }
+    };
+    let target_dir = video_downloader.target_dir();
+    let temp_root = &video_downloader.temp_root;
+
+    if same_device(temp_root, &target_dir) {
+        let larger = match required.temp.bytes >= required.target.bytes {
+            true => required.temp,
+            false => required.target,
+        };
+        return shortage(target_dir, larger, &available_space);
+    }
+    if let Some(temp_shortage) = shortage(temp_root.clone(), required.temp, &available_space)? {
+        return Ok(Some(temp_shortage));
+    }
+    shortage(target_dir, required.target, &available_space)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Format;
+
+    fn downloader_with_sizes(sizes: &[(&str, u64)]) -> VideoDownloader {
+        let mut video_downloader = VideoDownloader::new("libs", "output", "").unwrap();
+        for (format_id, size) in sizes {
+            video_downloader.video_info.formats.push(Format {
+                format_id: format_id.to_string(),
+                url: "https://example.invalid/stream".to_string(),
+                filesize: Some(*size),
+                ..Format::default()
+            });
+        }
+        video_downloader
+    }
+
+    #[test]
+    fn separate_streams_need_room_to_merge() {
+        let mut video_downloader = downloader_with_sizes(&[("137", 300), ("140", 100)]);
+        video_downloader.selected_video_format = Some("137".to_string());
+        video_downloader.selected_audio_format = Some("140".to_string());
+
+        assert_eq!(
+            required_space(&video_downloader),
+            Some(RequiredSpace {
+                temp: FileSize::exact(800),
+                target: FileSize::exact(400),
+            })
+        );
+    }
+
+    #[test]
+    fn progressive_formats_are_not_merged() {
+        let mut video_downloader = downloader_with_sizes(&[("18", 500)]);
+        video_downloader.selected_audio_video_format = Some("18".to_string());
+
+        assert_eq!(
+            required_space(&video_downloader),
+            Some(RequiredSpace {
+                temp: FileSize::exact(500),
+                target: FileSize::exact(500),
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_sizes_skip_the_check() {
+        let mut video_downloader = downloader_with_sizes(&[]);
+        video_downloader.selected_video_format = Some("137".to_string());
+
+        assert_eq!(required_space(&video_downloader), None);
+        assert_eq!(check_disk_space(&video_downloader).unwrap(), None);
+    }
+
+    fn downloader_in_temp_dir(name: &str) -> VideoDownloader {
+        let dir = std::env::temp_dir();
+        let mut video_downloader = downloader_with_sizes(&[("137", 300), ("140", 100)]);
+        video_downloader.selected_video_format = Some("137".to_string());
+        video_downloader.selected_audio_format = Some("140".to_string());
+        video_downloader.output_dir = dir.join(format!("youtube_downloader-{}-output", name));
+        video_downloader.temp_root = dir.join(format!("youtube_downloader-{}-temp", name));
+        video_downloader
+    }
+
+    #[test]
+    fn same_device_needs_the_larger_amount() {
+        // The merged file is renamed into place, so it never exists twice.
+        let video_downloader = downloader_in_temp_dir("disk-space-fits");
+
+        let shortage = check_disk_space_with(&video_downloader, |_| Ok(800)).unwrap();
+
+        assert_eq!(shortage, None);
+    }
+
+    #[test]
+    fn same_device_reports_a_shortage() {
+        let video_downloader = downloader_in_temp_dir("disk-space-short");
+
+        let shortage = check_disk_space_with(&video_downloader, |_| Ok(799))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(shortage.dir, video_downloader.target_dir());
+        assert_eq!(shortage.required, FileSize::exact(800));
+        assert_eq!(shortage.available, 799);
+    }
+}
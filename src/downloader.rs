@@ -1,101 +1,100 @@
-use std::collections::HashMap;
 use std::fs;
+use std::sync::{Arc, OnceLock};
 
-use super::ui::message::Message as UIMessage;
+use anyhow::Context;
 use std::path::PathBuf;
-use tracing::{debug, error, info};
-use yt_dlp::model::ExtractorInfo;
-use yt_dlp::model::Version;
-use yt_dlp::model::Video;
-use yt_dlp::{fetcher::deps::Libraries, Youtube};
+use tracing::{debug, error};
 
-use crate::ui::message::Message;
+use crate::backend::yt_dlp_backend::YtDlpBackend;
+use crate::backend::{Format, MediaBackend, Video};
+use crate::cache::MetadataCache;
+use crate::live::LiveStatus;
+use crate::retry::{RetryAttempt, RetryPolicy};
+use crate::settings::DEFAULT_ORGANIZE_BY_EXTRACTOR;
+use crate::temp_dir::{move_into_place, JobTempDir};
+use crate::thumbnail::{convert_thumbnail, url_extension, ThumbnailRequest};
+use crate::ui::state::FormatListItem;
+use crate::video_url::parse_video_url;
+use crate::worker::EventSender;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct VideoDownloader {
     pub executables_dir: PathBuf,
     pub output_dir: PathBuf,
+    pub output_dir_override: Option<PathBuf>,
+    /// Saves downloads into a subfolder per site, e.g. `output/Youtube`.
+    pub organize_by_extractor: bool,
+    pub temp_root: PathBuf,
+    pub post_download_hook: Option<String>,
     pub video_url: String,
-    yt_dlp_executable_path: PathBuf,
-    ffmpeg_executable_path: PathBuf,
-    libraries: Libraries,
-    fetcher: Youtube,
+    pub yt_dlp_executable_path: PathBuf,
+    pub ffmpeg_executable_path: PathBuf,
+    pub backend: Arc<dyn MediaBackend>,
     pub output_file_name: String,
     pub video_info: Video,
     pub thumbnail_path: Option<PathBuf>,
     pub video_path: Option<PathBuf>,
     pub selected_audio_format: Option<String>,
     pub selected_video_format: Option<String>,
-}
-
-impl Default for VideoDownloader {
-    fn default() -> Self {
-        Self::new(
-            "libs",
-            "output",
-            "https://www.youtube.com/watch?v=1A6uPztchXk",
-        )
-    }
+    pub selected_audio_video_format: Option<String>,
+    pub retry_policy: RetryPolicy,
+    pub metadata_cache: MetadataCache,
 }
 
 impl VideoDownloader {
-    pub fn new(executables_dir: &str, output_dir: &str, video_url: &str) -> Self {
+    /// The output dir is created when something is first saved to it.
+    pub fn new(executables_dir: &str, output_dir: &str, video_url: &str) -> anyhow::Result<Self> {
         let executables_dir_path_buf = PathBuf::from(executables_dir);
         let output_dir_path_buf = PathBuf::from(output_dir);
         let yt_dlp_executable_path_buf = executables_dir_path_buf.join("yt-dlp");
         let ffmpeg_executable_path_buf = executables_dir_path_buf.join("ffmpeg");
-        let libraries = Libraries::new(
+        let backend = YtDlpBackend::new(
             yt_dlp_executable_path_buf.clone(),
             ffmpeg_executable_path_buf.clone(),
-        );
-        let fetcher = Youtube::new(libraries.clone(), output_dir_path_buf.clone()).unwrap();
-        Self {
+        )
+        .context("Failed to set up the yt-dlp backend")?;
+        Ok(Self {
             executables_dir: executables_dir_path_buf,
             output_dir: output_dir_path_buf,
+            output_dir_override: None,
+            organize_by_extractor: DEFAULT_ORGANIZE_BY_EXTRACTOR,
+            temp_root: PathBuf::from("temp"),
+            post_download_hook: None,
             video_url: video_url.to_string(),
             yt_dlp_executable_path: yt_dlp_executable_path_buf,
             ffmpeg_executable_path: ffmpeg_executable_path_buf,
-            libraries: libraries,
-            fetcher: fetcher,
+            backend: Arc::new(backend),
             output_file_name: String::new(),
-            video_info: Video {
-                id: String::new(),
-                title: String::new(),
-                channel: String::new(),
-                channel_id: String::new(),
-                description: String::new(),
-                formats: Vec::new(),
-                automatic_captions: HashMap::new(),
-                thumbnail: String::new(),
-                availability: String::new(),
-                upload_date: 0,
-                view_count: 0,
-                like_count: Some(0),
-                comment_count: Some(0),
-                channel_url: String::new(),
-                channel_follower_count: Some(0),
-                thumbnails: Vec::new(),
-                tags: Vec::new(),
-                categories: Vec::new(),
-                age_limit: 0,
-                has_drm: None,
-                live_status: String::new(),
-                playable_in_embed: true,
-                extractor_info: ExtractorInfo {
-                    extractor: String::new(),
-                    extractor_key: String::new(),
-                },
-                version: Version {
-                    version: String::new(),
-                    current_git_head: Some(String::new()),
-                    release_git_head: Some(String::new()),
-                    repository: String::new(),
-                },
-            },
+            video_info: Video::default(),
             thumbnail_path: None,
             video_path: None,
             selected_audio_format: None,
             selected_video_format: None,
+            selected_audio_video_format: None,
+            retry_policy: RetryPolicy::default(),
+            metadata_cache: MetadataCache::default(),
+        })
+    }
+
+    pub fn set_output_dir(&mut self, output_dir: PathBuf) {
+        self.output_dir = output_dir;
+    }
+
+    pub fn with_backend(mut self, backend: Arc<dyn MediaBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Directory the current download is saved to: the per-download override if one
+    /// was chosen, otherwise the default output dir or its subfolder for the site.
+    pub fn target_dir(&self) -> PathBuf {
+        if let Some(output_dir_override) = &self.output_dir_override {
+            return output_dir_override.clone();
+        }
+        let extractor_dir = sanitize_filename(&self.video_info.extractor_key);
+        match self.organize_by_extractor && !extractor_dir.trim().is_empty() {
+            true => self.output_dir.join(extractor_dir.trim()),
+            false => self.output_dir.clone(),
         }
     }
 }
@@ -104,26 +103,83 @@ pub fn change_video_url(video_downloader: &mut VideoDownloader, video_url: Strin
     video_downloader.video_url = video_url;
 }
 
-pub async fn get_video_info(video_downloader: &mut VideoDownloader) -> anyhow::Result<Video> {
-    match video_downloader
-        .fetcher
-        .fetch_video_infos(video_downloader.video_url.clone())
-        .await
-    {
+fn report_retry(events: &EventSender) -> impl FnMut(&RetryAttempt) {
+    let events = events.clone();
+    move |attempt: &RetryAttempt| {
+        events.status(attempt.to_string());
+    }
+}
+
+async fn fetch_video_info(
+    video_downloader: &VideoDownloader,
+    events: &EventSender,
+) -> anyhow::Result<Video> {
+    let backend = video_downloader.backend.clone();
+    let mut video_info = video_downloader
+        .retry_policy
+        .run("Fetching video info", report_retry(events), || {
+            backend.fetch_info(&video_downloader.video_url)
+        })
+        .await?;
+    video_info.formats = backend
+        .list_formats(&video_info)
+        .into_iter()
+        .cloned()
+        .collect();
+    Ok(video_info)
+}
+
+pub async fn get_video_info(
+    video_downloader: &mut VideoDownloader,
+    events: &EventSender,
+    refresh: bool,
+) -> anyhow::Result<Video> {
+    // Only YouTube ids are known before fetching and unique across sites.
+    let video_id = parse_video_url(&video_downloader.video_url)
+        .ok()
+        .filter(|video_url| video_url.is_youtube())
+        .map(|video_url| video_url.video_id);
+    let cached_video_info = match (&video_id, refresh) {
+        (Some(video_id), false) => video_downloader.metadata_cache.get(video_id),
+        _ => None,
+    };
+
+    let fetch_result = match cached_video_info {
+        Some(video_info) => {
+            debug!("Using cached video infos for {}.", video_info.id);
+            Ok(video_info)
+        }
+        None => {
+            let fetch_result = fetch_video_info(video_downloader, events).await;
+            if let (Ok(video_info), Some(_)) = (&fetch_result, &video_id) {
+                if let Err(e) = video_downloader.metadata_cache.put(video_info) {
+                    error!("Failed to cache video infos: {}", e);
+                }
+            }
+            fetch_result
+        }
+    };
+
+    match fetch_result {
         Ok(video_info) => {
             debug!("Video infos recieved.");
             video_downloader.video_info = video_info;
-            video_downloader.output_file_name =
-                sanitize_filename(video_downloader.video_info.title.clone().as_str())
-                    .trim()
-                    .to_string();
-            let info_file_name = video_downloader.output_file_name.clone() + ".txt";
-            let info_file_path = "output/".to_string() + &info_file_name;
-            fs::write(
-                info_file_path,
-                format!("{:#?}", video_downloader.video_info),
-            )
-            .unwrap();
+            video_downloader.output_file_name = output_file_name(&video_downloader.video_info);
+            let target_dir = video_downloader.target_dir();
+            let info_file_path =
+                target_dir.join(video_downloader.output_file_name.clone() + ".txt");
+            if let Err(e) = fs::create_dir_all(&target_dir).and_then(|_| {
+                fs::write(
+                    &info_file_path,
+                    format!("{:#?}", video_downloader.video_info),
+                )
+            }) {
+                error!(
+                    "Failed to write video infos to {}: {}",
+                    info_file_path.display(),
+                    e
+                );
+            }
 
             Ok(video_downloader.video_info.clone())
         }
@@ -136,19 +192,24 @@ pub async fn get_video_info(video_downloader: &mut VideoDownloader) -> anyhow::R
 
 pub async fn get_video_thumbnail(
     video_downloader: &mut VideoDownloader,
+    events: &EventSender,
 ) -> anyhow::Result<PathBuf> {
-    let thumbnail_extension = video_downloader
-        .video_info
-        .thumbnail
-        .split(".")
-        .last()
-        .unwrap();
+    if video_downloader.video_info.thumbnail.is_empty() {
+        anyhow::bail!("{} has no thumbnail", video_downloader.video_info.title);
+    }
+    let thumbnail_extension =
+        url_extension(&video_downloader.video_info.thumbnail).unwrap_or_else(|| "jpg".to_string());
+    let backend = video_downloader.backend.clone();
+    let target_dir = video_downloader.target_dir();
+    fs::create_dir_all(&target_dir)
+        .with_context(|| format!("Failed to create {}", target_dir.display()))?;
+    let thumbnail_path =
+        target_dir.join(video_downloader.output_file_name.clone() + "." + &thumbnail_extension);
     match video_downloader
-        .fetcher
-        .download_thumbnail_from_url(
-            video_downloader.video_url.clone(),
-            video_downloader.output_file_name.clone() + "." + thumbnail_extension,
-        )
+        .retry_policy
+        .run("Downloading thumbnail", report_retry(events), || {
+            backend.download_thumbnail(&video_downloader.video_url, &thumbnail_path)
+        })
         .await
     {
         Ok(path) => {
@@ -162,99 +223,215 @@ pub async fn get_video_thumbnail(
     }
 }
 
-pub async fn download_video(
-    video_downloader: &mut VideoDownloader,
-    tx: &tokio::sync::broadcast::Sender<Message>,
+/// Downloads the chosen thumbnail and saves it next to the video in the requested
+/// image format.
+pub async fn save_thumbnail(
+    video_downloader: &VideoDownloader,
+    request: &ThumbnailRequest,
+    events: &EventSender,
 ) -> anyhow::Result<PathBuf> {
-    debug!("Downloading video...");
-    let video_info = video_downloader.video_info.clone();
-    let video_path = video_downloader.output_file_name.clone() + ".mp4";
-    let video_fetcher = video_downloader.fetcher.clone();
-    let tx_clone = tx.clone();
-    video_downloader.video_path = Some(video_path.clone().into());
-    let video_path_clone = video_path.clone();
+    let job_temp_dir = JobTempDir::create(&video_downloader.temp_root)
+        .context("Failed to create temp dir for thumbnail")?;
+    let source_path = job_temp_dir.path().join("thumbnail");
+    let backend = video_downloader.backend.clone();
+    let ignore_progress = |_: u64, _: u64| {};
+    video_downloader
+        .retry_policy
+        .run("Downloading thumbnail", report_retry(events), || {
+            backend.download_stream(&request.url, &source_path, &ignore_progress)
+        })
+        .await?;
 
-    debug!("Starting Download...");
+    let file_name = format!(
+        "{}.{}",
+        video_downloader.output_file_name,
+        request.format.extension()
+    );
+    let converted_path = job_temp_dir.path().join(&file_name);
+    convert_thumbnail(
+        &source_path,
+        &converted_path,
+        request.format,
+        request.crop_square,
+    )?;
+    let thumbnail_path = video_downloader.target_dir().join(file_name);
+    move_into_place(&converted_path, &thumbnail_path)
+        .with_context(|| format!("Failed to move thumbnail to {}", thumbnail_path.display()))?;
+    debug!("Thumbnail saved to {}.", thumbnail_path.display());
+    Ok(thumbnail_path)
+}
 
-    let video_download_progress_callback = move |downloaded: u64, total: u64| {
+fn report_progress(events: &EventSender) -> impl Fn(u64, u64) + Clone + Send + Sync + 'static {
+    let events = events.clone();
+    move |downloaded: u64, total: u64| {
         let percentage = if total > 0 {
             (downloaded as f64 / total as f64 * 100.0) as u64
         } else {
             0
         };
-        let _ = tx_clone.send(UIMessage::ProgressUpdated(
-            downloaded as f64,
-            percentage as f32,
-        ));
-        info!(
-            "Video Download Progress: {}/{} bytes ({}%)",
-            downloaded, total, percentage
-        );
+        events.progress(downloaded as f64, percentage as f32);
+    }
+}
+
+fn find_format(video_info: &Video, format_id: &str) -> anyhow::Result<Format> {
+    video_info
+        .formats
+        .iter()
+        .find(|format| format.format_id == format_id)
+        .cloned()
+        .with_context(|| format!("Format {} is not available", format_id))
+}
+
+/// Progressive formats already carry both audio and video, so they are fetched as a
+/// single stream and need no ffmpeg merge.
+async fn download_progressive_video(
+    video_downloader: &mut VideoDownloader,
+    format_id: &str,
+    events: &EventSender,
+) -> anyhow::Result<PathBuf> {
+    let format = find_format(&video_downloader.video_info, format_id)?;
+    let extension = match FormatListItem::new(&format).video_ext.as_str() {
+        "" | "none" => "mp4".to_string(),
+        extension => extension.to_string(),
     };
+    let video_file_name = video_downloader.output_file_name.clone() + "." + &extension;
+    let video_path = video_downloader.target_dir().join(&video_file_name);
+    let job_temp_dir = JobTempDir::create(&video_downloader.temp_root)
+        .context("Failed to create temp dir for download")?;
+    let temp_video_path = job_temp_dir.path().join(&video_file_name);
+    debug!(
+        "Downloading progressive format {} to {}...",
+        format_id,
+        temp_video_path.display()
+    );
 
-    let audio_download_progress_callback = move |downloaded: u64, total: u64| {
-        let percentage = if total > 0 {
-            (downloaded as f64 / total as f64 * 100.0) as u64
-        } else {
-            0
-        };
+    let backend = video_downloader.backend.clone();
+    let progress_callback = report_progress(events);
+    video_downloader
+        .retry_policy
+        .run("Downloading video", report_retry(events), || {
+            backend.download_stream(&format.url, &temp_video_path, &progress_callback)
+        })
+        .await?;
+    move_into_place(&temp_video_path, &video_path)
+        .with_context(|| format!("Failed to move video to {}", video_path.display()))?;
+    debug!("Video Downloaded to {}.", video_path.display());
+    video_downloader.video_path = Some(video_path.clone());
+
+    download_captions(video_downloader, events).await?;
 
-        info!(
-            "Audio DownloadProgress: {}/{} bytes ({}%)",
-            downloaded, total, percentage
+    Ok(video_path)
+}
+
+pub async fn download_video(
+    video_downloader: &mut VideoDownloader,
+    events: &EventSender,
+) -> anyhow::Result<PathBuf> {
+    if LiveStatus::parse(&video_downloader.video_info.live_status).needs_recording() {
+        anyhow::bail!(
+            "{} is a live stream or premiere, record it instead",
+            video_downloader.video_info.title
         );
+    }
+    if let Some(format_id) = video_downloader.selected_audio_video_format.clone() {
+        return download_progressive_video(video_downloader, &format_id, events).await;
+    }
+
+    debug!("Downloading video...");
+    let (auto_selected_video_format_id, auto_selected_audio_format_id) =
+        auto_select_formats(&video_downloader.video_info);
+    let video_format_id = video_downloader
+        .selected_video_format
+        .clone()
+        .or(auto_selected_video_format_id);
+    let audio_format_id = video_downloader
+        .selected_audio_format
+        .clone()
+        .or(auto_selected_audio_format_id);
+    let (video_format_id, audio_format_id) = match (video_format_id, audio_format_id) {
+        (Some(video_format_id), Some(audio_format_id)) => (video_format_id, audio_format_id),
+        // Many sites only offer formats with audio and video in one stream.
+        _ => {
+            let format_id = best_progressive_format(&video_downloader.video_info)
+                .context("No audio and video formats available")?;
+            debug!("No separate streams, falling back to format {}", format_id);
+            return download_progressive_video(video_downloader, &format_id, events).await;
+        }
     };
+    let video_format = find_format(&video_downloader.video_info, &video_format_id)?;
+    let audio_format = find_format(&video_downloader.video_info, &audio_format_id)?;
 
-    let (video_download_id, audio_download_id, video_temp_file_name, audio_temp_file_name) = video_fetcher
-        .download_video_with_progress_format(
-            &video_info,
-            video_path,
-            video_downloader.selected_video_format.clone(),
-            video_downloader.selected_audio_format.clone(),
-            video_download_progress_callback,
-            audio_download_progress_callback,
-        )
-        .await
-        .expect("Failed to download video");
-    debug!(
-        "video download id: {}, audio download id: {}",
-        video_download_id, audio_download_id
-    );
-    video_fetcher
-        .wait_for_download(video_download_id)
-        .await
-        .expect("Waiting for video download to finish failed");
-    debug!("Video Downloaded to {}.", video_temp_file_name);
-    video_fetcher
-        .wait_for_download(audio_download_id)
+    let video_file_name = video_downloader.output_file_name.clone() + ".mp4";
+    let final_video_path = video_downloader.target_dir().join(&video_file_name);
+    let job_temp_dir = JobTempDir::create(&video_downloader.temp_root)
+        .context("Failed to create temp dir for download")?;
+    let video_stream_path = job_temp_dir.path().join(format!(
+        "video.{}",
+        FormatListItem::new(&video_format).video_ext
+    ));
+    let audio_stream_path = job_temp_dir.path().join(format!(
+        "audio.{}",
+        FormatListItem::new(&audio_format).audio_ext
+    ));
+    let backend = video_downloader.backend.clone();
+
+    debug!("Starting Download...");
+    let video_progress_callback = report_progress(events);
+    video_downloader
+        .retry_policy
+        .run("Downloading video stream", report_retry(events), || {
+            backend.download_stream(
+                &video_format.url,
+                &video_stream_path,
+                &video_progress_callback,
+            )
+        })
         .await
-        .expect("Waiting for audio download to finish failed");
-    debug!("Audio Downloaded to {}.", audio_temp_file_name);
+        .context("Failed to download video")?;
+    debug!("Video Downloaded to {}.", video_stream_path.display());
 
-    debug!("Download finished");
+    let audio_progress_callback = |downloaded: u64, total: u64| {
+        debug!("Audio Download Progress: {}/{} bytes", downloaded, total);
+    };
+    video_downloader
+        .retry_policy
+        .run("Downloading audio stream", report_retry(events), || {
+            backend.download_stream(
+                &audio_format.url,
+                &audio_stream_path,
+                &audio_progress_callback,
+            )
+        })
+        .await
+        .context("Failed to download audio")?;
+    debug!("Audio Downloaded to {}.", audio_stream_path.display());
 
     debug!("Combining audio and video...");
-    let output_path = video_fetcher.combine_audio_and_video(
-        video_temp_file_name,
-        audio_temp_file_name,
-        video_path_clone,
-    ).await.expect("Failed to combine audio and video");
+    let output_path = backend
+        .merge(
+            &video_stream_path,
+            &audio_stream_path,
+            &job_temp_dir.path().join("merged.mp4"),
+        )
+        .await
+        .context("Failed to combine audio and video")?;
     debug!("Combined audio and video to {}.", output_path.display());
 
-    debug!("removing temp files...");
-    for entry in fs::read_dir(video_downloader.output_dir.clone())? {
-        let entry = entry?;
-        let file_type = entry.file_type()?;
-        if !file_type.is_dir() {
-            if entry.file_name().to_str().unwrap().contains("temp_audio_")
-                || entry.file_name().to_str().unwrap().contains("temp_video_")
-            {
-                fs::remove_file(entry.path())?;
-            }
-        }
-    }
-    debug!("temp files removed");
+    move_into_place(&output_path, &final_video_path)
+        .with_context(|| format!("Failed to move video to {}", final_video_path.display()))?;
+    drop(job_temp_dir);
+    debug!("Video moved to {}.", final_video_path.display());
+    video_downloader.video_path = Some(final_video_path);
 
+    download_captions(video_downloader, events).await?;
+
+    Ok(video_downloader.video_path.clone().unwrap())
+}
+
+async fn download_captions(
+    video_downloader: &VideoDownloader,
+    events: &EventSender,
+) -> anyhow::Result<()> {
     debug!("Downloading captions...");
     for caption_group in &video_downloader.video_info.automatic_captions {
         let caption_languages = caption_group.0;
@@ -264,32 +441,257 @@ pub async fn download_video(
         {
             for caption in caption_group.1 {
                 let caption_extension = &caption.extension;
-                if caption_extension.to_string() == "srt" || caption_extension.to_string() == "vtt"
-                {
+                if caption_extension == "srt" || caption_extension == "vtt" {
                     let caption_url = &caption.url;
-                    let response = reqwest::get(caption_url).await?.text().await?;
-                    let mut caption_file_path =
-                        "output/".to_string() + &video_downloader.output_file_name;
+                    let mut caption_file_name = video_downloader.output_file_name.clone();
                     if caption_languages.contains("orig") {
                         let original_language = caption_languages.replace("-orig", "");
-                        caption_file_path = caption_file_path + "." + &original_language + ".";
+                        caption_file_name = caption_file_name + "." + &original_language + ".";
                     } else {
-                        caption_file_path = caption_file_path + ".en.";
+                        caption_file_name = caption_file_name + ".en.";
                     }
 
-                    caption_file_path = caption_file_path + caption_extension.to_string().as_str();
+                    caption_file_name = caption_file_name + caption_extension;
 
-                    fs::write(caption_file_path, response).unwrap();
+                    let caption_file_path = video_downloader.target_dir().join(caption_file_name);
+                    fs::create_dir_all(video_downloader.target_dir())?;
+                    let backend = video_downloader.backend.clone();
+                    let ignore_progress = |_: u64, _: u64| {};
+                    video_downloader
+                        .retry_policy
+                        .run("Downloading captions", report_retry(events), || {
+                            backend.download_stream(
+                                caption_url,
+                                &caption_file_path,
+                                &ignore_progress,
+                            )
+                        })
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "Failed to download captions to {}",
+                                caption_file_path.display()
+                            )
+                        })?;
                 }
             }
         }
     }
     debug!("Captions downloaded.");
 
-    Ok(video_downloader.video_path.clone().unwrap())
+    Ok(())
 }
 
-fn sanitize_filename(input: &str) -> String {
-    let re = regex::Regex::new(r"[^ a-zA-Z0-9_\.-]").unwrap();
+pub fn auto_select_formats(video_info: &Video) -> (Option<String>, Option<String>) {
+    let auto_selected_video_format_id = video_info
+        .best_video_format()
+        .map(|format| format.format_id.clone());
+
+    let auto_selected_audio_format = match video_info.best_audio_format() {
+        Some(format) => format,
+        None => return (auto_selected_video_format_id, None),
+    };
+
+    let mut auto_selected_audio_format_id: Option<String> = None;
+    let mut auto_selected_audio_format_match_level: i32 = 0;
+    for audio_format in video_info.formats.iter().filter(|format| format.is_audio()) {
+        let mut audio_format_match_level = 0;
+        if audio_format.quality == auto_selected_audio_format.quality {
+            audio_format_match_level += 1;
+        }
+        if audio_format.audio_ext == auto_selected_audio_format.audio_ext {
+            audio_format_match_level += 1;
+        }
+        if audio_format
+            .format_note
+            .as_deref()
+            .unwrap_or_default()
+            .contains("original")
+        {
+            audio_format_match_level += 2;
+        }
+        if audio_format.audio_codec == auto_selected_audio_format.audio_codec {
+            audio_format_match_level += 1;
+        }
+        if audio_format.format_id == auto_selected_audio_format.format_id {
+            audio_format_match_level += 1;
+        }
+
+        if audio_format_match_level > auto_selected_audio_format_match_level {
+            auto_selected_audio_format_id = Some(audio_format.format_id.clone());
+            auto_selected_audio_format_match_level = audio_format_match_level;
+        }
+    }
+
+    (auto_selected_video_format_id, auto_selected_audio_format_id)
+}
+
+/// Best format carrying both audio and video, for sites without separate streams.
+/// Formats are listed from worst to best, which decides ties.
+pub fn best_progressive_format(video_info: &Video) -> Option<String> {
+    video_info
+        .formats
+        .iter()
+        .map(FormatListItem::new)
+        .filter(|format| format.carries_audio_and_video())
+        .max_by(|a, b| {
+            (a.height, a.effective_bitrate())
+                .partial_cmp(&(b.height, b.effective_bitrate()))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|format| format.format_id)
+}
+
+/// File name for the video without extension: its title, or its id when the title has
+/// nothing left after sanitizing, as happens with titles in non-Latin scripts.
+fn output_file_name(video_info: &Video) -> String {
+    [&video_info.title, &video_info.id]
+        .into_iter()
+        .map(|name| sanitize_filename(name).trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or("video".to_string())
+}
+
+pub fn sanitize_filename(input: &str) -> String {
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| regex::Regex::new(r"[^ a-zA-Z0-9_\.-]").unwrap());
     re.replace_all(input, "").to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use std::path::Path;
+
+    const VIDEO_URL: &str = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn fake_downloader(dir: &Path, backend: FakeBackend) -> VideoDownloader {
+        let mut video_downloader =
+            VideoDownloader::new("libs", &dir.join("output").display().to_string(), VIDEO_URL)
+                .unwrap()
+                .with_backend(Arc::new(backend));
+        video_downloader.metadata_cache.cache_dir = dir.join("cache");
+        video_downloader.temp_root = dir.join("temp");
+        video_downloader
+    }
+
+    fn fake_video() -> Video {
+        Video {
+            id: "dQw4w9WgXcQ".to_string(),
+            title: "Fake: Video?".to_string(),
+            thumbnail: "https://example.invalid/thumbnail.jpg".to_string(),
+            ..Video::default()
+        }
+    }
+
+    #[test]
+    fn output_dir_is_created_only_when_saving() {
+        let dir = test_dir("lazy-output");
+        let mut video_downloader = fake_downloader(&dir, FakeBackend::default());
+        video_downloader.set_output_dir(dir.join("other"));
+
+        assert!(!dir.join("output").exists());
+        assert!(!dir.join("other").exists());
+    }
+
+    #[tokio::test]
+    async fn get_video_info_uses_backend() {
+        let dir = test_dir("info");
+        let mut video_downloader = fake_downloader(
+            &dir,
+            FakeBackend::default().with_video(VIDEO_URL, fake_video()),
+        );
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let events = EventSender::new(1, tx);
+
+        let video_info = get_video_info(&mut video_downloader, &events, true)
+            .await
+            .unwrap();
+
+        assert_eq!(video_info.title, "Fake: Video?");
+        assert_eq!(video_downloader.output_file_name, "Fake Video");
+        assert!(dir.join("output").join("Fake Video.txt").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn refresh_bypasses_the_metadata_cache() {
+        let dir = test_dir("refresh");
+        let mut video_downloader = fake_downloader(
+            &dir,
+            FakeBackend::default().with_video(VIDEO_URL, fake_video()),
+        );
+        video_downloader
+            .metadata_cache
+            .put(&Video {
+                title: "Cached Title".to_string(),
+                ..fake_video()
+            })
+            .unwrap();
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let events = EventSender::new(1, tx);
+
+        let cached = get_video_info(&mut video_downloader, &events, false)
+            .await
+            .unwrap();
+        let refreshed = get_video_info(&mut video_downloader, &events, true)
+            .await
+            .unwrap();
+
+        assert_eq!(cached.title, "Cached Title");
+        assert_eq!(refreshed.title, "Fake: Video?");
+        // The fresh info replaces the cached one.
+        assert_eq!(
+            video_downloader.metadata_cache.get("dQw4w9WgXcQ"),
+            Some(refreshed)
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn get_video_info_reports_backend_errors() {
+        let dir = test_dir("info-error");
+        let mut video_downloader = fake_downloader(&dir, FakeBackend::default());
+        video_downloader.retry_policy.max_attempts = 1;
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let events = EventSender::new(1, tx);
+
+        let result = get_video_info(&mut video_downloader, &events, true).await;
+
+        assert!(result.is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn get_video_thumbnail_downloads_through_backend() {
+        let dir = test_dir("thumbnail");
+        let backend = FakeBackend::default()
+            .with_video(VIDEO_URL, fake_video())
+            .with_stream("https://example.invalid/thumbnail.jpg", b"jpeg");
+        let mut video_downloader = fake_downloader(&dir, backend);
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let events = EventSender::new(1, tx);
+        get_video_info(&mut video_downloader, &events, true)
+            .await
+            .unwrap();
+
+        let thumbnail_path = get_video_thumbnail(&mut video_downloader, &events)
+            .await
+            .unwrap();
+
+        assert_eq!(thumbnail_path, dir.join("output").join("Fake Video.jpg"));
+        assert_eq!(fs::read(&thumbnail_path).unwrap(), b"jpeg");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt::Display;
+use std::iter::Sum;
+use std::ops::Add;
+
+use url::Url;
+
+use crate::backend::Format;
+
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+/// A size in bytes, either reported by the extractor or estimated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct FileSize {
+    pub bytes: u64,
+    pub approximate: bool,
+}
+
+impl FileSize {
+    pub fn exact(bytes: u64) -> Self {
+        Self {
+            bytes,
+            approximate: false,
+        }
+    }
+
+    pub fn approximate(bytes: u64) -> Self {
+        Self {
+            bytes,
+            approximate: true,
+        }
+    }
+
+    /// Estimates a stream size from its bitrate in kbit/s and its duration in seconds.
+    pub fn from_bitrate(bitrate: f64, duration: f64) -> Option<Self> {
+        match bitrate > 0.0 && duration > 0.0 {
+            true => Some(Self::approximate(
+                (bitrate * 1000.0 / 8.0 * duration) as u64,
+            )),
+            false => None,
+        }
+    }
+}
+
+impl Add for FileSize {
+    type Output = FileSize;
+
+    fn add(self, other: FileSize) -> FileSize {
+        FileSize {
+            bytes: self.bytes + other.bytes,
+            approximate: self.approximate || other.approximate,
+        }
+    }
+}
+
+impl Sum for FileSize {
+    fn sum<I: Iterator<Item = FileSize>>(iter: I) -> Self {
+        iter.fold(FileSize::default(), Add::add)
+    }
+}
+
+impl Display for FileSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.approximate {
+            true => write!(f, "~{}", format_bytes(self.bytes)),
+            false => write!(f, "{}", format_bytes(self.bytes)),
+        }
+    }
+}
+
+/// Formats a byte count with binary units and one decimal, e.g. `1.5 MB`.
+pub fn format_bytes(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    // Sizes just below the next unit would otherwise print as `1024.0 KB`.
+    while size >= 1023.95 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} {}", bytes, UNITS[unit]),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
+/// YouTube stream URLs carry the stream duration in seconds as a `dur` query parameter.
+pub fn stream_duration(stream_url: &str) -> Option<f64> {
+    Url::parse(stream_url)
+        .ok()?
+        .query_pairs()
+        .find(|(key, _)| key == "dur")
+        .and_then(|(_, value)| value.parse::<f64>().ok())
+}
+
+/// Duration of the whole video, taken from the longest stream that reports one. Used
+/// for streams whose URL carries no `dur`, such as HLS and progressive formats.
+pub fn video_duration(formats: &[Format]) -> Option<f64> {
+    formats
+        .iter()
+        .filter_map(|format| stream_duration(&format.url))
+        .max_by(f64::total_cmp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_bytes_with_one_decimal() {
+        let cases = [
+            (0, "0 B"),
+            (1023, "1023 B"),
+            (1024, "1.0 KB"),
+            (1536, "1.5 KB"),
+            (1024 * 1024 - 52, "1023.9 KB"),
+            (1024 * 1024 - 51, "1.0 MB"),
+            (1024 * 1024 - 1, "1.0 MB"),
+            (5 * 1024 * 1024 * 1024 + 512 * 1024 * 1024, "5.5 GB"),
+            (3 * 1024_u64.pow(5), "3072.0 TB"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(format_bytes(bytes), expected, "{} bytes", bytes);
+        }
+    }
+
+    #[test]
+    fn marks_approximate_sizes() {
+        assert_eq!(FileSize::exact(1536).to_string(), "1.5 KB");
+        assert_eq!(FileSize::approximate(1536).to_string(), "~1.5 KB");
+        let total: FileSize = [FileSize::exact(1024), FileSize::approximate(512)]
+            .into_iter()
+            .sum();
+        assert_eq!(total.to_string(), "~1.5 KB");
+        let total: FileSize = [FileSize::exact(1024), FileSize::exact(512)]
+            .into_iter()
+            .sum();
+        assert_eq!(total.to_string(), "1.5 KB");
+    }
+
+    #[test]
+    fn estimates_size_from_bitrate() {
+        assert_eq!(
+            FileSize::from_bitrate(128.0, 60.0),
+            Some(FileSize::approximate(960_000))
+        );
+        assert_eq!(FileSize::from_bitrate(128.0, 0.0), None);
+        assert_eq!(FileSize::from_bitrate(0.0, 60.0), None);
+    }
+
+    #[test]
+    fn reads_stream_duration() {
+        assert_eq!(
+            stream_duration("https://rr1.googlevideo.com/videoplayback?itag=137&dur=212.040"),
+            Some(212.04)
+        );
+        assert_eq!(
+            stream_duration("https://example.invalid/manifest.m3u8"),
+            None
+        );
+        assert_eq!(stream_duration("not a url"), None);
+    }
+}
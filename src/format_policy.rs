@@ -0,0 +1,343 @@
+use std::cmp::Ordering;
+
+use crate::backend::Video;
+use crate::ui::state::FormatListItem;
+
+const POLICY_KEYWORDS: [&str; 5] = ["hdr", "hdr-only", "sdr", "drm", "hfr"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DynamicRangePreference {
+    #[default]
+    Any,
+    PreferHdr,
+    HdrOnly,
+    SdrOnly,
+}
+
+/// Rules for picking formats automatically, written as comma separated clauses,
+/// e.g. `height<=1080,fps>=50,hdr,vcodec=av01|vp9,acodec=opus,lang=en`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FormatPolicy {
+    pub max_height: Option<u32>,
+    pub min_fps: Option<f64>,
+    pub prefer_high_frame_rate: bool,
+    pub dynamic_range: DynamicRangePreference,
+    pub video_codecs: Vec<String>,
+    pub audio_codecs: Vec<String>,
+    pub language: Option<String>,
+    pub allow_drm: bool,
+}
+
+impl FormatPolicy {
+    pub fn looks_like_policy(spec: &str) -> bool {
+        spec.contains(['=', '<', '>', ','])
+            || POLICY_KEYWORDS
+                .iter()
+                .any(|keyword| spec.trim().eq_ignore_ascii_case(keyword))
+    }
+
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut policy = FormatPolicy::default();
+        for clause in spec
+            .split(',')
+            .map(|clause| clause.trim().to_ascii_lowercase())
+        {
+            if clause.is_empty() {
+                continue;
+            }
+
+            if let Some(value) = clause.strip_prefix("height<=") {
+                policy.max_height = Some(parse_number(&clause, value)?);
+            } else if let Some(value) = clause.strip_prefix("fps>=") {
+                policy.min_fps = Some(parse_number(&clause, value)?);
+            } else if let Some(value) = clause.strip_prefix("vcodec=") {
+                policy.video_codecs = split_alternatives(value);
+            } else if let Some(value) = clause.strip_prefix("acodec=") {
+                policy.audio_codecs = split_alternatives(value);
+            } else if let Some(value) = clause.strip_prefix("lang=") {
+                policy.language = Some(value.to_string());
+            } else {
+                match clause.as_str() {
+                    "hdr" => policy.dynamic_range = DynamicRangePreference::PreferHdr,
+                    "hdr-only" => policy.dynamic_range = DynamicRangePreference::HdrOnly,
+                    "sdr" => policy.dynamic_range = DynamicRangePreference::SdrOnly,
+                    "hfr" => policy.prefer_high_frame_rate = true,
+                    "drm" => policy.allow_drm = true,
+                    _ => return Err(format!("unknown format policy clause '{}'", clause)),
+                }
+            }
+        }
+        Ok(policy)
+    }
+
+    fn accepts_video(&self, format: &FormatListItem) -> bool {
+        (self.allow_drm || !format.has_drm)
+            && self
+                .max_height
+                .map_or(true, |max_height| format.height <= max_height)
+            && self.min_fps.map_or(true, |min_fps| format.fps >= min_fps)
+            && match self.dynamic_range {
+                DynamicRangePreference::HdrOnly => format.is_hdr(),
+                DynamicRangePreference::SdrOnly => !format.is_hdr(),
+                _ => true,
+            }
+    }
+
+    fn accepts_audio(&self, format: &FormatListItem) -> bool {
+        self.allow_drm || !format.has_drm
+    }
+
+    fn compare_video(&self, left: &FormatListItem, right: &FormatListItem) -> Ordering {
+        let hdr_rank = |format: &FormatListItem| match self.dynamic_range {
+            DynamicRangePreference::PreferHdr => format.is_hdr(),
+            _ => false,
+        };
+        let frame_rate_rank = |format: &FormatListItem| match self.prefer_high_frame_rate {
+            true => format.is_high_frame_rate(),
+            false => false,
+        };
+        codec_rank(&self.video_codecs, &right.video_codec)
+            .cmp(&codec_rank(&self.video_codecs, &left.video_codec))
+            .then(hdr_rank(left).cmp(&hdr_rank(right)))
+            .then(frame_rate_rank(left).cmp(&frame_rate_rank(right)))
+            .then(left.height.cmp(&right.height))
+            .then(left.fps.total_cmp(&right.fps))
+            .then(
+                left.effective_bitrate()
+                    .total_cmp(&right.effective_bitrate()),
+            )
+    }
+
+    fn compare_audio(&self, left: &FormatListItem, right: &FormatListItem) -> Ordering {
+        let language_rank = |format: &FormatListItem| match &self.language {
+            Some(language) => format.language.to_ascii_lowercase().starts_with(language),
+            None => format.format_note.contains("original"),
+        };
+        language_rank(left)
+            .cmp(&language_rank(right))
+            .then(
+                codec_rank(&self.audio_codecs, &right.audio_codec)
+                    .cmp(&codec_rank(&self.audio_codecs, &left.audio_codec)),
+            )
+            .then(
+                left.effective_bitrate()
+                    .total_cmp(&right.effective_bitrate()),
+            )
+            .then(left.sample_rate.cmp(&right.sample_rate))
+    }
+
+    pub fn select_formats(&self, video_info: &Video) -> (Option<String>, Option<String>) {
+        let formats: Vec<FormatListItem> = video_info
+            .formats
+            .iter()
+            .filter(|format| !format.is_storyboard())
+            .map(FormatListItem::new)
+            .collect();
+
+        let video_format = formats
+            .iter()
+            .filter(|format| is_codec(&format.video_codec) && !is_codec(&format.audio_codec))
+            .filter(|format| self.accepts_video(format))
+            .max_by(|left, right| self.compare_video(left, right));
+        let audio_format = formats
+            .iter()
+            .filter(|format| is_codec(&format.audio_codec) && !is_codec(&format.video_codec))
+            .filter(|format| self.accepts_audio(format))
+            .max_by(|left, right| self.compare_audio(left, right));
+
+        (
+            video_format.map(|format| format.format_id.clone()),
+            audio_format.map(|format| format.format_id.clone()),
+        )
+    }
+}
+
+fn is_codec(codec: &str) -> bool {
+    !codec.is_empty() && codec != "none"
+}
+
+/// Position of the codec in the preference list, lower is better.
+fn codec_rank(preferences: &[String], codec: &str) -> usize {
+    preferences
+        .iter()
+        .position(|preference| codec.to_ascii_lowercase().starts_with(preference.as_str()))
+        .unwrap_or(preferences.len())
+}
+
+fn split_alternatives(value: &str) -> Vec<String> {
+    value
+        .split('|')
+        .map(|alternative| alternative.trim().to_string())
+        .filter(|alternative| !alternative.is_empty())
+        .collect()
+}
+
+fn parse_number<T: std::str::FromStr>(clause: &str, value: &str) -> Result<T, String> {
+    value
+        .trim_end_matches('p')
+        .parse()
+        .map_err(|_| format!("invalid number in format policy clause '{}'", clause))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Format;
+
+    #[test]
+    fn parses_policy_clauses() {
+        let cases = [
+            (
+                "height<=1080",
+                FormatPolicy {
+                    max_height: Some(1080),
+                    ..FormatPolicy::default()
+                },
+            ),
+            (
+                " Height<=720p , FPS>=50 ",
+                FormatPolicy {
+                    max_height: Some(720),
+                    min_fps: Some(50.0),
+                    ..FormatPolicy::default()
+                },
+            ),
+            ("vcodec=", FormatPolicy::default()),
+            (
+                "vcodec=av01|vp9,acodec=opus",
+                FormatPolicy {
+                    video_codecs: vec!["av01".to_string(), "vp9".to_string()],
+                    audio_codecs: vec!["opus".to_string()],
+                    ..FormatPolicy::default()
+                },
+            ),
+            (
+                "lang=EN",
+                FormatPolicy {
+                    language: Some("en".to_string()),
+                    ..FormatPolicy::default()
+                },
+            ),
+            (
+                "hdr-only,hfr,drm",
+                FormatPolicy {
+                    dynamic_range: DynamicRangePreference::HdrOnly,
+                    prefer_high_frame_rate: true,
+                    allow_drm: true,
+                    ..FormatPolicy::default()
+                },
+            ),
+        ];
+        for (spec, expected) in cases {
+            assert_eq!(FormatPolicy::parse(spec), Ok(expected), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed_clauses() {
+        let cases = [
+            (
+                "resolution=1080",
+                "unknown format policy clause 'resolution=1080'",
+            ),
+            (
+                "height>=1080",
+                "unknown format policy clause 'height>=1080'",
+            ),
+            (
+                "height<=",
+                "invalid number in format policy clause 'height<='",
+            ),
+            (
+                "height<=tall",
+                "invalid number in format policy clause 'height<=tall'",
+            ),
+            ("fps>=-", "invalid number in format policy clause 'fps>=-'"),
+        ];
+        for (spec, error) in cases {
+            assert_eq!(
+                FormatPolicy::parse(spec),
+                Err(error.to_string()),
+                "{}",
+                spec
+            );
+        }
+    }
+
+    #[test]
+    fn tells_policies_from_format_ids() {
+        for spec in ["height<=1080", "hdr", "SDR", "lang=en", "hfr,drm"] {
+            assert!(FormatPolicy::looks_like_policy(spec), "{}", spec);
+        }
+        for spec in ["137", "137+140", "best"] {
+            assert!(!FormatPolicy::looks_like_policy(spec), "{}", spec);
+        }
+    }
+
+    fn video_format(format_id: &str, codec: &str, height: u32, fps: f64, bitrate: f64) -> Format {
+        Format {
+            format_id: format_id.to_string(),
+            video_codec: Some(codec.to_string()),
+            audio_codec: Some("none".to_string()),
+            height: Some(height),
+            fps: Some(fps),
+            total_bitrate: Some(bitrate),
+            ..Format::default()
+        }
+    }
+
+    fn audio_format(format_id: &str, codec: &str, language: &str, bitrate: f64) -> Format {
+        Format {
+            format_id: format_id.to_string(),
+            video_codec: Some("none".to_string()),
+            audio_codec: Some(codec.to_string()),
+            language: Some(language.to_string()),
+            total_bitrate: Some(bitrate),
+            ..Format::default()
+        }
+    }
+
+    fn test_video() -> Video {
+        Video {
+            formats: vec![
+                video_format("137", "avc1.640028", 1080, 30.0, 4500.0),
+                video_format("299", "avc1.64002a", 1080, 60.0, 6000.0),
+                video_format("303", "vp9", 1080, 60.0, 4000.0),
+                video_format("313", "vp9", 2160, 30.0, 18000.0),
+                audio_format("140", "mp4a.40.2", "en", 128.0),
+                audio_format("250", "opus", "en-US", 70.0),
+                audio_format("251", "opus", "de", 160.0),
+            ],
+            ..Video::default()
+        }
+    }
+
+    #[test]
+    fn selects_formats_by_policy() {
+        let cases = [
+            ("", (Some("313"), Some("251"))),
+            ("height<=1080", (Some("299"), Some("251"))),
+            ("height<=1080,vcodec=vp9", (Some("303"), Some("251"))),
+            ("vcodec=av01|avc1", (Some("299"), Some("251"))),
+            ("lang=en", (Some("313"), Some("140"))),
+            ("lang=en,acodec=opus", (Some("313"), Some("250"))),
+            // Without a format of the language the best audio is taken.
+            ("lang=fr", (Some("313"), Some("251"))),
+            // Nothing fits the cap, which leaves the video to the progressive fallback.
+            ("height<=480", (None, Some("251"))),
+        ];
+        let video = test_video();
+        for (spec, (video_format_id, audio_format_id)) in cases {
+            let policy = FormatPolicy::parse(spec).unwrap();
+            assert_eq!(
+                policy.select_formats(&video),
+                (
+                    video_format_id.map(str::to_string),
+                    audio_format_id.map(str::to_string)
+                ),
+                "{}",
+                spec
+            );
+        }
+    }
+}
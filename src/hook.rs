@@ -0,0 +1,209 @@
+use std::fmt::Display;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use tracing::{info, warn};
+
+// Hook output beyond this is cut off so a chatty command cannot flood the UI.
+const MAX_OUTPUT_CHARS: usize = 4000;
+// A hook that hangs would otherwise keep the worker from starting the next job.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone)]
+pub struct HookOutcome {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub timed_out: bool,
+    pub output: String,
+}
+
+impl Display for HookOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.timed_out {
+            return write!(f, "Post-download hook timed out and was killed");
+        }
+        match (self.success, self.exit_code) {
+            (true, _) => write!(f, "Post-download hook succeeded"),
+            (false, Some(exit_code)) => {
+                write!(f, "Post-download hook failed with exit code {}", exit_code)
+            }
+            (false, None) => write!(f, "Post-download hook was terminated by a signal"),
+        }
+    }
+}
+
+// Placeholder values reach the shell through these variables instead of being pasted
+// into the command text, so no title can inject shell syntax.
+const PLACEHOLDER_VARIABLES: [(&str, &str); 3] = [
+    ("{path}", "DOWNLOAD_PATH"),
+    ("{id}", "VIDEO_ID"),
+    ("{title}", "VIDEO_TITLE"),
+];
+
+/// Replaces the `{path}`, `{id}` and `{title}` placeholders of a hook command template
+/// with quoted references to the variables `run_post_download_hook` sets.
+pub fn render_command(template: &str) -> String {
+    PLACEHOLDER_VARIABLES
+        .iter()
+        .fold(template.to_string(), |command, (placeholder, variable)| {
+            command.replace(placeholder, &variable_reference(variable))
+        })
+}
+
+#[cfg(not(windows))]
+fn variable_reference(variable: &str) -> String {
+    format!("\"${}\"", variable)
+}
+
+// Delayed `!VAR!` expansion happens after cmd has parsed the line, so `&`, `|`, `%`
+// or quotes in the value are never interpreted.
+#[cfg(windows)]
+fn variable_reference(variable: &str) -> String {
+    format!("\"!{}!\"", variable)
+}
+
+pub async fn run_post_download_hook(
+    template: &str,
+    path: &Path,
+    id: &str,
+    title: &str,
+) -> anyhow::Result<HookOutcome> {
+    run_hook(template, path, id, title, HOOK_TIMEOUT).await
+}
+
+async fn run_hook(
+    template: &str,
+    path: &Path,
+    id: &str,
+    title: &str,
+    timeout: Duration,
+) -> anyhow::Result<HookOutcome> {
+    let command = render_command(template);
+    info!("Running post-download hook: {}", command);
+
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = tokio::process::Command::new("sh");
+        shell.arg("-c").arg(&command);
+        shell
+    };
+    // The command is passed verbatim; cmd does not follow the quoting rules the
+    // standard library applies to arguments.
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = tokio::process::Command::new("cmd");
+        shell.raw_arg(format!("/V:ON /D /S /C \"{}\"", command));
+        shell
+    };
+    let values = [
+        path.display().to_string(),
+        id.to_string(),
+        title.to_string(),
+    ];
+    for ((_, variable), value) in PLACEHOLDER_VARIABLES.iter().zip(values) {
+        shell.env(variable, value);
+    }
+    shell.kill_on_drop(true);
+    let output = match tokio::time::timeout(timeout, shell.output()).await {
+        Ok(output) => output.with_context(|| format!("Failed to run hook `{}`", command))?,
+        Err(_) => {
+            let outcome = HookOutcome {
+                command,
+                exit_code: None,
+                success: false,
+                timed_out: true,
+                output: String::new(),
+            };
+            warn!("{} after {}s", outcome, timeout.as_secs());
+            return Ok(outcome);
+        }
+    };
+
+    let mut combined_output = String::from_utf8_lossy(&output.stdout).to_string();
+    combined_output.push_str(&String::from_utf8_lossy(&output.stderr));
+    if combined_output.chars().count() > MAX_OUTPUT_CHARS {
+        combined_output = combined_output.chars().take(MAX_OUTPUT_CHARS).collect();
+        combined_output.push_str("\n[output truncated]");
+    }
+
+    let outcome = HookOutcome {
+        command,
+        exit_code: output.status.code(),
+        success: output.status.success(),
+        timed_out: false,
+        output: combined_output.trim_end().to_string(),
+    };
+    match outcome.success {
+        true => info!("{}: {}", outcome, outcome.output),
+        false => warn!("{}: {}", outcome, outcome.output),
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(windows))]
+    #[test]
+    fn placeholders_become_quoted_variables() {
+        assert_eq!(
+            render_command("ffprobe {path} --id={id} {title}"),
+            "ffprobe \"$DOWNLOAD_PATH\" --id=\"$VIDEO_ID\" \"$VIDEO_TITLE\""
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn placeholders_become_quoted_variables() {
+        assert_eq!(
+            render_command("ffprobe {path} --id={id} {title}"),
+            "ffprobe \"!DOWNLOAD_PATH!\" --id=\"!VIDEO_ID!\" \"!VIDEO_TITLE!\""
+        );
+    }
+
+    #[tokio::test]
+    async fn hostile_values_stay_single_arguments() {
+        let title = "a\" & calc & \"'; echo pwned; '$(id)`id`%PATH%^|<>!";
+        #[cfg(not(windows))]
+        let template = "printf '%s|' {title} {id}";
+        #[cfg(windows)]
+        let template = "echo {title} {id}";
+
+        let outcome = run_post_download_hook(template, Path::new("video.mp4"), "id & x", title)
+            .await
+            .unwrap();
+
+        assert!(outcome.success, "{}", outcome.output);
+        #[cfg(not(windows))]
+        assert_eq!(outcome.output, format!("{}|id & x|", title));
+        #[cfg(windows)]
+        assert_eq!(outcome.output, format!("\"{}\" \"id & x\"", title));
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn hanging_hook_times_out_as_a_failure() {
+        let started_at = std::time::Instant::now();
+
+        let outcome = run_hook(
+            "sleep 30",
+            Path::new("video.mp4"),
+            "id",
+            "title",
+            Duration::from_millis(200),
+        )
+        .await
+        .unwrap();
+
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+        assert!(!outcome.success);
+        assert!(outcome.timed_out);
+        assert_eq!(
+            outcome.to_string(),
+            "Post-download hook timed out and was killed"
+        );
+    }
+}
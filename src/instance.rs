@@ -0,0 +1,196 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+#[cfg(unix)]
+use tracing::{info, warn};
+
+use crate::ui::message::Message as UIMessage;
+
+const INSTANCE_FILE_PREFIX: &str = "youtube_downloader";
+// A second launch may race the first one, which takes a moment to start listening.
+const FORWARD_ATTEMPTS: u32 = 10;
+const FORWARD_RETRY_DELAY: Duration = Duration::from_millis(300);
+
+/// What other processes (a second launch, the native-messaging host) can ask the
+/// running instance to do. Sent as one JSON line over the instance socket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InstanceRequest {
+    /// Queues the URLs, if any, and brings the window to the front.
+    QueueUrls(Vec<String>),
+}
+
+/// Per-user path for the instance lock and socket files.
+fn instance_file(extension: &str) -> PathBuf {
+    let file_name = format!("{}.{}", INSTANCE_FILE_PREFIX, extension);
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => PathBuf::from(runtime_dir).join(file_name),
+        None => std::env::temp_dir().join(format!(
+            "{}-{}",
+            std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default(),
+            file_name
+        )),
+    }
+}
+
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    instance_file("sock")
+}
+
+/// Held by the one running app instance for its whole lifetime; the OS releases
+/// the lock when the process exits, even after a crash.
+pub struct InstanceLock {
+    _file: Option<File>,
+}
+
+impl InstanceLock {
+    /// Returns `None` if another instance holds the lock. URLs can only be forwarded
+    /// on Unix, so elsewhere no lock is taken and every launch runs on its own.
+    pub fn acquire() -> anyhow::Result<Option<Self>> {
+        if cfg!(not(unix)) {
+            return Ok(Some(Self { _file: None }));
+        }
+        let file = File::create(instance_file("lock"))?;
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(Self { _file: Some(file) })),
+            Err(e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Hands URLs to the instance holding the lock, retrying while it starts up.
+pub fn forward_to_running_instance(urls: &[String]) -> anyhow::Result<()> {
+    let mut attempt = 1;
+    loop {
+        match forward_urls(urls) {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= FORWARD_ATTEMPTS => return Err(e),
+            Err(e) => debug!("Running instance not reachable yet: {}", e),
+        }
+        attempt += 1;
+        std::thread::sleep(FORWARD_RETRY_DELAY);
+    }
+}
+
+/// Hands URLs to the running instance. Fails if no instance is listening.
+#[cfg(unix)]
+pub fn forward_urls(urls: &[String]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let mut stream = std::os::unix::net::UnixStream::connect(socket_path())?;
+    let mut request = serde_json::to_string(&InstanceRequest::QueueUrls(urls.to_vec()))?;
+    request.push('\n');
+    stream.write_all(request.as_bytes())?;
+    debug!("Forwarded {} URL(s) to the running instance", urls.len());
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn forward_urls(_urls: &[String]) -> anyhow::Result<()> {
+    anyhow::bail!("Forwarding to a running instance is only supported on Unix")
+}
+
+/// Accepts requests from other processes and passes them to the UI. Must only be
+/// called while holding the [`InstanceLock`].
+#[cfg(unix)]
+pub async fn listen(to_ui: tokio::sync::broadcast::Sender<UIMessage>) -> anyhow::Result<()> {
+    use tokio::io::AsyncBufReadExt;
+
+    let socket_path = socket_path();
+    // We hold the lock, so a socket file can only be left over from a crash.
+    let _ = std::fs::remove_file(&socket_path);
+    let listener = tokio::net::UnixListener::bind(&socket_path)?;
+    info!("Listening for other instances on {}", socket_path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let mut lines = tokio::io::BufReader::new(stream).lines();
+        while let Some(line) = lines.next_line().await? {
+            match serde_json::from_str::<InstanceRequest>(&line) {
+                Ok(InstanceRequest::QueueUrls(urls)) => {
+                    debug!("Received {} URL(s) from another process", urls.len());
+                    let _ = to_ui.send(UIMessage::FocusWindow);
+                    if !urls.is_empty() {
+                        let _ = to_ui.send(UIMessage::QueueUrls(urls));
+                    }
+                }
+                Err(e) => warn!("Ignoring invalid instance request: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn listen(_to_ui: tokio::sync::broadcast::Sender<UIMessage>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_round_trip_as_json_lines() {
+        let request = InstanceRequest::QueueUrls(vec!["https://youtu.be/dQw4w9WgXcQ".to_string()]);
+
+        let line = serde_json::to_string(&request).unwrap();
+
+        assert_eq!(line, r#"{"QueueUrls":["https://youtu.be/dQw4w9WgXcQ"]}"#);
+        assert_eq!(
+            serde_json::from_str::<InstanceRequest>(&line).unwrap(),
+            request
+        );
+    }
+
+    // The only test touching the instance files, so changing the environment cannot
+    // affect another one.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn second_launch_forwards_urls_to_the_listening_instance() {
+        let runtime_dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-instance-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&runtime_dir);
+        std::fs::create_dir_all(&runtime_dir).unwrap();
+        std::env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+
+        let instance_lock = InstanceLock::acquire().unwrap();
+        assert!(instance_lock.is_some());
+        assert!(InstanceLock::acquire().unwrap().is_none());
+
+        let (to_ui, mut from_listener) = tokio::sync::broadcast::channel(16);
+        let listener = tokio::spawn(listen(to_ui));
+        let urls = vec!["https://youtu.be/dQw4w9WgXcQ".to_string()];
+        let forwarded_urls = urls.clone();
+        tokio::task::spawn_blocking(move || forward_to_running_instance(&forwarded_urls))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let messages = tokio::time::timeout(Duration::from_secs(5), async {
+            [
+                from_listener.recv().await.unwrap(),
+                from_listener.recv().await.unwrap(),
+            ]
+        })
+        .await
+        .unwrap();
+        assert!(matches!(messages[0], UIMessage::FocusWindow));
+        match &messages[1] {
+            UIMessage::QueueUrls(received_urls) => assert_eq!(received_urls, &urls),
+            message => panic!("unexpected message: {:?}", message),
+        }
+
+        listener.abort();
+        drop(instance_lock);
+        let _ = std::fs::remove_dir_all(&runtime_dir);
+    }
+}
@@ -0,0 +1,25 @@
+//! The downloader itself, shared by the app binary and the integration tests.
+
+pub mod api;
+pub mod backend;
+pub mod batch;
+pub mod cache;
+pub mod cli;
+pub mod disk_space;
+pub mod downloader;
+pub mod file_size;
+pub mod format_policy;
+pub mod hook;
+pub mod installer;
+pub mod instance;
+pub mod live;
+pub mod native_host;
+pub mod notifications;
+pub mod retry;
+pub mod settings;
+pub mod subscriptions;
+pub mod temp_dir;
+pub mod thumbnail;
+pub mod ui;
+pub mod video_url;
+pub mod worker;
@@ -0,0 +1,434 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Context};
+use tracing::{debug, info, warn};
+
+use crate::downloader::{get_video_info, VideoDownloader};
+use crate::file_size::format_bytes;
+use crate::temp_dir::{move_into_place, JobTempDir};
+use crate::worker::EventSender;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+// Streams often start late; give up waiting this long after the scheduled start.
+const START_GRACE_PERIOD: Duration = Duration::from_secs(2 * 60 * 60);
+// How long to wait for streams that do not report when they are scheduled.
+const UNSCHEDULED_MAX_WAIT: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveStatus {
+    NotLive,
+    IsLive,
+    IsUpcoming,
+    WasLive,
+    PostLive,
+}
+
+impl LiveStatus {
+    pub fn parse(live_status: &str) -> Self {
+        match live_status {
+            "is_live" => LiveStatus::IsLive,
+            "is_upcoming" => LiveStatus::IsUpcoming,
+            "was_live" => LiveStatus::WasLive,
+            "post_live" => LiveStatus::PostLive,
+            _ => LiveStatus::NotLive,
+        }
+    }
+
+    /// Live and upcoming streams have no finished media yet and must be recorded.
+    pub fn needs_recording(&self) -> bool {
+        matches!(self, LiveStatus::IsLive | LiveStatus::IsUpcoming)
+    }
+}
+
+impl Display for LiveStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LiveStatus::NotLive => write!(f, "Not live"),
+            LiveStatus::IsLive => write!(f, "Live now"),
+            LiveStatus::IsUpcoming => write!(f, "Scheduled stream or premiere"),
+            LiveStatus::WasLive => write!(f, "Finished live stream"),
+            LiveStatus::PostLive => write!(f, "Live stream being processed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordingMode {
+    #[default]
+    FromNow,
+    FromStart,
+}
+
+impl RecordingMode {
+    pub const ALL: [RecordingMode; 2] = [RecordingMode::FromNow, RecordingMode::FromStart];
+}
+
+impl Display for RecordingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingMode::FromNow => write!(f, "Record from now"),
+            RecordingMode::FromStart => write!(f, "Record from start"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RecordingOptions {
+    pub mode: RecordingMode,
+    pub max_duration: Option<Duration>,
+    pub max_size: Option<u64>,
+    pub wait_for_start: bool,
+}
+
+/// Time after which waiting for an upcoming stream is given up.
+pub fn wait_deadline(scheduled_start: Option<SystemTime>, now: SystemTime) -> SystemTime {
+    match scheduled_start {
+        Some(scheduled_start) => scheduled_start.max(now) + START_GRACE_PERIOD,
+        None => now + UNSCHEDULED_MAX_WAIT,
+    }
+}
+
+/// Polls the video info until a scheduled stream or premiere goes live, failing once
+/// `deadline` has passed.
+pub async fn wait_until_live(
+    video_downloader: &mut VideoDownloader,
+    events: &EventSender,
+    poll_interval: Duration,
+    deadline: SystemTime,
+) -> anyhow::Result<()> {
+    let mut checks = 0;
+    loop {
+        match LiveStatus::parse(&video_downloader.video_info.live_status) {
+            LiveStatus::IsLive => return Ok(()),
+            LiveStatus::IsUpcoming => {}
+            live_status => bail!("Stream is no longer upcoming: {}", live_status),
+        }
+
+        let remaining = match deadline.duration_since(SystemTime::now()) {
+            Ok(remaining) if !remaining.is_zero() => remaining,
+            _ => bail!(
+                "{} did not start in time, gave up after {} check(s)",
+                video_downloader.video_info.title,
+                checks
+            ),
+        };
+        let wait = poll_interval.min(remaining);
+        checks += 1;
+        events.status(format!(
+            "Waiting for {} to start (checked {} time(s), next check in {}s)...",
+            video_downloader.video_info.title,
+            checks,
+            wait.as_secs()
+        ));
+        tokio::time::sleep(wait).await;
+        if let Err(e) = get_video_info(video_downloader, events, true).await {
+            warn!("Failed to refresh info of upcoming stream: {}", e);
+        }
+    }
+}
+
+/// Records a live stream through the backend until the stream ends or one of the
+/// limits is reached.
+pub async fn record_live_stream(
+    video_downloader: &mut VideoDownloader,
+    options: &RecordingOptions,
+    events: &EventSender,
+) -> anyhow::Result<PathBuf> {
+    let backend = video_downloader.backend.clone();
+    if options.wait_for_start {
+        let scheduled_start = backend
+            .scheduled_start(&video_downloader.video_url)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to get the scheduled start: {}", e);
+                None
+            });
+        let deadline = wait_deadline(scheduled_start, SystemTime::now());
+        wait_until_live(video_downloader, events, DEFAULT_POLL_INTERVAL, deadline).await?;
+    }
+    if LiveStatus::parse(&video_downloader.video_info.live_status) != LiveStatus::IsLive {
+        bail!("{} is not live", video_downloader.video_info.title);
+    }
+
+    let job_temp_dir = JobTempDir::create(&video_downloader.temp_root)
+        .context("Failed to create temp dir for recording")?;
+    info!(
+        "Recording {} ({})...",
+        video_downloader.video_url, options.mode
+    );
+    let mut recording = backend
+        .record_live(
+            &video_downloader.video_url,
+            options.mode,
+            job_temp_dir.path(),
+        )
+        .await?;
+    let started_at = Instant::now();
+    let mut progress_interval = tokio::time::interval(PROGRESS_INTERVAL);
+
+    loop {
+        let limit_reached = tokio::select! {
+            result = recording.wait() => break result?,
+            _ = progress_interval.tick() => {
+                let elapsed = started_at.elapsed();
+                let recorded_size = recorded_size(job_temp_dir.path());
+                events.progress(recorded_size as f64, 0.0);
+                events.status(format!(
+                    "Recording: {} in {}",
+                    format_bytes(recorded_size),
+                    format_elapsed(elapsed)
+                ));
+
+                limit_reached(options, elapsed, recorded_size)
+            }
+        };
+        if limit_reached {
+            info!("Recording limit reached, stopping.");
+            break recording.stop().await?;
+        }
+    }
+    debug!("Recording ended.");
+
+    let recording_path = std::fs::read_dir(job_temp_dir.path())?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_stem()
+                .map(|file_stem| file_stem == "recording")
+                .unwrap_or(false)
+        })
+        .context("The stream ended without producing a recording")?;
+    let extension = recording_path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_string())
+        .unwrap_or("mp4".to_string());
+    let final_path = video_downloader
+        .target_dir()
+        .join(video_downloader.output_file_name.clone() + "." + &extension);
+    move_into_place(&recording_path, &final_path)
+        .with_context(|| format!("Failed to move recording to {}", final_path.display()))?;
+    video_downloader.video_path = Some(final_path.clone());
+    info!("Recording saved to {}.", final_path.display());
+
+    Ok(final_path)
+}
+
+fn limit_reached(options: &RecordingOptions, elapsed: Duration, recorded_size: u64) -> bool {
+    options
+        .max_duration
+        .map_or(false, |max_duration| elapsed >= max_duration)
+        || options
+            .max_size
+            .map_or(false, |max_size| recorded_size >= max_size)
+}
+
+fn recorded_size(job_dir: &Path) -> u64 {
+    std::fs::read_dir(job_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+
+    const VIDEO_URL: &str = "https://www.youtube.com/watch?v=jfKfPfyJRdk";
+
+    fn live_downloader(name: &str, backend: Arc<FakeBackend>) -> (PathBuf, VideoDownloader) {
+        let dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-live-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut video_downloader =
+            VideoDownloader::new("libs", &dir.join("output").display().to_string(), VIDEO_URL)
+                .unwrap()
+                .with_backend(backend);
+        video_downloader.temp_root = dir.join("temp");
+        video_downloader.output_file_name = "Live Stream".to_string();
+        video_downloader.video_info.title = "Live Stream".to_string();
+        video_downloader.video_info.live_status = "is_live".to_string();
+        (dir, video_downloader)
+    }
+
+    #[test]
+    fn parses_live_status() {
+        let cases = [
+            ("is_live", LiveStatus::IsLive, true),
+            ("is_upcoming", LiveStatus::IsUpcoming, true),
+            ("was_live", LiveStatus::WasLive, false),
+            ("post_live", LiveStatus::PostLive, false),
+            ("not_live", LiveStatus::NotLive, false),
+            ("", LiveStatus::NotLive, false),
+        ];
+        for (live_status, expected, needs_recording) in cases {
+            assert_eq!(LiveStatus::parse(live_status), expected, "{}", live_status);
+            assert_eq!(
+                expected.needs_recording(),
+                needs_recording,
+                "{}",
+                live_status
+            );
+        }
+    }
+
+    #[test]
+    fn stops_at_the_first_limit_reached() {
+        let options = RecordingOptions {
+            max_duration: Some(Duration::from_secs(60)),
+            max_size: Some(1000),
+            ..RecordingOptions::default()
+        };
+        let cases = [
+            (59, 999, false),
+            (60, 0, true),
+            (0, 1000, true),
+            (61, 1001, true),
+        ];
+        for (elapsed, recorded_size, expected) in cases {
+            assert_eq!(
+                limit_reached(&options, Duration::from_secs(elapsed), recorded_size),
+                expected,
+                "{}s, {} bytes",
+                elapsed,
+                recorded_size
+            );
+        }
+        assert!(!limit_reached(
+            &RecordingOptions::default(),
+            Duration::from_secs(u32::MAX as u64),
+            u64::MAX
+        ));
+    }
+
+    #[test]
+    fn recorded_size_sums_the_job_dir() {
+        let dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-recorded-size-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(recorded_size(&dir), 0);
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("recording.mp4.part-Frag1"), vec![0; 300]).unwrap();
+        std::fs::write(dir.join("recording.mp4.part-Frag2"), vec![0; 200]).unwrap();
+        assert_eq!(recorded_size(&dir), 500);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn waits_until_the_scheduled_start_plus_grace_period() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let scheduled_start = now + Duration::from_secs(600);
+        assert_eq!(
+            wait_deadline(Some(scheduled_start), now),
+            scheduled_start + START_GRACE_PERIOD
+        );
+        // A start time in the past still gets the grace period from now.
+        assert_eq!(
+            wait_deadline(Some(now - Duration::from_secs(600)), now),
+            now + START_GRACE_PERIOD
+        );
+        assert_eq!(wait_deadline(None, now), now + UNSCHEDULED_MAX_WAIT);
+    }
+
+    #[tokio::test]
+    async fn waiting_fails_once_the_deadline_passed() {
+        let (dir, mut video_downloader) =
+            live_downloader("deadline", Arc::new(FakeBackend::default()));
+        video_downloader.video_info.title = "Premiere".to_string();
+        video_downloader.video_info.live_status = "is_upcoming".to_string();
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let events = EventSender::new(1, tx);
+
+        let error = wait_until_live(
+            &mut video_downloader,
+            &events,
+            DEFAULT_POLL_INTERVAL,
+            SystemTime::now() - Duration::from_secs(1),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            error.to_string().contains("did not start in time"),
+            "unexpected error: {}",
+            error
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn records_until_the_size_limit_and_moves_the_recording_into_place() {
+        let backend = Arc::new(
+            FakeBackend::default()
+                .with_live_stream(VIDEO_URL, b"segments")
+                .with_scheduled_start(VIDEO_URL, SystemTime::now()),
+        );
+        let (dir, mut video_downloader) = live_downloader("record", backend.clone());
+        let options = RecordingOptions {
+            mode: RecordingMode::FromStart,
+            max_size: Some(8),
+            wait_for_start: true,
+            ..RecordingOptions::default()
+        };
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let events = EventSender::new(1, tx);
+
+        let recording_path = record_live_stream(&mut video_downloader, &options, &events)
+            .await
+            .unwrap();
+
+        assert_eq!(recording_path, dir.join("output").join("Live Stream.mp4"));
+        assert_eq!(std::fs::read(&recording_path).unwrap(), b"segments");
+        assert_eq!(video_downloader.video_path, Some(recording_path));
+        assert_eq!(
+            *backend.recordings.lock().unwrap(),
+            vec![(VIDEO_URL.to_string(), RecordingMode::FromStart)]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn refuses_to_record_a_finished_stream() {
+        let backend = Arc::new(FakeBackend::default().with_live_stream(VIDEO_URL, b"segments"));
+        let (dir, mut video_downloader) = live_downloader("finished", backend.clone());
+        video_downloader.video_info.live_status = "was_live".to_string();
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let events = EventSender::new(1, tx);
+
+        let error =
+            record_live_stream(&mut video_downloader, &RecordingOptions::default(), &events)
+                .await
+                .unwrap_err();
+
+        assert_eq!(error.to_string(), "Live Stream is not live");
+        assert!(backend.recordings.lock().unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,139 +1,145 @@
-mod downloader;
-mod installer;
-mod ui;
+use youtube_downloader::{
+    api, cli, downloader, instance, native_host, settings, subscriptions, temp_dir, ui, worker,
+};
 
-use tracing::{debug, warn};
+use clap::Parser;
+use tracing::{error, info, warn};
 use tracing_subscriber::filter::EnvFilter;
 use ui::{
     message::Message as UIMessage, state::DownloaderUIState,
     subscription::subscription as ui_subscription,
 };
 
-use downloader::{change_video_url, download_video, get_video_info, get_video_thumbnail};
-
 fn main() -> iced::Result {
-    tracing_subscriber::fmt()
+    let args: Vec<String> = std::env::args().collect();
+    let is_native_host = native_host::is_browser_launch(&args);
+
+    let tracing_builder = tracing_subscriber::fmt()
         .with_max_level(tracing::Level::DEBUG)
         .with_target(false)
-        .with_env_filter(EnvFilter::new("iced=off,youtube_downloader=debug"))
-        .init();
+        .with_env_filter(EnvFilter::new("iced=off,youtube_downloader=debug"));
+    // As a native-messaging host stdout carries the messages to the browser.
+    match is_native_host {
+        true => tracing_builder.with_writer(std::io::stderr).init(),
+        false => tracing_builder.init(),
+    }
+
+    if is_native_host {
+        std::process::exit(native_host::run());
+    }
+
+    let cli = cli::Cli::parse_from(&args);
+    if let Some(command) = cli.command {
+        std::process::exit(cli::run_command(command));
+    }
+    let startup_urls = cli.urls;
+
+    // Keep the lock until the app exits so later launches forward to this instance.
+    let _instance_lock = match instance::InstanceLock::acquire() {
+        Ok(Some(instance_lock)) => Some(instance_lock),
+        Ok(None) => match instance::forward_to_running_instance(&startup_urls) {
+            Ok(()) => {
+                info!("Already running, passed the URLs to the running instance.");
+                return Ok(());
+            }
+            Err(e) => {
+                error!(
+                    "Video Downloader is already running but not reachable: {}",
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        Err(e) => {
+            warn!("Could not take the single-instance lock: {}", e);
+            None
+        }
+    };
 
-    let (ui_to_worker_tx, mut worker_from_ui_rx_1) =
-        tokio::sync::broadcast::channel::<ui::message::Message>(16);
-    let (worker_to_ui_tx, mut ui_from_worker_rx_1) =
-        tokio::sync::broadcast::channel::<ui::message::Message>(16);
+    // Messages for the UI from outside the app, like URLs from other instances.
+    let (to_ui_tx, _) = tokio::sync::broadcast::channel::<UIMessage>(16);
 
-    let worker_to_ui_tx_2 = worker_to_ui_tx.clone();
-    let mut worker_from_ui_rx_2 = ui_to_worker_tx.subscribe();
+    let mut settings = settings::Settings::load();
+    temp_dir::cleanup_orphaned_temp_dirs(&settings.temp_dir);
 
+    let mut video_downloader = match downloader::VideoDownloader::new(
+        "libs",
+        &settings.output_dir.display().to_string(),
+        "",
+    ) {
+        Ok(video_downloader) => video_downloader,
+        Err(e) => {
+            error!("Failed to set up the downloader: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    video_downloader.organize_by_extractor = settings.organize_by_extractor;
+    video_downloader.temp_root = settings.temp_dir.clone();
+    video_downloader.post_download_hook = settings.post_download_hook.clone();
+    let worker = worker::spawn(video_downloader, settings.notifications.clone());
+
+    if settings.api.enabled {
+        if settings.api.token.is_empty() {
+            settings.api.token = api::generate_token();
+            if let Err(e) = settings.save() {
+                warn!("Failed to save generated API token: {}", e);
+            }
+            info!("Generated control API token: {}", settings.api.token);
+        }
+        let api_settings = settings.api.clone();
+        let api_worker = worker.clone();
+        std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async move {
+                    if let Err(e) = api::serve(api_settings, api_worker).await {
+                        warn!("Control API stopped: {}", e);
+                    }
+                });
+        });
+    }
+
+    let instance_to_ui_tx = to_ui_tx.clone();
     std::thread::spawn(move || {
         tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()
             .unwrap()
             .block_on(async move {
-                let mut video_downloader = downloader::VideoDownloader::default();
-                let worker_to_ui_tx_3 = worker_to_ui_tx.clone();
-                loop {
-                    let result_msg = worker_from_ui_rx_2.recv().await;
-                    match result_msg {
-                        Ok(UIMessage::InstallLibraries) => {
-                            debug!("Worker thread received InstallLibraries message");
-                            let _ =
-                                match installer::install(&video_downloader.executables_dir).await {
-                                    Ok((_ffmpeg_path, _yt_dlp_path)) => {
-                                        worker_to_ui_tx_3.send(UIMessage::LibrariesInstalled)
-                                    }
-                                    Err(e) => worker_to_ui_tx_3.send(UIMessage::StatusMessage(
-                                        format!("Failed to install libraries: {}", e),
-                                    )),
-                                };
-                        }
-                        Ok(UIMessage::UpdateLibraries) => {
-                            debug!("Worker thread received UpdateLibraries message");
-                            let _ = match installer::update(&video_downloader.executables_dir).await
-                            {
-                                Ok(_yt_dlp_path) => {
-                                    worker_to_ui_tx_3.send(UIMessage::LibrariesUpdated)
-                                }
-                                Err(e) => worker_to_ui_tx_3.send(UIMessage::StatusMessage(
-                                    format!("Failed to update libraries: {}", e),
-                                )),
-                            };
-                        }
-                        Ok(UIMessage::UrlChanged(url)) => {
-                            debug!("Worker thread received UrlChanged message: {}", url);
-                            change_video_url(&mut video_downloader, url);
-                        }
-                        Ok(UIMessage::FetchInfo) => {
-                            debug!("Worker thread received FetchInfo message.");
-                            let _ = match get_video_info(&mut video_downloader).await {
-                                Ok(video_info) => {
-                                    worker_to_ui_tx_3.send(UIMessage::InfoFetched(video_info))
-                                }
-                                Err(e) => worker_to_ui_tx_3.send(UIMessage::StatusMessage(
-                                    format!("Failed to fetch video info: {}", e),
-                                )),
-                            };
-                        }
-                        Ok(UIMessage::FetchThumbnail) => {
-                            debug!("Worker thread received FetchThumbnail message.");
-                            let _ = match get_video_thumbnail(&mut video_downloader).await {
-                                Ok(path) => {
-                                    worker_to_ui_tx_3.send(UIMessage::ThumbnailFetched(Some(path)))
-                                }
-                                Err(e) => worker_to_ui_tx_3.send(UIMessage::StatusMessage(
-                                    format!("Failed to fetch video thumbnail: {}", e),
-                                )),
-                            };
-                        }
-                        Ok(UIMessage::SelectAudioFormat(format)) => {
-                            video_downloader.selected_audio_format = Some(format.format_id);
-                        }
-                        Ok(UIMessage::SelectVideoFormat(format)) => {
-                            video_downloader.selected_video_format = Some(format.format_id);
-                        }
-                        Ok(UIMessage::DownloadVideo) => {
-                            debug!("Worker thread received DownloadVideo message.");
-                            let worker_to_ui_tx_4 = worker_to_ui_tx.clone();
-                            let _ = match download_video(&mut video_downloader, &worker_to_ui_tx_4)
-                                .await
-                            {
-                                Ok(path) => {
-                                    worker_to_ui_tx_3.send(UIMessage::VideoDownloaded(Some(path)))
-                                }
-                                Err(e) => worker_to_ui_tx_3.send(UIMessage::StatusMessage(
-                                    format!("Failed to download video: {}", e),
-                                )),
-                            };
-                        }
-                        Ok(UIMessage::Number(number)) => {
-                            debug!("Worker thread received number: {}", number);
-                        }
-                        Ok(msg) => {
-                            warn!("Worker thread received unknown message: {:?}", msg);
-                        }
-                        Err(e) => {
-                            warn!("Worker thread received error: {}", e);
-                        }
-                    };
+                if let Err(e) = instance::listen(instance_to_ui_tx).await {
+                    warn!("Not accepting URLs from other processes: {}", e);
                 }
-            })
+            });
     });
 
+    let subscription_worker = worker.clone();
     let iced_application =
-        iced::application("YouTube Downloader", ui::update::update, ui::view::view)
-            .subscription(move |_| ui_subscription(worker_to_ui_tx_2.clone()))
+        iced::application("Video Downloader", ui::update::update, ui::view::view)
+            .subscription(move |downloader_ui_state| {
+                ui_subscription(
+                    downloader_ui_state,
+                    subscription_worker.clone(),
+                    to_ui_tx.clone(),
+                )
+            })
             .theme(|_| iced::Theme::Dark)
             .centered();
     let _ = iced_application.run_with(|| {
         (
             DownloaderUIState {
-                sender: Some(ui_to_worker_tx),
-                reciever: Some(ui_from_worker_rx_1),
+                output_dir: settings.output_dir.display().to_string(),
+                post_download_hook: settings.post_download_hook.clone().unwrap_or_default(),
+                notification_settings: settings.notifications.clone(),
+                subscriptions: subscriptions::SubscriptionStore::load().subscriptions,
+                worker: Some(worker),
                 ..Default::default()
             },
-            iced::Task::none(),
+            match startup_urls.is_empty() {
+                true => iced::Task::none(),
+                false => iced::Task::done(UIMessage::QueueUrls(startup_urls)),
+            },
         )
     });
 
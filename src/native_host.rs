@@ -0,0 +1,273 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::instance::{forward_to_running_instance, InstanceLock};
+use crate::video_url::parse_video_url;
+
+pub const HOST_NAME: &str = "com.youtube_downloader.host";
+// Browsers reject messages from the host larger than this.
+const MAX_RESPONSE_SIZE: usize = 1024 * 1024;
+// Requests only carry a URL, anything much larger is not from our extension.
+const MAX_REQUEST_SIZE: u32 = 64 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct HostRequest {
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Delivery {
+    Forwarded,
+    Launched,
+}
+
+#[derive(Debug, Serialize)]
+struct HostResponse {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delivery: Option<Delivery>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Chrome starts a native-messaging host with the extension origin as its only
+/// argument, plus `--parent-window` on Windows. Firefox passes the absolute path of
+/// the host manifest followed by the extension id.
+pub fn is_browser_launch(args: &[String]) -> bool {
+    match args {
+        [_, origin] => is_extension_origin(origin),
+        [_, origin, parent_window] if parent_window.starts_with("--parent-window=") => {
+            is_extension_origin(origin)
+        }
+        [_, manifest_path, extension_id] => {
+            Path::new(manifest_path).is_absolute()
+                && manifest_path.ends_with(".json")
+                && is_firefox_extension_id(extension_id)
+        }
+        _ => false,
+    }
+}
+
+fn is_extension_origin(arg: &str) -> bool {
+    arg.strip_prefix("chrome-extension://")
+        .and_then(|origin| origin.strip_suffix('/'))
+        .map_or(false, |extension_id| {
+            !extension_id.is_empty() && extension_id.chars().all(|c| c.is_ascii_lowercase())
+        })
+}
+
+/// Firefox extension ids are either email-like (`name@example.com`) or a braced UUID.
+fn is_firefox_extension_id(arg: &str) -> bool {
+    let is_uuid = arg.len() == 38
+        && arg.starts_with('{')
+        && arg.ends_with('}')
+        && arg[1..37]
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || c == '-');
+    let is_email_like = arg.split_once('@').map_or(false, |(name, domain)| {
+        !name.is_empty()
+            && !domain.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-+@".contains(c))
+    });
+    is_uuid || is_email_like
+}
+
+/// Host manifest to install for the browser, pointing at this executable.
+pub fn manifest(extension_id: &str, firefox: bool) -> anyhow::Result<String> {
+    let mut manifest = serde_json::json!({
+        "name": HOST_NAME,
+        "description": "Send videos to Video Downloader",
+        "path": std::env::current_exe()?,
+        "type": "stdio",
+    });
+    match firefox {
+        true => manifest["allowed_extensions"] = serde_json::json!([extension_id]),
+        false => {
+            manifest["allowed_origins"] =
+                serde_json::json!([format!("chrome-extension://{}/", extension_id)])
+        }
+    }
+    Ok(serde_json::to_string_pretty(&manifest)?)
+}
+
+/// Answers messages from the browser extension until the browser closes stdin.
+/// Messages are JSON prefixed with their length as a native-endian u32.
+pub fn run() -> i32 {
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    loop {
+        let response = match read_message(&mut stdin) {
+            Ok(Some(request)) => handle_request(request),
+            Ok(None) => return 0,
+            Err(e) => {
+                warn!("Failed to read native message: {}", e);
+                return 1;
+            }
+        };
+        if let Err(e) = write_message(&mut stdout, &response) {
+            warn!("Failed to write native message: {}", e);
+            return 1;
+        }
+    }
+}
+
+fn read_message(reader: &mut impl Read) -> anyhow::Result<Option<HostRequest>> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let length = u32::from_ne_bytes(length);
+    if length > MAX_REQUEST_SIZE {
+        anyhow::bail!("Native message of {} bytes is too large", length);
+    }
+    let mut message = vec![0u8; length as usize];
+    reader.read_exact(&mut message)?;
+    Ok(Some(serde_json::from_slice(&message)?))
+}
+
+fn write_message(writer: &mut impl Write, response: &HostResponse) -> anyhow::Result<()> {
+    let message = serde_json::to_vec(response)?;
+    if message.len() > MAX_RESPONSE_SIZE {
+        anyhow::bail!("Native message of {} bytes is too large", message.len());
+    }
+    writer.write_all(&(message.len() as u32).to_ne_bytes())?;
+    writer.write_all(&message)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn handle_request(request: HostRequest) -> HostResponse {
+    debug!("Native host received {}", request.url);
+    let url = match parse_video_url(&request.url) {
+        Ok(video_url) => video_url.normalized(),
+        Err(e) => {
+            return HostResponse {
+                ok: false,
+                delivery: None,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+
+    // The lock is only probed here and released before launching, so the new
+    // instance can take it.
+    let is_running = matches!(InstanceLock::acquire(), Ok(None));
+    let delivery = match is_running {
+        true => forward_to_running_instance(&[url.clone()]).map(|_| Delivery::Forwarded),
+        false => {
+            debug!("No running instance, launching one");
+            launch_app(&url).map(|_| Delivery::Launched)
+        }
+    };
+    match delivery {
+        Ok(delivery) => HostResponse {
+            ok: true,
+            delivery: Some(delivery),
+            error: None,
+        },
+        Err(e) => HostResponse {
+            ok: false,
+            delivery: None,
+            error: Some(format!("Failed to reach the downloader: {}", e)),
+        },
+    }
+}
+
+/// Starts the app with the URL queued. Its output must not reach our stdout, which
+/// belongs to the browser.
+fn launch_app(url: &str) -> anyhow::Result<()> {
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // Browsers kill the host's process group when the extension disconnects.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let child = command.spawn()?;
+    info!("Launched downloader (pid {}) for {}", child.id(), url);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn detects_browser_launches_by_their_exact_arguments() {
+        #[cfg(not(windows))]
+        let manifest_path =
+            "/home/user/.mozilla/native-messaging-hosts/com.youtube_downloader.host.json";
+        #[cfg(windows)]
+        let manifest_path = r"C:\Users\user\com.youtube_downloader.host.json";
+        let cases = [
+            (args(&["app", "chrome-extension://abcdefghijklmnop/"]), true),
+            (
+                args(&[
+                    "app",
+                    "chrome-extension://abcdefghijklmnop/",
+                    "--parent-window=0",
+                ]),
+                true,
+            ),
+            (
+                args(&["app", manifest_path, "downloader@example.com"]),
+                true,
+            ),
+            (
+                args(&[
+                    "app",
+                    manifest_path,
+                    "{2b3c4d5e-6f70-8192-a3b4-c5d6e7f80912}",
+                ]),
+                true,
+            ),
+            (args(&["app"]), false),
+            (args(&["app", "https://youtu.be/dQw4w9WgXcQ"]), false),
+            (args(&["app", "chrome-extension://"]), false),
+            (
+                args(&[
+                    "app",
+                    "https://youtu.be/dQw4w9WgXcQ",
+                    "chrome-extension://abc/",
+                ]),
+                false,
+            ),
+            // A batch file and a URL, as a user would pass them.
+            (
+                args(&["app", "urls.json", "https://youtu.be/dQw4w9WgXcQ"]),
+                false,
+            ),
+            (
+                args(&["app", manifest_path, "https://youtu.be/dQw4w9WgXcQ"]),
+                false,
+            ),
+            (args(&["app", manifest_path, "not an id"]), false),
+            (
+                args(&["app", manifest_path, "https://user@example.com/watch"]),
+                false,
+            ),
+            (
+                args(&["app", manifest_path, "downloader@example.com", "extra"]),
+                false,
+            ),
+        ];
+        for (args, expected) in cases {
+            assert_eq!(is_browser_launch(&args), expected, "{:?}", args);
+        }
+    }
+}
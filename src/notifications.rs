@@ -0,0 +1,323 @@
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+const APP_NAME: &str = "Video Downloader";
+// Let the notification server pick its default timeout.
+#[cfg(target_os = "linux")]
+const DEFAULT_EXPIRE_TIMEOUT: i32 = -1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationEvent {
+    DownloadFinished,
+    DownloadFailed,
+    QueueDrained,
+}
+
+impl NotificationEvent {
+    /// Urgency hint of the freedesktop notification spec: 1 is normal, 2 critical.
+    #[cfg(target_os = "linux")]
+    fn urgency(&self) -> u8 {
+        match self {
+            NotificationEvent::DownloadFailed => 2,
+            NotificationEvent::DownloadFinished | NotificationEvent::QueueDrained => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    pub download_finished: bool,
+    pub download_failed: bool,
+    pub queue_drained: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            download_finished: true,
+            download_failed: true,
+            queue_drained: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    pub fn is_enabled(&self, event: NotificationEvent) -> bool {
+        self.enabled
+            && match event {
+                NotificationEvent::DownloadFinished => self.download_finished,
+                NotificationEvent::DownloadFailed => self.download_failed,
+                NotificationEvent::QueueDrained => self.queue_drained,
+            }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: std::collections::HashMap<&str, zbus::zvariant::Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Sends desktop notifications through the freedesktop notification interface.
+/// Without a reachable bus it logs and does nothing, so callers never have to care.
+pub struct Notifier {
+    pub settings: NotificationSettings,
+    #[cfg(target_os = "linux")]
+    connection: Option<zbus::Connection>,
+}
+
+impl Notifier {
+    #[cfg(target_os = "linux")]
+    pub async fn session(settings: NotificationSettings) -> Self {
+        match zbus::Connection::session().await {
+            Ok(connection) => Self::with_connection(connection, settings),
+            Err(e) => {
+                warn!("Desktop notifications unavailable: {}", e);
+                Self {
+                    settings,
+                    connection: None,
+                }
+            }
+        }
+    }
+
+    /// Uses an existing connection, e.g. to a private bus running a mock
+    /// notification server.
+    #[cfg(target_os = "linux")]
+    pub fn with_connection(connection: zbus::Connection, settings: NotificationSettings) -> Self {
+        Self {
+            settings,
+            connection: Some(connection),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub async fn session(settings: NotificationSettings) -> Self {
+        Self { settings }
+    }
+
+    pub async fn notify(&self, event: NotificationEvent, summary: &str, body: &str) {
+        if !self.settings.is_enabled(event) {
+            debug!("Notification for {:?} is disabled", event);
+            return;
+        }
+        if let Err(e) = self.send(event, summary, body).await {
+            warn!("Failed to send desktop notification: {}", e);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn send(
+        &self,
+        event: NotificationEvent,
+        summary: &str,
+        body: &str,
+    ) -> anyhow::Result<()> {
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => return Ok(()),
+        };
+        // The interface has no properties, and a private bus cannot track them anyway.
+        let proxy = NotificationsProxy::builder(connection)
+            .cache_properties(zbus::proxy::CacheProperties::No)
+            .build()
+            .await?;
+        let hints = std::collections::HashMap::from([(
+            "urgency",
+            zbus::zvariant::Value::from(event.urgency()),
+        )]);
+        let notification_id = proxy
+            .notify(
+                APP_NAME,
+                0,
+                "",
+                summary,
+                body,
+                &[],
+                hints,
+                DEFAULT_EXPIRE_TIMEOUT,
+            )
+            .await?;
+        debug!("Sent desktop notification {}", notification_id);
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn send(
+        &self,
+        _event: NotificationEvent,
+        summary: &str,
+        _body: &str,
+    ) -> anyhow::Result<()> {
+        debug!(
+            "Desktop notifications are not supported on this platform: {} ({})",
+            summary, APP_NAME
+        );
+        Ok(())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use zbus::zvariant::{OwnedValue, Value};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Received {
+        summary: String,
+        body: String,
+        urgency: Option<u8>,
+    }
+
+    /// Notification server recording what it was asked to show.
+    struct MockNotifications {
+        received: Arc<Mutex<Vec<Received>>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl MockNotifications {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: String,
+            _replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            body: String,
+            _actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let urgency = hints.get("urgency").and_then(|urgency| match &**urgency {
+                Value::U8(urgency) => Some(*urgency),
+                _ => None,
+            });
+            let mut received = self.received.lock().unwrap();
+            received.push(Received {
+                summary,
+                body,
+                urgency,
+            });
+            received.len() as u32
+        }
+    }
+
+    /// A notifier talking to a mock server over a private peer-to-peer bus.
+    async fn mock_notifier(
+        settings: NotificationSettings,
+    ) -> (Notifier, zbus::Connection, Arc<Mutex<Vec<Received>>>) {
+        let (client_stream, server_stream) = tokio::net::UnixStream::pair().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server = zbus::connection::Builder::unix_stream(server_stream)
+            .server(zbus::Guid::generate())
+            .unwrap()
+            .p2p()
+            .serve_at(
+                "/org/freedesktop/Notifications",
+                MockNotifications {
+                    received: received.clone(),
+                },
+            )
+            .unwrap()
+            .build();
+        let client = zbus::connection::Builder::unix_stream(client_stream)
+            .p2p()
+            .build();
+        let (server, client) = tokio::try_join!(server, client).unwrap();
+        (
+            Notifier::with_connection(client, settings),
+            server,
+            received,
+        )
+    }
+
+    #[tokio::test]
+    async fn sends_summary_body_and_urgency() {
+        let (notifier, _server, received) = mock_notifier(NotificationSettings::default()).await;
+
+        notifier
+            .notify(
+                NotificationEvent::DownloadFinished,
+                "Download finished",
+                "Video was saved to output/Video.mp4",
+            )
+            .await;
+        notifier
+            .notify(
+                NotificationEvent::DownloadFailed,
+                "Download failed",
+                "Video: HTTP 403 Forbidden",
+            )
+            .await;
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![
+                Received {
+                    summary: "Download finished".to_string(),
+                    body: "Video was saved to output/Video.mp4".to_string(),
+                    urgency: Some(1),
+                },
+                Received {
+                    summary: "Download failed".to_string(),
+                    body: "Video: HTTP 403 Forbidden".to_string(),
+                    urgency: Some(2),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_settings_suppress_notifications() {
+        let (notifier, _server, received) = mock_notifier(NotificationSettings {
+            enabled: false,
+            ..NotificationSettings::default()
+        })
+        .await;
+        notifier
+            .notify(NotificationEvent::DownloadFailed, "Download failed", "")
+            .await;
+        assert!(received.lock().unwrap().is_empty());
+
+        let (notifier, _server, received) = mock_notifier(NotificationSettings {
+            download_finished: false,
+            ..NotificationSettings::default()
+        })
+        .await;
+        notifier
+            .notify(NotificationEvent::DownloadFinished, "Download finished", "")
+            .await;
+        notifier
+            .notify(NotificationEvent::QueueDrained, "Batch finished", "")
+            .await;
+        let summaries: Vec<String> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|received| received.summary.clone())
+            .collect();
+        assert_eq!(summaries, vec!["Batch finished"]);
+    }
+}
@@ -0,0 +1,394 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+use rand::Rng;
+use regex::Regex;
+use reqwest::{header::RETRY_AFTER, StatusCode};
+use tracing::{debug, warn};
+
+const TRANSIENT_ERROR_MARKERS: [&str; 9] = [
+    "timed out",
+    "timeout",
+    "connection reset",
+    "connection refused",
+    "connection aborted",
+    "broken pipe",
+    "temporary failure in name resolution",
+    "network is unreachable",
+    "unexpected eof",
+];
+
+// yt-dlp reports HTTP failures as e.g. `HTTP Error 503: Service Unavailable`.
+const YT_DLP_HTTP_ERROR_PATTERN: &str = r"(?i)\bhttp error (\d{3})\b";
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    pub jitter: f64,
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.25,
+            max_retry_after: Duration::from_secs(120),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryAttempt {
+    pub operation: String,
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay: Duration,
+    pub reason: String,
+}
+
+impl Display for RetryAttempt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} failed ({}), retrying in {:.1}s (attempt {}/{})",
+            self.operation,
+            self.reason,
+            self.delay.as_secs_f64(),
+            self.attempt + 1,
+            self.max_attempts
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetryDecision {
+    Retry(Option<Duration>),
+    GiveUp,
+}
+
+/// Non-success HTTP response, keeping the `Retry-After` hint so the retry loop can honour it.
+#[derive(Debug)]
+pub struct HttpStatusError {
+    pub url: String,
+    pub status: StatusCode,
+    pub retry_after: Option<Duration>,
+}
+
+impl Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP {} for {}", self.status, self.url)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+pub fn error_for_status(
+    url: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, HttpStatusError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, SystemTime::now()));
+
+    Err(HttpStatusError {
+        url: url.to_string(),
+        status,
+        retry_after,
+    })
+}
+
+/// Parses a `Retry-After` value, either delay seconds or an HTTP-date.
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let retry_at = httpdate::parse_http_date(value).ok()?;
+    // A date in the past means retrying right away.
+    Some(retry_at.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+/// Status of an HTTP failure reported in yt-dlp's error text.
+fn yt_dlp_http_status(message: &str) -> Option<StatusCode> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(YT_DLP_HTTP_ERROR_PATTERN).unwrap());
+    let code = re.captures(message)?.get(1)?.as_str().parse::<u16>().ok()?;
+    StatusCode::from_u16(code).ok()
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+pub fn classify(error: &anyhow::Error) -> RetryDecision {
+    for cause in error.chain() {
+        if let Some(http_error) = cause.downcast_ref::<HttpStatusError>() {
+            return match is_retryable_status(http_error.status) {
+                true => RetryDecision::Retry(http_error.retry_after),
+                false => RetryDecision::GiveUp,
+            };
+        }
+
+        if let Some(reqwest_error) = cause.downcast_ref::<reqwest::Error>() {
+            if let Some(status) = reqwest_error.status() {
+                return match is_retryable_status(status) {
+                    true => RetryDecision::Retry(None),
+                    false => RetryDecision::GiveUp,
+                };
+            }
+            if reqwest_error.is_timeout()
+                || reqwest_error.is_connect()
+                || reqwest_error.is_request()
+                || reqwest_error.is_body()
+            {
+                return RetryDecision::Retry(None);
+            }
+            return RetryDecision::GiveUp;
+        }
+
+        if let Some(io_error) = cause.downcast_ref::<std::io::Error>() {
+            match io_error.kind() {
+                std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::UnexpectedEof => return RetryDecision::Retry(None),
+                _ => {}
+            }
+        }
+    }
+
+    // yt-dlp only reports network failures through its error text.
+    let message = format!("{:#}", error).to_lowercase();
+    if let Some(status) = yt_dlp_http_status(&message) {
+        return match is_retryable_status(status) {
+            true => RetryDecision::Retry(None),
+            false => RetryDecision::GiveUp,
+        };
+    }
+    if TRANSIENT_ERROR_MARKERS
+        .iter()
+        .any(|marker| message.contains(marker))
+    {
+        return RetryDecision::Retry(None);
+    }
+
+    RetryDecision::GiveUp
+}
+
+impl RetryPolicy {
+    pub fn backoff_delay(&self, failed_attempt: u32) -> Duration {
+        let exponent = failed_attempt.saturating_sub(1) as i32;
+        let base = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = base.min(self.max_delay.as_secs_f64());
+        let jitter = if self.jitter > 0.0 {
+            rand::thread_rng().gen_range(-self.jitter..=self.jitter)
+        } else {
+            0.0
+        };
+        Duration::from_secs_f64((capped * (1.0 + jitter)).max(0.0))
+    }
+
+    pub async fn run<T, F, Fut>(
+        &self,
+        operation: &str,
+        mut on_retry: impl FnMut(&RetryAttempt),
+        mut action: F,
+    ) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let error = match action().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            if attempt >= max_attempts {
+                warn!(
+                    "{} failed after {} attempts: {:#}",
+                    operation, attempt, error
+                );
+                return Err(error);
+            }
+
+            let delay = match classify(&error) {
+                RetryDecision::GiveUp => {
+                    debug!(
+                        "{} failed with a non-retryable error: {:#}",
+                        operation, error
+                    );
+                    return Err(error);
+                }
+                RetryDecision::Retry(Some(retry_after)) if retry_after > self.max_retry_after => {
+                    warn!(
+                        "{} asked to retry after {}s, giving up",
+                        operation,
+                        retry_after.as_secs()
+                    );
+                    return Err(error);
+                }
+                RetryDecision::Retry(Some(retry_after)) => retry_after,
+                RetryDecision::Retry(None) => self.backoff_delay(attempt),
+            };
+
+            let retry_attempt = RetryAttempt {
+                operation: operation.to_string(),
+                attempt,
+                max_attempts,
+                delay,
+                reason: error.to_string(),
+            };
+            warn!("{}", retry_attempt);
+            on_retry(&retry_attempt);
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http_error(status: u16, retry_after: Option<Duration>) -> anyhow::Error {
+        anyhow::Error::new(HttpStatusError {
+            url: "https://example.invalid/video.mp4".to_string(),
+            status: StatusCode::from_u16(status).unwrap(),
+            retry_after,
+        })
+        .context("Downloading video stream")
+    }
+
+    #[test]
+    fn classifies_structured_http_status() {
+        let cases = [
+            (503, None, RetryDecision::Retry(None)),
+            (500, None, RetryDecision::Retry(None)),
+            (408, None, RetryDecision::Retry(None)),
+            (
+                429,
+                Some(Duration::from_secs(7)),
+                RetryDecision::Retry(Some(Duration::from_secs(7))),
+            ),
+            (404, None, RetryDecision::GiveUp),
+            (403, None, RetryDecision::GiveUp),
+        ];
+        for (status, retry_after, expected) in cases {
+            assert_eq!(
+                classify(&http_error(status, retry_after)),
+                expected,
+                "status {}",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn classifies_yt_dlp_error_text() {
+        let cases = [
+            (
+                "ERROR: unable to download video data: HTTP Error 503: Service Unavailable",
+                true,
+            ),
+            ("ERROR: HTTP Error 429: Too Many Requests", true),
+            (
+                "ERROR: unable to download webpage: HTTP Error 404: Not Found",
+                false,
+            ),
+            ("ERROR: HTTP Error 403: Forbidden", false),
+            ("ERROR: Read timed out.", true),
+            ("ERROR: [Errno 104] Connection reset by peer", true),
+            ("ERROR: Video unavailable", false),
+            // Only a real status code counts, not any text that starts like one.
+            ("ERROR: title mentions an http error 5 times", false),
+        ];
+        for (message, retry) in cases {
+            let decision = classify(&anyhow::anyhow!(message));
+            assert_eq!(decision == RetryDecision::Retry(None), retry, "{}", message);
+        }
+    }
+
+    #[test]
+    fn classifies_io_errors() {
+        let eof = anyhow::Error::new(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "stream ended early",
+        ));
+        assert_eq!(classify(&eof), RetryDecision::Retry(None));
+        let denied = anyhow::Error::new(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "output dir is read-only",
+        ));
+        assert_eq!(classify(&denied), RetryDecision::GiveUp);
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(
+            parse_retry_after(" 120 ", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:29:30 GMT", now),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        };
+        let delays: Vec<u64> = (1..=5)
+            .map(|attempt| policy.backoff_delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+    }
+
+    #[test]
+    fn backoff_jitter_stays_within_bounds() {
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_secs(4),
+            jitter: 0.25,
+            ..RetryPolicy::default()
+        };
+        for _ in 0..200 {
+            let delay = policy.backoff_delay(1).as_secs_f64();
+            assert!((3.0..=5.0).contains(&delay), "delay {}", delay);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::api::ApiSettings;
+use crate::notifications::NotificationSettings;
+
+pub const SETTINGS_FILE: &str = "settings.json";
+/// Per-site subfolders are opt-in, for settings and downloaders alike.
+pub const DEFAULT_ORGANIZE_BY_EXTRACTOR: bool = false;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Default directory downloads are saved to.
+    pub output_dir: PathBuf,
+    /// Saves downloads into a subfolder per site, named after the yt-dlp extractor.
+    pub organize_by_extractor: bool,
+    /// Where per-job temp directories are created, e.g. on a fast local disk.
+    pub temp_dir: PathBuf,
+    /// Shell command run after each download, with `{path}`, `{id}` and `{title}`
+    /// placeholders. Their values are also set as `DOWNLOAD_PATH`, `VIDEO_ID` and
+    /// `VIDEO_TITLE`.
+    pub post_download_hook: Option<String>,
+    pub notifications: NotificationSettings,
+    /// Localhost HTTP API other tools can submit downloads through.
+    pub api: ApiSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            output_dir: PathBuf::from("output"),
+            organize_by_extractor: DEFAULT_ORGANIZE_BY_EXTRACTOR,
+            temp_dir: PathBuf::from("temp"),
+            post_download_hook: None,
+            notifications: NotificationSettings::default(),
+            api: ApiSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        Self::load_from(Path::new(SETTINGS_FILE))
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return Self::default(),
+        };
+        match serde_json::from_str(&content) {
+            Ok(settings) => settings,
+            Err(e) => {
+                warn!("Ignoring invalid {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.save_to(Path::new(SETTINGS_FILE))
+    }
+
+    pub fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
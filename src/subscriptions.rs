@@ -0,0 +1,401 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::batch::{run_batch, BatchEntry, BatchReport, FormatChoice};
+use crate::downloader::VideoDownloader;
+use crate::format_policy::FormatPolicy;
+use crate::retry::error_for_status;
+use crate::worker::{CancelCheck, EventSender};
+
+pub const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+pub const SUBSCRIPTIONS_FILE: &str = "subscriptions.json";
+// Seen ids beyond this are forgotten; the channel feed only lists recent uploads.
+const MAX_SEEN_VIDEO_IDS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subscription {
+    pub channel_id: String,
+    pub channel_name: String,
+    pub channel_url: String,
+    /// Format policy such as `height<=1080,vcodec=vp9`; empty for automatic selection.
+    #[serde(default)]
+    pub format_policy: String,
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub seen_video_ids: Vec<String>,
+    #[serde(default)]
+    pub last_checked: Option<u64>,
+}
+
+impl Subscription {
+    pub fn new(channel_id: &str, channel_name: &str, channel_url: &str) -> Self {
+        Self {
+            channel_id: channel_id.to_string(),
+            channel_name: channel_name.to_string(),
+            channel_url: channel_url.to_string(),
+            format_policy: String::new(),
+            output_dir: None,
+            seen_video_ids: Vec::new(),
+            last_checked: None,
+        }
+    }
+
+    fn format_choice(&self) -> FormatChoice {
+        if self.format_policy.trim().is_empty() {
+            return FormatChoice::Auto;
+        }
+        match FormatPolicy::parse(&self.format_policy) {
+            Ok(policy) => FormatChoice::Policy(policy),
+            Err(e) => {
+                warn!("Ignoring format policy of {}: {}", self.channel_name, e);
+                FormatChoice::Auto
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscriptionStore {
+    pub subscriptions: Vec<Subscription>,
+}
+
+impl SubscriptionStore {
+    pub fn load() -> Self {
+        Self::load_from(Path::new(SUBSCRIPTIONS_FILE))
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return Self::default(),
+        };
+        match serde_json::from_str(&content) {
+            Ok(store) => store,
+            Err(e) => {
+                warn!("Ignoring invalid {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.save_to(Path::new(SUBSCRIPTIONS_FILE))
+    }
+
+    pub fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn subscribe(&mut self, subscription: Subscription) -> bool {
+        if self
+            .subscriptions
+            .iter()
+            .any(|existing| existing.channel_id == subscription.channel_id)
+        {
+            return false;
+        }
+        self.subscriptions.push(subscription);
+        true
+    }
+
+    pub fn unsubscribe(&mut self, channel_id: &str) {
+        self.subscriptions
+            .retain(|subscription| subscription.channel_id != channel_id);
+    }
+
+    pub fn get_mut(&mut self, channel_id: &str) -> Option<&mut Subscription> {
+        self.subscriptions
+            .iter_mut()
+            .find(|subscription| subscription.channel_id == channel_id)
+    }
+}
+
+fn feed_url(channel_id: &str) -> String {
+    format!(
+        "https://www.youtube.com/feeds/videos.xml?channel_id={}",
+        channel_id
+    )
+}
+
+fn upload_url(video_id: &str) -> String {
+    format!("https://www.youtube.com/watch?v={}", video_id)
+}
+
+/// Ids of the most recent uploads of a channel, newest first, from its Atom feed.
+pub async fn fetch_recent_uploads(channel_id: &str) -> anyhow::Result<Vec<String>> {
+    let url = feed_url(channel_id);
+    let response = reqwest::get(&url).await?;
+    let feed = error_for_status(&url, response)?.text().await?;
+    static VIDEO_ID_PATTERN: OnceLock<Regex> = OnceLock::new();
+    let video_id_pattern = VIDEO_ID_PATTERN
+        .get_or_init(|| Regex::new(r"<yt:videoId>([\w-]{11})</yt:videoId>").unwrap());
+    Ok(video_id_pattern
+        .captures_iter(&feed)
+        .map(|captures| captures[1].to_string())
+        .collect())
+}
+
+/// Uploads not seen before. The first check of a channel finds none, so subscribing
+/// does not download the whole back catalogue.
+fn new_uploads(subscription: &Subscription, upload_ids: &[String]) -> Vec<String> {
+    if subscription.last_checked.is_none() {
+        return Vec::new();
+    }
+    let seen_video_ids: HashSet<&String> = subscription.seen_video_ids.iter().collect();
+    upload_ids
+        .iter()
+        .filter(|video_id| !seen_video_ids.contains(video_id))
+        .cloned()
+        .collect()
+}
+
+/// Checks every subscription in the store at `store_path` for new uploads and
+/// downloads them.
+pub async fn check_subscriptions(
+    video_downloader: &VideoDownloader,
+    store_path: &Path,
+    events: &EventSender,
+    cancel_check: &mut CancelCheck<'_>,
+) -> BatchReport {
+    let mut report = BatchReport::default();
+    let subscriptions = SubscriptionStore::load_from(store_path).subscriptions;
+
+    for subscription in subscriptions {
+        if cancel_check.is_cancelled() {
+            report.cancelled = true;
+            break;
+        }
+        events.status(format!(
+            "Checking {} for new uploads...",
+            subscription.channel_name
+        ));
+        let upload_ids = match fetch_recent_uploads(&subscription.channel_id).await {
+            Ok(upload_ids) => upload_ids,
+            Err(e) => {
+                warn!(
+                    "Failed to check {} for new uploads: {}",
+                    subscription.channel_name, e
+                );
+                continue;
+            }
+        };
+
+        let new_video_ids = new_uploads(&subscription, &upload_ids);
+        debug!(
+            "{} has {} new upload(s)",
+            subscription.channel_name,
+            new_video_ids.len()
+        );
+
+        let entries: Vec<BatchEntry> = new_video_ids
+            .iter()
+            .enumerate()
+            .map(|(position, video_id)| BatchEntry {
+                line: position + 1,
+                url: upload_url(video_id),
+                format: subscription.format_choice(),
+                output_name: None,
+                output_dir: subscription.output_dir.clone(),
+            })
+            .collect();
+        let channel_report = run_batch(
+            video_downloader,
+            entries,
+            Vec::new(),
+            events,
+            false,
+            cancel_check,
+        )
+        .await;
+
+        // Failed uploads stay unseen so the next check retries them.
+        let seen_now: Vec<String> = match subscription.last_checked {
+            Some(_) => new_video_ids
+                .into_iter()
+                .filter(|video_id| {
+                    !channel_report
+                        .failed
+                        .iter()
+                        .any(|failure| failure.url == upload_url(video_id))
+                })
+                .collect(),
+            None => upload_ids,
+        };
+        report.total += channel_report.total;
+        report.downloaded.extend(channel_report.downloaded);
+        report.failed.extend(channel_report.failed);
+        // Uploads after the cancel were never tried, so the channel stays unchecked.
+        if channel_report.cancelled {
+            report.cancelled = true;
+            break;
+        }
+
+        if let Err(e) = mark_checked(store_path, &subscription.channel_id, seen_now) {
+            warn!("Failed to save subscriptions: {}", e);
+        }
+    }
+
+    report
+}
+
+/// Reloads the store before saving so edits made while checking are kept.
+fn mark_checked(store_path: &Path, channel_id: &str, seen_now: Vec<String>) -> anyhow::Result<()> {
+    let mut store = SubscriptionStore::load_from(store_path);
+    if let Some(subscription) = store.get_mut(channel_id) {
+        subscription.seen_video_ids.extend(seen_now);
+        let overflow = subscription
+            .seen_video_ids
+            .len()
+            .saturating_sub(MAX_SEEN_VIDEO_IDS);
+        subscription.seen_video_ids.drain(..overflow);
+        subscription.last_checked = Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+        );
+    }
+    store.save_to(store_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    use tokio::sync::{broadcast, mpsc};
+
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use crate::worker::{Job, WorkerCommand};
+
+    const CHANNEL_ID: &str = "UCuAXFkgsw1L7xaCfnd5JJOw";
+
+    fn video_ids(range: std::ops::Range<usize>) -> Vec<String> {
+        range.map(|index| format!("video{:06}", index)).collect()
+    }
+
+    fn store_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-subscriptions-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("subscriptions.json")
+    }
+
+    fn save_subscription(store_path: &Path, subscription: Subscription) {
+        let mut store = SubscriptionStore::default();
+        store.subscribe(subscription);
+        store.save_to(store_path).unwrap();
+    }
+
+    #[test]
+    fn first_check_only_records_a_baseline() {
+        let subscription = Subscription::new(CHANNEL_ID, "Channel", "https://example.invalid");
+
+        assert!(new_uploads(&subscription, &video_ids(0..15)).is_empty());
+    }
+
+    #[test]
+    fn detects_uploads_not_seen_before() {
+        let mut subscription = Subscription::new(CHANNEL_ID, "Channel", "https://example.invalid");
+        subscription.last_checked = Some(1);
+        subscription.seen_video_ids = video_ids(0..10);
+
+        // The feed lists the newest uploads first.
+        let mut upload_ids = video_ids(5..12);
+        upload_ids.reverse();
+
+        assert_eq!(
+            new_uploads(&subscription, &upload_ids),
+            vec!["video000011", "video000010"]
+        );
+    }
+
+    #[test]
+    fn mark_checked_saves_to_the_given_store() {
+        let store_path = store_path("mark-checked");
+        save_subscription(
+            &store_path,
+            Subscription::new(CHANNEL_ID, "Channel", "https://example.invalid"),
+        );
+
+        mark_checked(&store_path, CHANNEL_ID, video_ids(0..3)).unwrap();
+
+        let subscription = &SubscriptionStore::load_from(&store_path).subscriptions[0];
+        assert_eq!(subscription.seen_video_ids, video_ids(0..3));
+        assert!(subscription.last_checked.is_some());
+        let _ = fs::remove_dir_all(store_path.parent().unwrap());
+    }
+
+    #[test]
+    fn mark_checked_forgets_the_oldest_seen_ids() {
+        let store_path = store_path("trim");
+        let mut subscription = Subscription::new(CHANNEL_ID, "Channel", "https://example.invalid");
+        subscription.seen_video_ids = video_ids(0..MAX_SEEN_VIDEO_IDS);
+        save_subscription(&store_path, subscription);
+
+        mark_checked(
+            &store_path,
+            CHANNEL_ID,
+            video_ids(MAX_SEEN_VIDEO_IDS..MAX_SEEN_VIDEO_IDS + 20),
+        )
+        .unwrap();
+
+        let subscription = &SubscriptionStore::load_from(&store_path).subscriptions[0];
+        assert_eq!(
+            subscription.seen_video_ids,
+            video_ids(20..MAX_SEEN_VIDEO_IDS + 20)
+        );
+        let _ = fs::remove_dir_all(store_path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_before_the_next_channel() {
+        let store_path = store_path("cancel");
+        save_subscription(
+            &store_path,
+            Subscription::new(CHANNEL_ID, "Channel", "https://example.invalid"),
+        );
+        let dir = store_path.parent().unwrap();
+        let video_downloader =
+            VideoDownloader::new("libs", &dir.join("output").display().to_string(), "")
+                .unwrap()
+                .with_backend(Arc::new(FakeBackend::default()));
+        let (events_tx, _events_rx) = broadcast::channel(16);
+        let events = EventSender::new(1, events_tx.clone());
+        let (commands_tx, mut commands) = mpsc::unbounded_channel();
+        let mut deferred = VecDeque::new();
+        commands_tx
+            .send(Job {
+                id: 2,
+                command: WorkerCommand::Cancel(1),
+            })
+            .unwrap();
+
+        let mut cancel_check = CancelCheck::new(&mut commands, &mut deferred, &events_tx, 1);
+        let report =
+            check_subscriptions(&video_downloader, &store_path, &events, &mut cancel_check).await;
+
+        assert!(report.cancelled);
+        assert_eq!(report.total, 0);
+        let subscription = &SubscriptionStore::load_from(&store_path).subscriptions[0];
+        assert_eq!(
+            subscription.last_checked, None,
+            "the channel was never checked"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::{debug, warn};
+
+const JOB_DIR_PREFIX: &str = "job-";
+const PID_FILE_NAME: &str = ".pid";
+
+// Used where process liveness cannot be checked: job dirs untouched for this long
+// are considered orphaned.
+#[cfg(not(target_os = "linux"))]
+const ORPHAN_AGE: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// A temp directory owned by a single download job. It is removed when dropped, so
+/// partial streams are cleaned up on success, failure and cancellation alike.
+#[derive(Debug)]
+pub struct JobTempDir {
+    path: PathBuf,
+}
+
+impl JobTempDir {
+    pub fn create(temp_root: &Path) -> io::Result<Self> {
+        fs::create_dir_all(temp_root)?;
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let path = temp_root.join(format!(
+            "{}{}-{}-{:08x}",
+            JOB_DIR_PREFIX,
+            std::process::id(),
+            nanos,
+            rand::random::<u32>()
+        ));
+        fs::create_dir(&path)?;
+        fs::write(path.join(PID_FILE_NAME), std::process::id().to_string())?;
+        debug!("Created job temp dir {}", path.display());
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for JobTempDir {
+    fn drop(&mut self) {
+        match fs::remove_dir_all(&self.path) {
+            Ok(()) => debug!("Removed job temp dir {}", self.path.display()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!(
+                "Failed to remove job temp dir {}: {}",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Moves a finished file from a job temp dir to its final location. The rename is
+/// atomic when both are on the same filesystem; otherwise the file is copied next to
+/// the destination first so a half-copied file never appears under the final name.
+pub fn move_into_place(temp_file: &Path, final_path: &Path) -> io::Result<()> {
+    if let Some(parent) = final_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(temp_file, final_path).is_ok() {
+        return Ok(());
+    }
+
+    let mut partial_file_name = final_path.file_name().unwrap_or_default().to_os_string();
+    partial_file_name.push(".part");
+    let partial_path = final_path.with_file_name(partial_file_name);
+    fs::copy(temp_file, &partial_path)?;
+    if let Err(e) = fs::rename(&partial_path, final_path) {
+        let _ = fs::remove_file(&partial_path);
+        return Err(e);
+    }
+    fs::remove_file(temp_file)
+}
+
+/// Removes job temp dirs left behind by instances that crashed or were killed.
+pub fn cleanup_orphaned_temp_dirs(temp_root: &Path) {
+    let entries = match fs::read_dir(temp_root) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_job_dir = path.is_dir()
+            && entry
+                .file_name()
+                .to_str()
+                .map(|file_name| file_name.starts_with(JOB_DIR_PREFIX))
+                .unwrap_or(false);
+        if !is_job_dir || !is_orphaned(&path) {
+            continue;
+        }
+
+        match fs::remove_dir_all(&path) {
+            Ok(()) => debug!("Removed orphaned temp dir {}", path.display()),
+            Err(e) => warn!(
+                "Failed to remove orphaned temp dir {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+}
+
+fn is_orphaned(job_dir: &Path) -> bool {
+    let owner_pid = fs::read_to_string(job_dir.join(PID_FILE_NAME))
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok());
+    match owner_pid {
+        Some(pid) if pid == std::process::id() => false,
+        Some(pid) => !is_process_running(pid, job_dir),
+        None => true,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_process_running(pid: u32, _job_dir: &Path) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_process_running(_pid: u32, job_dir: &Path) -> bool {
+    fs::metadata(job_dir)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .map(|age| age < ORPHAN_AGE)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "youtube_downloader-temp-dir-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    #[test]
+    fn job_temp_dir_is_removed_on_drop() {
+        let root = test_root("drop");
+        let job_temp_dir = JobTempDir::create(&root).unwrap();
+        let path = job_temp_dir.path().to_path_buf();
+        fs::write(path.join("video.part"), b"partial").unwrap();
+        assert!(path.is_dir());
+
+        drop(job_temp_dir);
+
+        assert!(!path.exists());
+        assert!(root.is_dir(), "the temp root itself stays");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn move_into_place_replaces_an_existing_file() {
+        let root = test_root("move");
+        let job_temp_dir = JobTempDir::create(&root).unwrap();
+        let temp_file = job_temp_dir.path().join("merged.mp4");
+        fs::write(&temp_file, b"new").unwrap();
+        let final_path = root.join("output").join("Video.mp4");
+        fs::create_dir_all(final_path.parent().unwrap()).unwrap();
+        fs::write(&final_path, b"old download").unwrap();
+
+        move_into_place(&temp_file, &final_path).unwrap();
+
+        assert_eq!(fs::read(&final_path).unwrap(), b"new");
+        assert!(!temp_file.exists());
+        drop(job_temp_dir);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn cleanup_removes_only_orphaned_job_dirs() {
+        let root = test_root("cleanup");
+        let live = JobTempDir::create(&root).unwrap();
+        let without_pid = root.join(format!("{}crashed", JOB_DIR_PREFIX));
+        fs::create_dir_all(&without_pid).unwrap();
+        fs::write(without_pid.join("video.part"), b"partial").unwrap();
+        let dead_owner = root.join(format!("{}dead-owner", JOB_DIR_PREFIX));
+        fs::create_dir_all(&dead_owner).unwrap();
+        fs::write(dead_owner.join(PID_FILE_NAME), u32::MAX.to_string()).unwrap();
+        let other = root.join("not-a-job");
+        fs::create_dir_all(&other).unwrap();
+
+        cleanup_orphaned_temp_dirs(&root);
+
+        assert!(live.path().is_dir());
+        assert!(!without_pid.exists());
+        // Elsewhere only the age tells, and this dir is brand new.
+        #[cfg(target_os = "linux")]
+        assert!(!dead_owner.exists());
+        assert!(other.is_dir());
+        drop(live);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use anyhow::Context;
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, GenericImageView, ImageFormat};
+use url::Url;
+
+use crate::backend::{Thumbnail, Video};
+
+const JPEG_QUALITY: u8 = 92;
+// Pixels with every channel below this are treated as part of a letterbox bar.
+const BAR_THRESHOLD: u8 = 24;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThumbnailListItem {
+    pub id: String,
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ThumbnailListItem {
+    pub fn new(thumbnail: &Thumbnail) -> Self {
+        Self {
+            id: thumbnail.id.clone(),
+            url: thumbnail.url.clone(),
+            width: thumbnail.width.unwrap_or(0),
+            height: thumbnail.height.unwrap_or(0),
+        }
+    }
+
+    fn extension(&self) -> Option<String> {
+        url_extension(&self.url)
+    }
+}
+
+impl Display for ThumbnailListItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)?;
+        if self.width > 0 {
+            write!(f, " - {}x{}", self.width, self.height)?;
+        }
+        if let Some(extension) = self.extension() {
+            write!(f, " ({})", extension)?;
+        }
+        Ok(())
+    }
+}
+
+/// Extension of the last path segment of a URL, ignoring the host, query and fragment.
+pub fn url_extension(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let file_name = url.path_segments()?.last()?;
+    let (stem, extension) = file_name.rsplit_once('.')?;
+    match stem.is_empty() || extension.is_empty() {
+        true => None,
+        false => Some(extension.to_ascii_lowercase()),
+    }
+}
+
+/// Thumbnails of a video, largest first.
+pub fn list_thumbnails(video_info: &Video) -> Vec<ThumbnailListItem> {
+    let mut thumbnails: Vec<ThumbnailListItem> = video_info
+        .thumbnails
+        .iter()
+        .map(ThumbnailListItem::new)
+        .collect();
+    thumbnails.sort_by_key(|thumbnail| std::cmp::Reverse(thumbnail.width * thumbnail.height));
+    thumbnails
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpeg,
+    Png,
+}
+
+impl ThumbnailFormat {
+    pub const ALL: [ThumbnailFormat; 2] = [ThumbnailFormat::Jpeg, ThumbnailFormat::Png];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::Png => "png",
+        }
+    }
+}
+
+impl Display for ThumbnailFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThumbnailFormat::Jpeg => write!(f, "JPEG"),
+            ThumbnailFormat::Png => write!(f, "PNG"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThumbnailRequest {
+    pub url: String,
+    pub format: ThumbnailFormat,
+    pub crop_square: bool,
+}
+
+/// Decodes a downloaded thumbnail (usually WebP or JPEG) and writes it as `format`,
+/// optionally cropped to the square cover art inside YouTube's letterboxing.
+pub fn convert_thumbnail(
+    source_path: &Path,
+    target_path: &Path,
+    format: ThumbnailFormat,
+    crop_square: bool,
+) -> anyhow::Result<()> {
+    let mut thumbnail = image::io::Reader::open(source_path)?
+        .with_guessed_format()?
+        .decode()
+        .with_context(|| format!("Failed to decode thumbnail {}", source_path.display()))?;
+    if crop_square {
+        thumbnail = crop_to_square(&trim_letterbox(&thumbnail));
+    }
+
+    match format {
+        ThumbnailFormat::Jpeg => {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(target_path)?);
+            JpegEncoder::new_with_quality(&mut file, JPEG_QUALITY)
+                .encode_image(&thumbnail.to_rgb8())?;
+        }
+        ThumbnailFormat::Png => thumbnail.save_with_format(target_path, ImageFormat::Png)?,
+    }
+    Ok(())
+}
+
+/// Removes the dark bars YouTube adds around 4:3 and square artwork.
+fn trim_letterbox(thumbnail: &DynamicImage) -> DynamicImage {
+    let (width, height) = thumbnail.dimensions();
+    let is_dark = |x: u32, y: u32| {
+        let pixel = thumbnail.get_pixel(x, y).0;
+        pixel[..3].iter().all(|channel| *channel < BAR_THRESHOLD)
+    };
+    let is_dark_row = |y: u32| (0..width).all(|x| is_dark(x, y));
+    let is_dark_column = |x: u32, top: u32, bottom: u32| (top..bottom).all(|y| is_dark(x, y));
+
+    let top = (0..height).find(|y| !is_dark_row(*y)).unwrap_or(0);
+    let bottom = (0..height)
+        .rev()
+        .find(|y| !is_dark_row(*y))
+        .map(|y| y + 1)
+        .unwrap_or(height);
+    if top >= bottom {
+        return thumbnail.clone();
+    }
+    let left = (0..width)
+        .find(|x| !is_dark_column(*x, top, bottom))
+        .unwrap_or(0);
+    let right = (0..width)
+        .rev()
+        .find(|x| !is_dark_column(*x, top, bottom))
+        .map(|x| x + 1)
+        .unwrap_or(width);
+    if left >= right {
+        return thumbnail.clone();
+    }
+
+    thumbnail.crop_imm(left, top, right - left, bottom - top)
+}
+
+fn crop_to_square(thumbnail: &DynamicImage) -> DynamicImage {
+    let (width, height) = thumbnail.dimensions();
+    let side = width.min(height);
+    thumbnail.crop_imm((width - side) / 2, (height - side) / 2, side, side)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thumbnail(url: &str, width: u32) -> ThumbnailListItem {
+        ThumbnailListItem {
+            id: "0".to_string(),
+            url: url.to_string(),
+            width,
+            height: width / 16 * 9,
+        }
+    }
+
+    #[test]
+    fn extension_comes_from_the_url_path() {
+        let cases = [
+            (
+                "https://i.ytimg.com/vi/dQw4w9WgXcQ/maxresdefault.jpg",
+                Some("jpg"),
+            ),
+            (
+                "https://i.ytimg.com/vi_webp/dQw4w9WgXcQ/hq720.WEBP?sqp=x.y",
+                Some("webp"),
+            ),
+            ("https://cdn.example.com/thumbs/1234#frame.png", None),
+            ("https://cdn.example.com/thumbnail", None),
+            ("https://cdn.example.com/", None),
+            ("https://cdn.example.com/.hidden", None),
+            ("https://cdn.example.com/thumbnail.", None),
+            ("not a url.jpg", None),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(
+                thumbnail(url, 0).extension().as_deref(),
+                expected,
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn crops_letterboxed_artwork_to_a_square() {
+        let dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-thumbnail-crop-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        // 80x70 artwork inside black bars on every side of a 160x90 frame.
+        let letterboxed = image::RgbImage::from_fn(160, 90, |x, y| {
+            match (40..120).contains(&x) && (10..80).contains(&y) {
+                true => image::Rgb([60 + x as u8, 150, 200]),
+                false => image::Rgb([8, 8, 8]),
+            }
+        });
+        let source_path = dir.join("letterboxed.png");
+        letterboxed.save(&source_path).unwrap();
+
+        let cropped_path = dir.join("cropped.png");
+        convert_thumbnail(&source_path, &cropped_path, ThumbnailFormat::Png, true).unwrap();
+        let cropped = image::open(&cropped_path).unwrap().to_rgb8();
+        assert_eq!(cropped.dimensions(), (70, 70));
+        // The square is centred on the artwork, which spans x = 40..120.
+        assert_eq!(cropped.get_pixel(0, 0).0, [105, 150, 200]);
+        assert_eq!(cropped.get_pixel(69, 69).0, [174, 150, 200]);
+
+        let uncropped_path = dir.join("uncropped.png");
+        convert_thumbnail(&source_path, &uncropped_path, ThumbnailFormat::Png, false).unwrap();
+        assert_eq!(
+            image::open(&uncropped_path).unwrap().dimensions(),
+            (160, 90)
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn display_skips_unknown_extensions() {
+        assert_eq!(
+            thumbnail("https://i.ytimg.com/vi/x/maxresdefault.jpg", 1280).to_string(),
+            "0 - 1280x720 (jpg)"
+        );
+        assert_eq!(
+            thumbnail("https://cdn.example.com/thumbnail?size=large", 0).to_string(),
+            "0"
+        );
+    }
+}
@@ -1,9 +1,7 @@
+pub mod format_table;
 pub mod message;
 pub mod state;
 pub mod subscription;
-pub mod view;
+pub mod subscriptions_tab;
 pub mod update;
-
-
-
-
+pub mod view;
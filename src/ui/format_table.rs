@@ -0,0 +1,484 @@
+use std::cmp::Ordering;
+
+use iced::{
+    widget::{button, text, text_input, Column, Row, Scrollable},
+    Alignment, Element, Length,
+};
+
+use super::{message::Message as UIMessage, state::FormatListItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatKind {
+    Video,
+    Audio,
+    AudioVideo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatColumn {
+    Id,
+    Resolution,
+    Fps,
+    Codec,
+    Container,
+    Bitrate,
+    Audio,
+    Size,
+    Hdr,
+    Language,
+    Drm,
+}
+
+impl FormatColumn {
+    pub const ALL: [FormatColumn; 11] = [
+        FormatColumn::Id,
+        FormatColumn::Resolution,
+        FormatColumn::Fps,
+        FormatColumn::Codec,
+        FormatColumn::Container,
+        FormatColumn::Bitrate,
+        FormatColumn::Audio,
+        FormatColumn::Size,
+        FormatColumn::Hdr,
+        FormatColumn::Language,
+        FormatColumn::Drm,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            FormatColumn::Id => "ID",
+            FormatColumn::Resolution => "Resolution",
+            FormatColumn::Fps => "FPS",
+            FormatColumn::Codec => "Codec",
+            FormatColumn::Container => "Container",
+            FormatColumn::Bitrate => "Bitrate",
+            FormatColumn::Audio => "Audio",
+            FormatColumn::Size => "Size",
+            FormatColumn::Hdr => "HDR",
+            FormatColumn::Language => "Language",
+            FormatColumn::Drm => "DRM",
+        }
+    }
+
+    fn width(&self) -> f32 {
+        match self {
+            FormatColumn::Codec => 140.0,
+            FormatColumn::Resolution | FormatColumn::Language | FormatColumn::Audio => 100.0,
+            FormatColumn::Drm => 50.0,
+            _ => 80.0,
+        }
+    }
+
+    pub fn cell_text(&self, format: &FormatListItem) -> String {
+        match self {
+            FormatColumn::Id => format.format_id.clone(),
+            FormatColumn::Resolution => match format.width > 0 {
+                true => format!("{}x{}", format.width, format.height),
+                false => "audio only".to_string(),
+            },
+            FormatColumn::Fps => match (format.fps > 0.0, format.is_high_frame_rate()) {
+                (true, true) => format!("{} HFR", format.fps),
+                (true, false) => format!("{}", format.fps),
+                _ => String::new(),
+            },
+            FormatColumn::Codec => [format.video_codec.as_str(), format.audio_codec.as_str()]
+                .into_iter()
+                .filter(|codec| !codec.is_empty() && *codec != "none")
+                .collect::<Vec<_>>()
+                .join(" + "),
+            FormatColumn::Container => format.container.clone(),
+            FormatColumn::Bitrate => match format.effective_bitrate() > 0.0 {
+                true => format!("{:.0}k", format.effective_bitrate()),
+                false => String::new(),
+            },
+            FormatColumn::Audio => match (format.sample_rate > 0, format.audio_channels > 0) {
+                (true, true) => format!(
+                    "{:.1}kHz {}ch",
+                    format.sample_rate as f64 / 1000.0,
+                    format.audio_channels
+                ),
+                (true, false) => format!("{:.1}kHz", format.sample_rate as f64 / 1000.0),
+                _ => String::new(),
+            },
+            FormatColumn::Size => format
+                .estimated_size()
+                .map(|estimated_size| estimated_size.to_string())
+                .unwrap_or_default(),
+            FormatColumn::Hdr => format.dynamic_range.clone(),
+            FormatColumn::Language => format.language.clone(),
+            FormatColumn::Drm => match format.has_drm {
+                true => "DRM".to_string(),
+                false => String::new(),
+            },
+        }
+    }
+
+    /// Formats without a value in this column, which sort last in either direction.
+    fn is_unknown(&self, format: &FormatListItem) -> bool {
+        match self {
+            FormatColumn::Fps
+            | FormatColumn::Bitrate
+            | FormatColumn::Audio
+            | FormatColumn::Size
+            | FormatColumn::Hdr
+            | FormatColumn::Language => self.cell_text(format).is_empty(),
+            _ => false,
+        }
+    }
+
+    fn compare(&self, left: &FormatListItem, right: &FormatListItem) -> Ordering {
+        match self {
+            FormatColumn::Id => match (
+                left.format_id.parse::<u32>(),
+                right.format_id.parse::<u32>(),
+            ) {
+                (Ok(left_id), Ok(right_id)) => left_id.cmp(&right_id),
+                _ => left.format_id.cmp(&right.format_id),
+            },
+            FormatColumn::Resolution => (left.width * left.height)
+                .cmp(&(right.width * right.height))
+                .then(left.fps.total_cmp(&right.fps)),
+            FormatColumn::Fps => left.fps.total_cmp(&right.fps),
+            FormatColumn::Bitrate => left
+                .effective_bitrate()
+                .total_cmp(&right.effective_bitrate()),
+            FormatColumn::Audio => (left.sample_rate, left.audio_channels)
+                .cmp(&(right.sample_rate, right.audio_channels)),
+            FormatColumn::Size => left
+                .estimated_size()
+                .map(|size| size.bytes)
+                .cmp(&right.estimated_size().map(|size| size.bytes)),
+            _ => self.cell_text(left).cmp(&self.cell_text(right)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatTableState {
+    pub formats: Vec<FormatListItem>,
+    pub sort_column: FormatColumn,
+    pub sort_ascending: bool,
+    pub filter: String,
+}
+
+impl Default for FormatTableState {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl FormatTableState {
+    pub fn new(formats: Vec<FormatListItem>) -> Self {
+        Self {
+            formats,
+            sort_column: FormatColumn::Bitrate,
+            sort_ascending: false,
+            filter: String::new(),
+        }
+    }
+
+    pub fn sort_by(&mut self, column: FormatColumn) {
+        if self.sort_column == column {
+            self.sort_ascending = !self.sort_ascending;
+        } else {
+            self.sort_column = column;
+            self.sort_ascending = true;
+        }
+    }
+
+    pub fn visible_formats(&self) -> Vec<&FormatListItem> {
+        let filter = self.filter.trim().to_lowercase();
+        let mut visible_formats: Vec<&FormatListItem> = self
+            .formats
+            .iter()
+            .filter(|format| {
+                filter.is_empty()
+                    || FormatColumn::ALL.iter().any(|column| {
+                        column
+                            .cell_text(format)
+                            .to_lowercase()
+                            .contains(filter.as_str())
+                    })
+                    || format.format_note.to_lowercase().contains(filter.as_str())
+            })
+            .collect();
+
+        let column = self.sort_column;
+        visible_formats.sort_by(|left, right| {
+            let ordering = column.compare(left, right);
+            column
+                .is_unknown(left)
+                .cmp(&column.is_unknown(right))
+                .then(match self.sort_ascending {
+                    true => ordering,
+                    false => ordering.reverse(),
+                })
+        });
+        visible_formats
+    }
+}
+
+pub fn view<'a>(
+    kind: FormatKind,
+    table: &'a FormatTableState,
+    selected_format: Option<&'a FormatListItem>,
+    disabled: bool,
+) -> Element<'a, UIMessage> {
+    let header = FormatColumn::ALL
+        .iter()
+        .fold(Row::new().spacing(4.0), |header, column| {
+            let title = match table.sort_column == *column {
+                true => format!(
+                    "{} {}",
+                    column.title(),
+                    if table.sort_ascending { "▲" } else { "▼" }
+                ),
+                false => column.title().to_string(),
+            };
+            header.push(
+                button(text(title).size(12))
+                    .style(button::secondary)
+                    .width(column.width())
+                    .on_press(UIMessage::SortFormats(kind, *column)),
+            )
+        });
+
+    let rows =
+        table
+            .visible_formats()
+            .into_iter()
+            .fold(Column::new().spacing(2.0), |rows, format| {
+                let is_selected = selected_format
+                    .map(|selected_format| selected_format.format_id == format.format_id)
+                    .unwrap_or(false);
+                let cells =
+                    FormatColumn::ALL
+                        .iter()
+                        .fold(Row::new().spacing(4.0), |cells, column| {
+                            cells.push(
+                                text(column.cell_text(format))
+                                    .size(12)
+                                    .width(column.width()),
+                            )
+                        });
+                let select_message = match kind {
+                    FormatKind::Video => UIMessage::SelectVideoFormat(format.clone()),
+                    FormatKind::Audio => UIMessage::SelectAudioFormat(format.clone()),
+                    FormatKind::AudioVideo => UIMessage::SelectAudioVideoFormat(format.clone()),
+                };
+                rows.push(
+                    button(cells)
+                        .style(match is_selected {
+                            true => button::primary,
+                            false => button::text,
+                        })
+                        .width(Length::Fill)
+                        .on_press_maybe((!disabled).then_some(select_message)),
+                )
+            });
+
+    let filter_placeholder = match kind {
+        FormatKind::Video => "Filter video formats...",
+        FormatKind::Audio => "Filter audio formats...",
+        FormatKind::AudioVideo => "Filter combined audio+video formats...",
+    };
+
+    Column::new()
+        .spacing(4.0)
+        .push(
+            Row::new()
+                .spacing(10.0)
+                .push(
+                    text_input(filter_placeholder, &table.filter)
+                        .size(14)
+                        .on_input(move |filter| UIMessage::FilterFormats(kind, filter)),
+                )
+                .push(text(format!("{} formats", table.formats.len())).size(12))
+                .align_y(Alignment::Center),
+        )
+        .push(header)
+        .push(Scrollable::new(rows).height(160.0))
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Format;
+
+    fn format(format_id: &str, customize: impl FnOnce(&mut Format)) -> FormatListItem {
+        let mut format = Format {
+            format_id: format_id.to_string(),
+            video_codec: Some("none".to_string()),
+            audio_codec: Some("none".to_string()),
+            ..Format::default()
+        };
+        customize(&mut format);
+        FormatListItem::new(&format)
+    }
+
+    fn test_table() -> FormatTableState {
+        FormatTableState::new(vec![
+            format("137", |format| {
+                format.video_codec = Some("avc1.640028".to_string());
+                format.container = Some("mp4_dash".to_string());
+                format.width = Some(1920);
+                format.height = Some(1080);
+                format.fps = Some(30.0);
+                format.total_bitrate = Some(4500.0);
+                format.filesize = Some(300_000);
+                format.dynamic_range = Some("SDR".to_string());
+            }),
+            format("18", |format| {
+                format.video_codec = Some("avc1.42001E".to_string());
+                format.audio_codec = Some("mp4a.40.2".to_string());
+                format.container = Some("mp4".to_string());
+                format.width = Some(640);
+                format.height = Some(360);
+                format.fps = Some(25.0);
+                format.sample_rate = Some(44100);
+                format.audio_channels = Some(2);
+                format.language = Some("en".to_string());
+            }),
+            format("251", |format| {
+                format.audio_codec = Some("opus".to_string());
+                format.container = Some("webm_dash".to_string());
+                format.total_bitrate = Some(160.0);
+                format.filesize = Some(20_000);
+                format.sample_rate = Some(48000);
+                format.audio_channels = Some(2);
+                format.language = Some("de".to_string());
+            }),
+            format("701", |format| {
+                format.video_codec = Some("av01.0.13M.10".to_string());
+                format.container = Some("webm".to_string());
+                format.width = Some(3840);
+                format.height = Some(2160);
+                format.fps = Some(60.0);
+                format.total_bitrate = Some(20000.0);
+                format.filesize_approx = Some(900_000);
+                format.dynamic_range = Some("HDR10".to_string());
+                format.has_drm = true;
+            }),
+        ])
+    }
+
+    fn sorted_ids(table: &FormatTableState) -> Vec<&str> {
+        table
+            .visible_formats()
+            .iter()
+            .map(|format| format.format_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sorts_by_every_column_in_both_directions() {
+        let cases = [
+            (
+                FormatColumn::Id,
+                ["18", "137", "251", "701"],
+                ["701", "251", "137", "18"],
+            ),
+            (
+                FormatColumn::Resolution,
+                ["251", "18", "137", "701"],
+                ["701", "137", "18", "251"],
+            ),
+            (
+                FormatColumn::Fps,
+                ["18", "137", "701", "251"],
+                ["701", "137", "18", "251"],
+            ),
+            (
+                FormatColumn::Codec,
+                ["701", "18", "137", "251"],
+                ["251", "137", "18", "701"],
+            ),
+            (
+                FormatColumn::Container,
+                ["18", "137", "701", "251"],
+                ["251", "701", "137", "18"],
+            ),
+            (
+                FormatColumn::Bitrate,
+                ["251", "137", "701", "18"],
+                ["701", "137", "251", "18"],
+            ),
+            (
+                FormatColumn::Audio,
+                ["18", "251", "137", "701"],
+                ["251", "18", "137", "701"],
+            ),
+            (
+                FormatColumn::Size,
+                ["251", "137", "701", "18"],
+                ["701", "137", "251", "18"],
+            ),
+            (
+                FormatColumn::Hdr,
+                ["701", "137", "18", "251"],
+                ["137", "701", "18", "251"],
+            ),
+            (
+                FormatColumn::Language,
+                ["251", "18", "137", "701"],
+                ["18", "251", "137", "701"],
+            ),
+            (
+                FormatColumn::Drm,
+                ["137", "18", "251", "701"],
+                ["701", "137", "18", "251"],
+            ),
+        ];
+        for (column, ascending, descending) in cases {
+            let mut table = test_table();
+            table.sort_by(column);
+            assert!(table.sort_ascending);
+            assert_eq!(
+                sorted_ids(&table),
+                ascending,
+                "{} ascending",
+                column.title()
+            );
+
+            table.sort_by(column);
+            assert!(!table.sort_ascending);
+            assert_eq!(
+                sorted_ids(&table),
+                descending,
+                "{} descending",
+                column.title()
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_sizes_and_bitrates_sort_last() {
+        let mut table = test_table();
+        for column in [FormatColumn::Size, FormatColumn::Bitrate] {
+            table.sort_column = column;
+            for sort_ascending in [true, false] {
+                table.sort_ascending = sort_ascending;
+                assert_eq!(sorted_ids(&table).last(), Some(&"18"), "{}", column.title());
+            }
+        }
+    }
+
+    #[test]
+    fn filter_narrows_rows() {
+        let mut table = test_table();
+        table.sort_by(FormatColumn::Id);
+        let cases = [
+            ("", vec!["18", "137", "251", "701"]),
+            ("opus", vec!["251"]),
+            ("  MP4 ", vec!["18", "137"]),
+            ("hdr10", vec!["701"]),
+            ("1920x1080", vec!["137"]),
+            ("no such format", vec![]),
+        ];
+        for (filter, expected) in cases {
+            table.filter = filter.to_string();
+            assert_eq!(sorted_ids(&table), expected, "{}", filter);
+        }
+    }
+}
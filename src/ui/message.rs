@@ -1,7 +1,15 @@
 use std::path::PathBuf;
-use yt_dlp::model::Video;
 
-use crate::ui::state::FormatListItem;
+use crate::backend::Video;
+use crate::batch::BatchReport;
+use crate::disk_space::DiskSpaceShortage;
+use crate::hook::HookOutcome;
+use crate::live::{RecordingMode, RecordingOptions};
+use crate::notifications::NotificationSettings;
+use crate::thumbnail::{ThumbnailFormat, ThumbnailListItem, ThumbnailRequest};
+use crate::ui::format_table::{FormatColumn, FormatKind};
+use crate::ui::state::{FormatListItem, Tab};
+use crate::worker::{JobEvent, WorkerEvent};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -15,12 +23,94 @@ pub enum Message {
     UIUpdated,
     UrlChanged(String),
     FetchInfo,
+    RefreshInfo,
     InfoFetched(Video),
+    InfoFetchFailed(String),
     FetchThumbnail,
     ThumbnailFetched(Option<PathBuf>),
+    ThumbnailSelected(ThumbnailListItem),
+    ThumbnailFormatSelected(ThumbnailFormat),
+    CropThumbnailToggled(bool),
+    SaveThumbnail(ThumbnailRequest),
+    ThumbnailSaved(PathBuf),
     DownloadVideo,
+    DownloadVideoAnyway,
+    RecordingModeSelected(RecordingMode),
+    RecordingMaxMinutesChanged(String),
+    RecordingMaxSizeChanged(String),
+    WaitForStartToggled(bool),
+    RecordLive(RecordingOptions),
+    InsufficientDiskSpace(DiskSpaceShortage),
+    DismissDiskSpaceWarning,
+    ChooseOutputDir,
+    OutputDirChosen(Option<PathBuf>),
+    ChooseDownloadOutputDir,
+    DownloadOutputDirChosen(Option<PathBuf>),
+    SetDownloadOutputDir(Option<PathBuf>),
     VideoDownloaded(Option<PathBuf>),
+    OpenVideo,
+    RevealVideo,
+    PostDownloadHookChanged(String),
+    SavePostDownloadHook,
+    HookFinished(HookOutcome),
+    NotificationSettingsChanged(NotificationSettings),
+    CancelDownload,
+    DownloadFailed(String),
     ProgressUpdated(f64, f32),
     SelectAudioFormat(FormatListItem),
     SelectVideoFormat(FormatListItem),
+    SelectAudioVideoFormat(FormatListItem),
+    SortFormats(FormatKind, FormatColumn),
+    FilterFormats(FormatKind, String),
+    WatchClipboardToggled(bool),
+    PollClipboard,
+    ClipboardRead(Option<String>),
+    LoadClipboardUrl,
+    DismissClipboardUrl,
+    FileDropped(PathBuf),
+    LoadNextQueuedUrl,
+    QueueUrls(Vec<String>),
+    FocusWindow,
+    ImportBatch(PathBuf),
+    BatchFinished(BatchReport),
+    TabSelected(Tab),
+    SubscribeToChannel,
+    Unsubscribe(String),
+    SubscriptionPolicyChanged(String, String),
+    SubscriptionOutputDirChanged(String, String),
+    AutoCheckSubscriptionsToggled(bool),
+    CheckSubscriptions,
+    SubscriptionsChecked(BatchReport),
+    /// An event from the worker, for any job. Only the UI's own jobs update the panel.
+    WorkerEvent(JobEvent),
+    /// What jobs started outside the UI, by the API or the CLI, are doing.
+    BackgroundActivity(String),
+}
+
+impl From<WorkerEvent> for Message {
+    fn from(event: WorkerEvent) -> Self {
+        match event {
+            WorkerEvent::Status(status_message) | WorkerEvent::Failed(status_message) => {
+                Message::StatusMessage(status_message)
+            }
+            WorkerEvent::Progress {
+                downloaded_bytes,
+                percent,
+            } => Message::ProgressUpdated(downloaded_bytes, percent),
+            WorkerEvent::LibrariesInstalled => Message::LibrariesInstalled,
+            WorkerEvent::LibrariesUpdated => Message::LibrariesUpdated,
+            WorkerEvent::InfoFetched(video_info) => Message::InfoFetched(video_info),
+            WorkerEvent::InfoFetchFailed(reason) => Message::InfoFetchFailed(reason),
+            WorkerEvent::ThumbnailFetched(path) => Message::ThumbnailFetched(Some(path)),
+            WorkerEvent::ThumbnailSaved(path) => Message::ThumbnailSaved(path),
+            WorkerEvent::InsufficientDiskSpace(shortage) => {
+                Message::InsufficientDiskSpace(shortage)
+            }
+            WorkerEvent::VideoDownloaded(path) => Message::VideoDownloaded(Some(path)),
+            WorkerEvent::DownloadFailed(reason) => Message::DownloadFailed(reason),
+            WorkerEvent::HookFinished(outcome) => Message::HookFinished(outcome),
+            WorkerEvent::BatchFinished(report) => Message::BatchFinished(report),
+            WorkerEvent::SubscriptionsChecked(report) => Message::SubscriptionsChecked(report),
+        }
+    }
 }
@@ -1,7 +1,22 @@
-use std::{fmt::Display, path::PathBuf};
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+    path::PathBuf,
+};
 
-use iced::widget::combo_box;
-use yt_dlp::model::format::Container;
+use crate::backend::Format;
+use crate::batch::BatchReport;
+use crate::disk_space::DiskSpaceShortage;
+use crate::file_size::{stream_duration, video_duration, FileSize};
+use crate::hook::HookOutcome;
+use crate::live::{LiveStatus, RecordingMode};
+use crate::notifications::NotificationSettings;
+use crate::settings::SETTINGS_FILE;
+use crate::subscriptions::{Subscription, SUBSCRIPTIONS_FILE};
+use crate::thumbnail::{ThumbnailFormat, ThumbnailListItem};
+use crate::ui::format_table::FormatTableState;
+use crate::video_url::{UrlError, VideoUrl};
+use crate::worker::{JobId, WorkerHandle};
 
 #[derive(Debug, Clone)]
 pub struct FormatListItem {
@@ -17,39 +32,93 @@ pub struct FormatListItem {
     pub fps: f64,
     pub quality: f64,
     pub file_size: u64,
+    pub file_size_approx: u64,
+    pub duration: f64,
+    pub bitrate: f64,
+    pub video_bitrate: f64,
+    pub audio_bitrate: f64,
+    pub sample_rate: u32,
+    pub audio_channels: u32,
+    pub dynamic_range: String,
+    pub language: String,
+    pub has_drm: bool,
 }
 
 impl FormatListItem {
-    pub fn new(format: &yt_dlp::model::format::Format) -> Self {
+    pub fn new(format: &Format) -> Self {
         Self {
             format_id: format.format_id.clone(),
-            format_note: format.format_note.clone().unwrap_or(String::new()),
-            container: format!("{}", format.container.clone().unwrap_or(Container::Unknown))
-                .replace("Container(", "")
-                .replace(")", ""),
-            audio_codec: format
-                .codec_info
-                .audio_codec
-                .clone()
-                .unwrap_or(String::new()),
-            audio_ext: format!("{}", format.codec_info.audio_ext.clone())
-                .replace("Extension(", "")
-                .replace(")", ""),
-            video_codec: format
-                .codec_info
-                .video_codec
-                .clone()
-                .unwrap_or(String::new()),
-            video_ext: format!("{}", format.codec_info.video_ext.clone())
-                .replace("Extension(", "")
-                .replace(")", ""),
-            width: format.video_resolution.width.unwrap_or(0),
-            height: format.video_resolution.height.unwrap_or(0),
-            fps: format.video_resolution.fps.unwrap_or(0.0.into()).into(),
-            quality: format.quality_info.quality.unwrap_or(0.0.into()).into(),
-            file_size: format.file_info.filesize.unwrap_or(0) as u64,
+            format_note: format.format_note.clone().unwrap_or_default(),
+            container: format.container.clone().unwrap_or_default(),
+            audio_codec: format.audio_codec.clone().unwrap_or_default(),
+            audio_ext: format.audio_ext.clone().unwrap_or_default(),
+            video_codec: format.video_codec.clone().unwrap_or_default(),
+            video_ext: format.video_ext.clone().unwrap_or_default(),
+            width: format.width.unwrap_or(0),
+            height: format.height.unwrap_or(0),
+            fps: format.fps.unwrap_or(0.0),
+            quality: format.quality.unwrap_or(0.0),
+            file_size: format.filesize.unwrap_or(0),
+            file_size_approx: format.filesize_approx.unwrap_or(0),
+            duration: stream_duration(&format.url).unwrap_or(0.0),
+            bitrate: format.total_bitrate.unwrap_or(0.0),
+            video_bitrate: format.video_bitrate.unwrap_or(0.0),
+            audio_bitrate: format.audio_bitrate.unwrap_or(0.0),
+            sample_rate: format.sample_rate.unwrap_or(0),
+            audio_channels: format.audio_channels.unwrap_or(0),
+            dynamic_range: format.dynamic_range.clone().unwrap_or_default(),
+            language: format.language.clone().unwrap_or_default(),
+            has_drm: format.has_drm,
         }
     }
+
+    /// Lists a format of a video, falling back to the video's duration when the stream
+    /// URL carries none, so sizes can still be estimated from the bitrate.
+    pub fn for_video(format: &Format, formats: &[Format]) -> Self {
+        let mut item = Self::new(format);
+        if item.duration <= 0.0 {
+            item.duration = video_duration(formats).unwrap_or(0.0);
+        }
+        item
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        !self.dynamic_range.is_empty() && !self.dynamic_range.eq_ignore_ascii_case("sdr")
+    }
+
+    pub fn is_high_frame_rate(&self) -> bool {
+        self.fps > 30.0
+    }
+
+    /// Total bitrate in kbit/s, falling back to the sum of the stream bitrates.
+    pub fn effective_bitrate(&self) -> f64 {
+        match self.bitrate > 0.0 {
+            true => self.bitrate,
+            false => self.video_bitrate + self.audio_bitrate,
+        }
+    }
+
+    /// Reported size, falling back to the approximate size or bitrate × duration.
+    pub fn estimated_size(&self) -> Option<FileSize> {
+        if self.file_size > 0 {
+            return Some(FileSize::exact(self.file_size));
+        }
+        if self.file_size_approx > 0 {
+            return Some(FileSize::approximate(self.file_size_approx));
+        }
+        FileSize::from_bitrate(self.effective_bitrate(), self.duration)
+    }
+
+    pub fn is_progressive(&self) -> bool {
+        let has_codec = |codec: &str| !codec.is_empty() && codec != "none";
+        has_codec(&self.video_codec) && has_codec(&self.audio_codec)
+    }
+
+    /// Progressive formats, plus formats without any codec info, which the generic
+    /// extractor reports for plain video files.
+    pub fn carries_audio_and_video(&self) -> bool {
+        self.is_progressive() || (self.video_codec.is_empty() && self.audio_codec.is_empty())
+    }
 }
 
 impl Display for FormatListItem {
@@ -76,51 +145,83 @@ impl Display for FormatListItem {
             display_text.push_str(format!(" {}fps", self.fps).as_str());
         }
 
+        if self.is_hdr() {
+            display_text.push_str(format!(" {}", self.dynamic_range).as_str());
+        }
+
         if self.quality > 0.0 {
             display_text.push_str(format!(" Q: {}", self.quality).as_str());
         }
 
-        if self.file_size > 0 {
-            let mut refined_file_size = self.file_size;
-            let mut unit = "B";
-            if refined_file_size > 1024 {
-                refined_file_size /= 1024;
-                unit = "KB";
-            }
+        if self.effective_bitrate() > 0.0 {
+            display_text.push_str(format!(" {:.0}kbps", self.effective_bitrate()).as_str());
+        }
 
-            if refined_file_size > 1024 {
-                refined_file_size /= 1024;
-                unit = "MB";
-            }
+        if self.sample_rate > 0 {
+            display_text.push_str(format!(" {}Hz", self.sample_rate).as_str());
+        }
 
-            if refined_file_size > 1024 {
-                refined_file_size /= 1024;
-                unit = "GB";
-            }
+        if self.audio_channels > 0 {
+            display_text.push_str(format!(" {}ch", self.audio_channels).as_str());
+        }
+
+        if self.language.len() > 0 {
+            display_text.push_str(format!(" [{}]", self.language).as_str());
+        }
 
-            display_text.push_str(format!(" {}{}", refined_file_size, unit).as_str());
+        if self.has_drm {
+            display_text.push_str(" DRM");
+        }
+
+        if let Some(estimated_size) = self.estimated_size() {
+            display_text.push_str(format!(" {}", estimated_size).as_str());
         }
         write!(f, "{}", display_text)
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tab {
+    #[default]
+    Download,
+    Subscriptions,
+}
+
 pub struct DownloaderUIState {
     pub status_message: String,
     pub disabled: bool,
-    pub reciever: Option<tokio::sync::broadcast::Receiver<super::message::Message>>,
-    pub sender: Option<tokio::sync::broadcast::Sender<super::message::Message>>,
+    pub active_tab: Tab,
+    pub worker: Option<WorkerHandle>,
+    /// Job of the running download, recording, batch or subscription check, to cancel it.
+    pub download_job: Option<JobId>,
+    /// Jobs the UI sent to the worker. Events of other jobs never touch the panel.
+    pub own_jobs: HashSet<JobId>,
+    /// Latest event of a job the UI did not start.
+    pub background_activity: Option<String>,
+    /// Where UI changes to the settings and subscriptions are saved.
+    pub settings_path: PathBuf,
+    pub subscriptions_path: PathBuf,
     pub executables_dir: String,
     pub output_dir: String,
+    pub download_output_dir: Option<PathBuf>,
     pub video_url: String,
+    pub parsed_video_url: Result<VideoUrl, UrlError>,
     pub video_id: String,
     pub video_title: String,
     pub video_description: String,
     pub video_channel: String,
     pub video_channel_id: String,
+    pub video_channel_url: String,
+    /// Name of the yt-dlp extractor that handled the URL, e.g. `youtube` or `vimeo`.
+    pub video_extractor: String,
     pub video_tags: Vec<String>,
     pub video_categories: Vec<String>,
     pub yt_dlp_version: String,
     pub thumbnail_path: Option<PathBuf>,
+    pub thumbnails: Vec<ThumbnailListItem>,
+    pub selected_thumbnail: Option<ThumbnailListItem>,
+    pub thumbnail_format: ThumbnailFormat,
+    pub crop_thumbnail: bool,
     pub video_path: Option<PathBuf>,
     pub show_download_button: bool,
     pub is_video_downloading: bool,
@@ -128,13 +229,31 @@ pub struct DownloaderUIState {
     pub video_size: f64,
     pub downloaded_size: f64,
     pub progress: f32,
-    pub video_formats: Vec<yt_dlp::model::format::Format>,
-    pub format_selection_list_video: combo_box::State<FormatListItem>,
-    pub format_selection_list_audio: combo_box::State<FormatListItem>,
-    pub format_selection_list_audio_video: combo_box::State<FormatListItem>,
+    pub disk_space_warning: Option<DiskSpaceShortage>,
+    pub live_status: LiveStatus,
+    pub recording_mode: RecordingMode,
+    pub recording_max_minutes: String,
+    pub recording_max_size_mb: String,
+    pub wait_for_start: bool,
+    pub post_download_hook: String,
+    pub hook_outcome: Option<HookOutcome>,
+    pub notification_settings: NotificationSettings,
+    pub video_formats: Vec<Format>,
+    pub video_format_table: FormatTableState,
+    pub audio_format_table: FormatTableState,
+    pub audio_video_format_table: FormatTableState,
     pub selected_format_video: Option<FormatListItem>,
     pub selected_format_audio: Option<FormatListItem>,
     pub selected_format_audio_video: Option<FormatListItem>,
+    pub watch_clipboard: bool,
+    pub last_clipboard_text: String,
+    pub clipboard_url: Option<String>,
+    pub queued_urls: VecDeque<String>,
+    pub is_batch_running: bool,
+    pub batch_report: Option<BatchReport>,
+    pub subscriptions: Vec<Subscription>,
+    pub auto_check_subscriptions: bool,
+    pub is_checking_subscriptions: bool,
 }
 
 impl Default for DownloaderUIState {
@@ -142,21 +261,34 @@ impl Default for DownloaderUIState {
         Self {
             status_message: String::from("Ready"),
             disabled: false,
-            reciever: None,
-            sender: None,
+            active_tab: Tab::default(),
+            worker: None,
+            download_job: None,
+            own_jobs: HashSet::new(),
+            background_activity: None,
+            settings_path: PathBuf::from(SETTINGS_FILE),
+            subscriptions_path: PathBuf::from(SUBSCRIPTIONS_FILE),
             executables_dir: String::from("libs"),
             output_dir: String::from("output"),
-            video_url: String::from("https://www.youtube.com/watch?v=1A6uPztchXk"),
+            download_output_dir: None,
+            video_url: String::new(),
+            parsed_video_url: Err(UrlError::Empty),
             video_id: String::new(),
             video_title: String::new(),
             video_description: String::new(),
             video_channel: String::new(),
             video_channel_id: String::new(),
+            video_channel_url: String::new(),
+            video_extractor: String::new(),
             video_formats: Vec::new(),
             video_tags: Vec::new(),
             video_categories: Vec::new(),
             yt_dlp_version: String::new(),
             thumbnail_path: None,
+            thumbnails: Vec::new(),
+            selected_thumbnail: None,
+            thumbnail_format: ThumbnailFormat::default(),
+            crop_thumbnail: false,
             video_path: None,
             show_download_button: false,
             is_video_downloading: false,
@@ -164,12 +296,30 @@ impl Default for DownloaderUIState {
             video_size: 0.0,
             downloaded_size: 0.0,
             progress: 0.0,
-            format_selection_list_video: combo_box::State::new(Vec::new()),
-            format_selection_list_audio: combo_box::State::new(Vec::new()),
-            format_selection_list_audio_video: combo_box::State::new(Vec::new()),
+            disk_space_warning: None,
+            live_status: LiveStatus::NotLive,
+            recording_mode: RecordingMode::default(),
+            recording_max_minutes: String::new(),
+            recording_max_size_mb: String::new(),
+            wait_for_start: false,
+            post_download_hook: String::new(),
+            hook_outcome: None,
+            notification_settings: NotificationSettings::default(),
+            video_format_table: FormatTableState::default(),
+            audio_format_table: FormatTableState::default(),
+            audio_video_format_table: FormatTableState::default(),
             selected_format_video: None,
             selected_format_audio: None,
             selected_format_audio_video: None,
+            watch_clipboard: false,
+            last_clipboard_text: String::new(),
+            clipboard_url: None,
+            queued_urls: VecDeque::new(),
+            is_batch_running: false,
+            batch_report: None,
+            subscriptions: Vec::new(),
+            auto_check_subscriptions: false,
+            is_checking_subscriptions: false,
         }
     }
 }
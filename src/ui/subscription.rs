@@ -1,24 +1,67 @@
-use super::message::Message;
+use std::time::Duration;
 
-use iced::Subscription;
+use super::{message::Message, state::DownloaderUIState};
+
+use iced::{event, window, Event, Subscription};
 
 use iced::futures::stream;
 
-fn some_worker(tx: tokio::sync::broadcast::Sender<Message>) -> impl stream::Stream<Item = Message> {
-    let mut rx2 = tx.subscribe();
+use crate::subscriptions::DEFAULT_CHECK_INTERVAL;
+use crate::worker::WorkerHandle;
+
+fn worker_events(worker: WorkerHandle) -> impl stream::Stream<Item = Message> {
+    let mut events = worker.subscribe();
+    async_stream::stream! {
+        loop {
+            match events.recv().await {
+                Ok(job_event) => yield Message::WorkerEvent(job_event),
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+/// Messages for the UI from outside the app, like URLs from other instances.
+fn inbox(tx: tokio::sync::broadcast::Sender<Message>) -> impl stream::Stream<Item = Message> {
+    let mut rx = tx.subscribe();
     async_stream::stream! {
         loop {
-         let msg_result = rx2.recv().await;
-            match msg_result {
-                Ok(msg) => {yield msg},
-                Err(_) => break
+            match rx.recv().await {
+                Ok(msg) => yield msg,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
             }
         }
     }
 }
 
-pub fn subscription(tx: tokio::sync::broadcast::Sender<Message>) -> Subscription<Message> {
+fn dropped_file(event: Event, _status: event::Status, _window: window::Id) -> Option<Message> {
+    match event {
+        Event::Window(window::Event::FileDropped(path)) => Some(Message::FileDropped(path)),
+        _ => None,
+    }
+}
+
+pub fn subscription(
+    downloader_ui_state: &DownloaderUIState,
+    worker: WorkerHandle,
+    tx: tokio::sync::broadcast::Sender<Message>,
+) -> Subscription<Message> {
     let subscription: iced_futures::subscription::Subscription<Message> =
-        Subscription::run_with_id("worker", some_worker(tx));
-    subscription
+        Subscription::run_with_id("worker", worker_events(worker));
+    let mut subscriptions = vec![
+        subscription,
+        Subscription::run_with_id("inbox", inbox(tx)),
+        event::listen_with(dropped_file),
+    ];
+    if downloader_ui_state.watch_clipboard {
+        subscriptions
+            .push(iced::time::every(Duration::from_secs(1)).map(|_| Message::PollClipboard));
+    }
+    if downloader_ui_state.auto_check_subscriptions {
+        subscriptions
+            .push(iced::time::every(DEFAULT_CHECK_INTERVAL).map(|_| Message::CheckSubscriptions));
+    }
+    Subscription::batch(subscriptions)
 }
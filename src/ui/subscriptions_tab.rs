@@ -0,0 +1,128 @@
+use iced::{
+    widget::{button, checkbox, container, text, text_input, Column, Row, Scrollable},
+    Alignment, Element, Length,
+};
+
+use super::{message::Message as UIMessage, state::DownloaderUIState};
+use crate::subscriptions::Subscription;
+
+pub fn view(downloader_ui_state: &DownloaderUIState) -> Element<UIMessage> {
+    let header = Row::new()
+        .spacing(10.0)
+        .push(
+            button(match downloader_ui_state.is_checking_subscriptions {
+                true => "Checking...",
+                false => "Check Now",
+            })
+            .on_press_maybe(
+                (!downloader_ui_state.disabled && !downloader_ui_state.subscriptions.is_empty())
+                    .then_some(UIMessage::CheckSubscriptions),
+            ),
+        )
+        .push_maybe(downloader_ui_state.is_checking_subscriptions.then(|| {
+            button("Cancel")
+                .style(button::secondary)
+                .on_press(UIMessage::CancelDownload)
+        }))
+        .push(
+            checkbox(
+                "Check for new uploads every hour",
+                downloader_ui_state.auto_check_subscriptions,
+            )
+            .on_toggle(UIMessage::AutoCheckSubscriptionsToggled),
+        )
+        .align_y(Alignment::Center);
+
+    let subscription_list = match downloader_ui_state.subscriptions.is_empty() {
+        true => Column::new().push(
+            text("No subscriptions yet. Fetch a video and use \"Subscribe to Channel\".").size(14),
+        ),
+        false => downloader_ui_state.subscriptions.iter().fold(
+            Column::new().spacing(10.0),
+            |rows, subscription| {
+                rows.push(subscription_row(
+                    subscription,
+                    &downloader_ui_state.output_dir,
+                ))
+            },
+        ),
+    };
+
+    Column::new()
+        .padding(20.0)
+        .spacing(10.0)
+        .push(header)
+        .push(Scrollable::new(subscription_list).height(Length::Fill))
+        .into()
+}
+
+fn subscription_row<'a>(
+    subscription: &'a Subscription,
+    default_output_dir: &str,
+) -> Element<'a, UIMessage> {
+    let channel_id = subscription.channel_id.clone();
+    let output_dir = subscription
+        .output_dir
+        .as_ref()
+        .map(|output_dir| output_dir.display().to_string())
+        .unwrap_or_default();
+
+    container(
+        Column::new()
+            .spacing(6.0)
+            .push(
+                Row::new()
+                    .spacing(10.0)
+                    .push(text(&subscription.channel_name).size(18))
+                    .push(text(&subscription.channel_url).size(12))
+                    .push(
+                        text(match subscription.last_checked {
+                            Some(_) => {
+                                format!("{} upload(s) seen", subscription.seen_video_ids.len())
+                            }
+                            None => "Not checked yet".to_string(),
+                        })
+                        .size(12),
+                    )
+                    .push(
+                        button(text("Unsubscribe").size(12))
+                            .style(button::secondary)
+                            .on_press(UIMessage::Unsubscribe(channel_id.clone())),
+                    )
+                    .align_y(Alignment::Center),
+            )
+            .push(
+                Row::new()
+                    .spacing(10.0)
+                    .push(
+                        text_input(
+                            "Format policy, e.g. height<=1080,vcodec=vp9 (empty for automatic)",
+                            &subscription.format_policy,
+                        )
+                        .size(14)
+                        .on_input({
+                            let channel_id = channel_id.clone();
+                            move |format_policy| {
+                                UIMessage::SubscriptionPolicyChanged(
+                                    channel_id.clone(),
+                                    format_policy,
+                                )
+                            }
+                        }),
+                    )
+                    .push(
+                        text_input(
+                            &format!("Output folder (default: {})", default_output_dir),
+                            &output_dir,
+                        )
+                        .size(14)
+                        .on_input(move |output_dir| {
+                            UIMessage::SubscriptionOutputDirChanged(channel_id.clone(), output_dir)
+                        }),
+                    ),
+            ),
+    )
+    .padding(10.0)
+    .style(container::bordered_box)
+    .into()
+}
@@ -1,9 +1,63 @@
+use crate::batch::is_batch_file;
+use crate::downloader::{auto_select_formats, best_progressive_format};
+use crate::live::LiveStatus;
+use crate::settings::Settings;
+use crate::subscriptions::{Subscription, SubscriptionStore};
+use crate::thumbnail::list_thumbnails;
+use crate::ui::format_table::{FormatKind, FormatTableState};
 use crate::ui::state::FormatListItem;
+use crate::video_url::{find_video_urls, parse_video_url, urls_from_dropped_file};
+use crate::worker::{WorkerCommand, WorkerEvent};
 
 use super::{message::Message as UIMessage, state::DownloaderUIState};
-use iced::{widget::combo_box, Task};
+use iced::Task;
+
+/// Result of handling a message: follow-up UI work and the commands for the worker.
+pub struct Update {
+    pub task: Task<UIMessage>,
+    pub worker_commands: Vec<WorkerCommand>,
+}
 
 pub fn update(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) -> Task<UIMessage> {
+    let Update {
+        task,
+        worker_commands,
+    } = apply(downloader_ui_state, message);
+    if let Some(worker) = downloader_ui_state.worker.clone() {
+        for command in worker_commands {
+            let cancellable = matches!(
+                command,
+                WorkerCommand::Download { .. }
+                    | WorkerCommand::RecordLive(_)
+                    | WorkerCommand::ImportBatch { .. }
+                    | WorkerCommand::CheckSubscriptions(_)
+            );
+            let job_id = worker.send(command);
+            downloader_ui_state.own_jobs.insert(job_id);
+            if cancellable {
+                downloader_ui_state.download_job = Some(job_id);
+            }
+        }
+    }
+    task
+}
+
+/// Applies `message` to the state without talking to the worker, so state transitions
+/// can be tested without a running app.
+pub fn apply(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) -> Update {
+    let mut worker_commands = Vec::new();
+    let task = handle(downloader_ui_state, message, &mut worker_commands);
+    Update {
+        task,
+        worker_commands,
+    }
+}
+
+fn handle(
+    downloader_ui_state: &mut DownloaderUIState,
+    message: UIMessage,
+    worker_commands: &mut Vec<WorkerCommand>,
+) -> Task<UIMessage> {
     match message {
         UIMessage::StatusMessage(msg) => {
             downloader_ui_state.status_message = msg;
@@ -16,11 +70,7 @@ pub fn update(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) -
         UIMessage::InstallLibraries => {
             downloader_ui_state.status_message = "Installing libraries...".to_string();
             downloader_ui_state.disabled = true;
-            let _ = downloader_ui_state
-                .sender
-                .as_ref()
-                .unwrap()
-                .send(UIMessage::InstallLibraries);
+            worker_commands.push(WorkerCommand::InstallLibraries);
 
             Task::none()
         }
@@ -32,11 +82,7 @@ pub fn update(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) -
         UIMessage::UpdateLibraries => {
             downloader_ui_state.status_message = "Updating libraries...".to_string();
             downloader_ui_state.disabled = true;
-            let _ = downloader_ui_state
-                .sender
-                .as_ref()
-                .unwrap()
-                .send(UIMessage::UpdateLibraries);
+            worker_commands.push(WorkerCommand::UpdateLibraries);
             Task::none()
         }
         UIMessage::LibrariesUpdated => {
@@ -45,18 +91,21 @@ pub fn update(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) -
             Task::none()
         }
         UIMessage::UrlChanged(url) => {
-            downloader_ui_state.video_url = url.clone();
-            let _ = downloader_ui_state
-                .sender
-                .as_ref()
-                .unwrap()
-                .send(UIMessage::UrlChanged(url));
+            downloader_ui_state.parsed_video_url = parse_video_url(&url);
+            downloader_ui_state.video_url = url;
             downloader_ui_state.disabled = false;
             downloader_ui_state.is_video_downloaded = false;
             downloader_ui_state.is_video_downloading = false;
             Task::none()
         }
-        UIMessage::FetchInfo => {
+        message @ (UIMessage::FetchInfo | UIMessage::RefreshInfo) => {
+            let normalized_url = match &downloader_ui_state.parsed_video_url {
+                Ok(video_url) => video_url.normalized(),
+                Err(e) => {
+                    downloader_ui_state.status_message = e.to_string();
+                    return Task::none();
+                }
+            };
             downloader_ui_state.status_message = "Fetching Video Info...".to_string();
             downloader_ui_state.disabled = true;
 
@@ -64,148 +113,130 @@ pub fn update(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) -
             downloader_ui_state.video_title = String::new();
             downloader_ui_state.video_channel = String::new();
             downloader_ui_state.video_channel_id = String::new();
+            downloader_ui_state.video_channel_url = String::new();
+            downloader_ui_state.video_extractor = String::new();
             downloader_ui_state.video_description = String::new();
             downloader_ui_state.thumbnail_path = None;
-            let _ = downloader_ui_state
-                .sender
-                .as_ref()
-                .unwrap()
-                .send(UIMessage::FetchInfo);
+            worker_commands.push(WorkerCommand::FetchInfo {
+                url: normalized_url,
+                refresh: matches!(message, UIMessage::RefreshInfo),
+            });
+            Task::none()
+        }
+        UIMessage::InfoFetchFailed(reason) => {
+            downloader_ui_state.status_message = format!("Failed to fetch video info: {}", reason);
+            downloader_ui_state.disabled = false;
             Task::none()
         }
         UIMessage::InfoFetched(video_info) => {
             downloader_ui_state.status_message = "Video Info Fetched.".to_string();
             downloader_ui_state.disabled = false;
 
-            let auto_selected_video_format = video_info.best_video_format().unwrap().clone();
-            let auto_selected_audio_format = video_info.best_audio_format().unwrap().clone();
-
-            let mut auto_selected_video_format_id: String = String::new();
-            let mut auto_selected_video_format_match_level: i32 = 0;
-            let mut auto_selected_audio_format_id: String = String::new();
-            let mut auto_selected_audio_format_match_level: i32 = 0;
-
-            for video_format in &video_info.formats {
-                if video_format.is_video() {
-                    let mut video_format_match_level = 0;
-                    if video_format.format_id == auto_selected_video_format.format_id {
-                        auto_selected_video_format_id = video_format.format_id.clone();
-                        video_format_match_level = 1;
-                    }
-                    if video_format_match_level > auto_selected_video_format_match_level {
-                        auto_selected_video_format_match_level = video_format_match_level;
-                        break;
-                    }
-                }
-            }
+            let (auto_selected_video_format_id, auto_selected_audio_format_id) =
+                auto_select_formats(&video_info);
+            let auto_selected_audio_video_format_id = match (
+                &auto_selected_video_format_id,
+                &auto_selected_audio_format_id,
+            ) {
+                (Some(_), Some(_)) => None,
+                _ => best_progressive_format(&video_info),
+            };
+            let has_thumbnail = !video_info.thumbnail.is_empty();
 
-            for audio_format in &video_info.formats {
-                if audio_format.is_audio() {
-                    let mut audio_format_match_level = 0;
-                    if audio_format.quality_info.quality
-                        == auto_selected_audio_format.quality_info.quality
-                    {
-                        audio_format_match_level += 1;
-                    }
-                    if audio_format.codec_info.audio_ext
-                        == auto_selected_audio_format.codec_info.audio_ext
-                    {
-                        audio_format_match_level += 1;
-                    }
-                    if audio_format
-                        .format_note
-                        .as_ref()
-                        .unwrap()
-                        .contains("original")
-                    {
-                        audio_format_match_level += 2;
-                    }
-                    if audio_format.codec_info.audio_codec.clone().unwrap()
-                        == auto_selected_audio_format
-                            .codec_info
-                            .audio_codec
-                            .clone()
-                            .unwrap()
-                    {
-                        audio_format_match_level += 1;
-                    }
-
-                    if audio_format.format_id == auto_selected_audio_format.format_id {
-                        audio_format_match_level += 1;
-                    }
-
-                    if audio_format_match_level > auto_selected_audio_format_match_level {
-                        auto_selected_audio_format_id = audio_format.format_id.clone();
-                        auto_selected_audio_format_match_level = audio_format_match_level;
-                    }
-                }
-            }
+            downloader_ui_state.live_status = LiveStatus::parse(&video_info.live_status);
+            downloader_ui_state.wait_for_start =
+                downloader_ui_state.live_status == LiveStatus::IsUpcoming;
+            downloader_ui_state.thumbnails = list_thumbnails(&video_info);
+            downloader_ui_state.selected_thumbnail =
+                downloader_ui_state.thumbnails.first().cloned();
 
             downloader_ui_state.video_id = video_info.id;
             downloader_ui_state.video_title = video_info.title;
             downloader_ui_state.video_channel = video_info.channel;
             downloader_ui_state.video_channel_id = video_info.channel_id;
+            downloader_ui_state.video_channel_url = video_info.channel_url;
+            downloader_ui_state.video_extractor = video_info.extractor;
             downloader_ui_state.video_description = video_info.description;
 
             downloader_ui_state.video_formats = video_info.formats;
 
-            downloader_ui_state.format_selection_list_video = combo_box::State::new(
-                downloader_ui_state
-                    .video_formats
-                    .clone()
-                    .into_iter()
-                    .filter(|format| format.format_note.as_ref().unwrap() != "storyboard")
+            let video_formats = &downloader_ui_state.video_formats;
+            downloader_ui_state.video_format_table = FormatTableState::new(
+                video_formats
+                    .iter()
+                    .filter(|format| !format.is_storyboard())
                     .filter(|format| format.is_video())
-                    .map(|format| FormatListItem::new(&format))
+                    .map(|format| FormatListItem::for_video(format, video_formats))
                     .collect(),
             );
-            downloader_ui_state.format_selection_list_audio = combo_box::State::new(
-                downloader_ui_state
-                    .video_formats
-                    .clone()
-                    .into_iter()
-                    .filter(|format| format.format_note.as_ref().unwrap() != "storyboard")
+            downloader_ui_state.audio_format_table = FormatTableState::new(
+                video_formats
+                    .iter()
+                    .filter(|format| !format.is_storyboard())
                     .filter(|format| format.is_audio())
-                    .map(|format| FormatListItem::new(&format))
+                    .map(|format| FormatListItem::for_video(format, video_formats))
                     .collect(),
             );
 
-            for format_selection_list_video_item in
-                downloader_ui_state.format_selection_list_video.options()
-            {
-                if format_selection_list_video_item.format_id == auto_selected_video_format_id {
-                    downloader_ui_state.selected_format_video =
-                        Some(format_selection_list_video_item.clone());
-                    let _ = downloader_ui_state.sender.as_ref().unwrap().send(
-                        UIMessage::SelectVideoFormat(format_selection_list_video_item.clone()),
-                    );
-                    break;
-                }
+            downloader_ui_state.audio_video_format_table = FormatTableState::new(
+                video_formats
+                    .iter()
+                    .filter(|format| !format.is_storyboard())
+                    .map(|format| FormatListItem::for_video(format, video_formats))
+                    .filter(|format| format.carries_audio_and_video())
+                    .collect(),
+            );
+            downloader_ui_state.selected_format_audio_video = None;
+            downloader_ui_state.download_output_dir = None;
+            worker_commands.push(WorkerCommand::SetDownloadOutputDir(None));
+
+            downloader_ui_state.selected_format_video = downloader_ui_state
+                .video_format_table
+                .formats
+                .iter()
+                .find(|format| Some(&format.format_id) == auto_selected_video_format_id.as_ref())
+                .cloned();
+            if let Some(format) = &downloader_ui_state.selected_format_video {
+                worker_commands.push(WorkerCommand::SelectVideoFormat(format.format_id.clone()));
             }
 
-            for format_selection_list_audio_item in
-                downloader_ui_state.format_selection_list_audio.options()
-            {
-                if format_selection_list_audio_item.format_id == auto_selected_audio_format_id {
-                    downloader_ui_state.selected_format_audio =
-                        Some(format_selection_list_audio_item.clone());
-                    let _ = downloader_ui_state.sender.as_ref().unwrap().send(
-                        UIMessage::SelectAudioFormat(format_selection_list_audio_item.clone()),
-                    );
-                    break;
-                }
+            downloader_ui_state.selected_format_audio = downloader_ui_state
+                .audio_format_table
+                .formats
+                .iter()
+                .find(|format| Some(&format.format_id) == auto_selected_audio_format_id.as_ref())
+                .cloned();
+            if let Some(format) = &downloader_ui_state.selected_format_audio {
+                worker_commands.push(WorkerCommand::SelectAudioFormat(format.format_id.clone()));
             }
 
-            Task::done(UIMessage::FetchThumbnail)
+            // Sites without separate streams get their best combined format instead.
+            downloader_ui_state.selected_format_audio_video = downloader_ui_state
+                .audio_video_format_table
+                .formats
+                .iter()
+                .find(|format| {
+                    Some(&format.format_id) == auto_selected_audio_video_format_id.as_ref()
+                })
+                .cloned();
+            if let Some(format) = &downloader_ui_state.selected_format_audio_video {
+                worker_commands.push(WorkerCommand::SelectAudioVideoFormat(
+                    format.format_id.clone(),
+                ));
+            }
+
+            match has_thumbnail {
+                true => Task::done(UIMessage::FetchThumbnail),
+                false => {
+                    downloader_ui_state.show_download_button = true;
+                    Task::none()
+                }
+            }
         }
         UIMessage::FetchThumbnail => {
             downloader_ui_state.status_message = "Fetching Video Thumbnail...".to_string();
             downloader_ui_state.disabled = true;
-            let _ = downloader_ui_state
-                .sender
-                .as_ref()
-                .unwrap()
-                .send(UIMessage::FetchThumbnail);
+            worker_commands.push(WorkerCommand::FetchThumbnail);
             Task::none()
         }
         UIMessage::ThumbnailFetched(thumbnail_path) => {
@@ -216,35 +247,153 @@ pub fn update(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) -
             downloader_ui_state.show_download_button = true;
             Task::none()
         }
+        UIMessage::ThumbnailSelected(thumbnail) => {
+            downloader_ui_state.selected_thumbnail = Some(thumbnail);
+            Task::none()
+        }
+        UIMessage::ThumbnailFormatSelected(thumbnail_format) => {
+            downloader_ui_state.thumbnail_format = thumbnail_format;
+            Task::none()
+        }
+        UIMessage::CropThumbnailToggled(crop_thumbnail) => {
+            downloader_ui_state.crop_thumbnail = crop_thumbnail;
+            Task::none()
+        }
+        UIMessage::SaveThumbnail(request) => {
+            downloader_ui_state.status_message = "Saving Thumbnail...".to_string();
+            worker_commands.push(WorkerCommand::SaveThumbnail(request));
+            Task::none()
+        }
+        UIMessage::ThumbnailSaved(thumbnail_path) => {
+            downloader_ui_state.status_message =
+                format!("Thumbnail saved to {}.", thumbnail_path.display());
+            downloader_ui_state.thumbnail_path = Some(thumbnail_path);
+            Task::none()
+        }
         UIMessage::SelectAudioFormat(format) => {
-            downloader_ui_state.selected_format_audio = Some(format.clone());
-            let _ = downloader_ui_state
-                .sender
-                .as_ref()
-                .unwrap()
-                .send(UIMessage::SelectAudioFormat(format));
+            worker_commands.push(WorkerCommand::SelectAudioFormat(format.format_id.clone()));
+            downloader_ui_state.selected_format_audio = Some(format);
+            downloader_ui_state.selected_format_audio_video = None;
             Task::none()
         }
         UIMessage::SelectVideoFormat(format) => {
-            downloader_ui_state.selected_format_video = Some(format.clone());
-            let _ = downloader_ui_state
-                .sender
-                .as_ref()
-                .unwrap()
-                .send(UIMessage::SelectVideoFormat(format));
+            worker_commands.push(WorkerCommand::SelectVideoFormat(format.format_id.clone()));
+            downloader_ui_state.selected_format_video = Some(format);
+            downloader_ui_state.selected_format_audio_video = None;
+            Task::none()
+        }
+        UIMessage::SelectAudioVideoFormat(format) => {
+            worker_commands.push(WorkerCommand::SelectAudioVideoFormat(
+                format.format_id.clone(),
+            ));
+            downloader_ui_state.selected_format_audio_video = Some(format);
+            Task::none()
+        }
+        UIMessage::SortFormats(kind, column) => {
+            format_table(downloader_ui_state, kind).sort_by(column);
+            Task::none()
+        }
+        UIMessage::FilterFormats(kind, filter) => {
+            format_table(downloader_ui_state, kind).filter = filter;
             Task::none()
         }
-        UIMessage::DownloadVideo => {
+        message @ (UIMessage::DownloadVideo | UIMessage::DownloadVideoAnyway) => {
             downloader_ui_state.is_video_downloading = true;
             downloader_ui_state.status_message = "Downloading Video...".to_string();
             downloader_ui_state.disabled = true;
+            downloader_ui_state.disk_space_warning = None;
 
-            let _ = downloader_ui_state
-                .sender
-                .as_ref()
-                .unwrap()
-                .send(UIMessage::DownloadVideo);
+            worker_commands.push(WorkerCommand::Download {
+                check_disk_space: matches!(message, UIMessage::DownloadVideo),
+            });
+
+            Task::none()
+        }
+        UIMessage::RecordingModeSelected(recording_mode) => {
+            downloader_ui_state.recording_mode = recording_mode;
+            Task::none()
+        }
+        UIMessage::RecordingMaxMinutesChanged(max_minutes) => {
+            downloader_ui_state.recording_max_minutes = max_minutes;
+            Task::none()
+        }
+        UIMessage::RecordingMaxSizeChanged(max_size_mb) => {
+            downloader_ui_state.recording_max_size_mb = max_size_mb;
+            Task::none()
+        }
+        UIMessage::WaitForStartToggled(wait_for_start) => {
+            downloader_ui_state.wait_for_start = wait_for_start;
+            Task::none()
+        }
+        UIMessage::RecordLive(options) => {
+            downloader_ui_state.is_video_downloading = true;
+            downloader_ui_state.status_message = match options.wait_for_start {
+                true => "Waiting for the stream to start...".to_string(),
+                false => "Recording...".to_string(),
+            };
+            downloader_ui_state.disabled = true;
 
+            worker_commands.push(WorkerCommand::RecordLive(options));
+
+            Task::none()
+        }
+        UIMessage::InsufficientDiskSpace(shortage) => {
+            downloader_ui_state.status_message = shortage.to_string();
+            downloader_ui_state.disabled = false;
+            downloader_ui_state.is_video_downloading = false;
+            downloader_ui_state.download_job = None;
+            downloader_ui_state.disk_space_warning = Some(shortage);
+            Task::none()
+        }
+        UIMessage::DismissDiskSpaceWarning => {
+            downloader_ui_state.disk_space_warning = None;
+            downloader_ui_state.status_message = "Download cancelled.".to_string();
+            Task::none()
+        }
+        UIMessage::ChooseOutputDir => Task::perform(
+            rfd::AsyncFileDialog::new()
+                .set_title("Choose a download folder")
+                .set_directory(&downloader_ui_state.output_dir)
+                .pick_folder(),
+            |folder| UIMessage::OutputDirChosen(folder.map(|folder| folder.path().to_path_buf())),
+        ),
+        UIMessage::OutputDirChosen(None) => Task::none(),
+        UIMessage::OutputDirChosen(Some(output_dir)) => {
+            downloader_ui_state.output_dir = output_dir.display().to_string();
+            let mut settings = Settings::load_from(&downloader_ui_state.settings_path);
+            settings.output_dir = output_dir.clone();
+            if let Err(e) = settings.save_to(&downloader_ui_state.settings_path) {
+                downloader_ui_state.status_message = format!("Failed to save settings: {}", e);
+            }
+            worker_commands.push(WorkerCommand::SetOutputDir(output_dir));
+            Task::none()
+        }
+        UIMessage::ChooseDownloadOutputDir => Task::perform(
+            rfd::AsyncFileDialog::new()
+                .set_title("Save this download to")
+                .set_directory(
+                    downloader_ui_state
+                        .download_output_dir
+                        .clone()
+                        .unwrap_or(downloader_ui_state.output_dir.clone().into()),
+                )
+                .pick_folder(),
+            |folder| {
+                UIMessage::DownloadOutputDirChosen(folder.map(|folder| folder.path().to_path_buf()))
+            },
+        ),
+        UIMessage::DownloadOutputDirChosen(None) => Task::none(),
+        UIMessage::DownloadOutputDirChosen(Some(output_dir)) => {
+            downloader_ui_state.download_output_dir = Some(output_dir.clone());
+            worker_commands.push(WorkerCommand::SetDownloadOutputDir(Some(output_dir)));
+            match downloader_ui_state.disk_space_warning.take() {
+                Some(_) => Task::done(UIMessage::DownloadVideo),
+                None => Task::none(),
+            }
+        }
+        UIMessage::SetDownloadOutputDir(None) => {
+            downloader_ui_state.download_output_dir = None;
+            worker_commands.push(WorkerCommand::SetDownloadOutputDir(None));
             Task::none()
         }
         UIMessage::VideoDownloaded(video_path) => {
@@ -256,15 +405,285 @@ pub fn update(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) -
 
             downloader_ui_state.video_path = video_path;
             downloader_ui_state.is_video_downloading = false;
+            downloader_ui_state.download_job = None;
             downloader_ui_state.is_video_downloaded = true;
+            downloader_ui_state.hook_outcome = None;
 
             Task::none()
         }
+        UIMessage::OpenVideo => {
+            if let Some(video_path) = &downloader_ui_state.video_path {
+                if let Err(e) = open::that_detached(video_path) {
+                    downloader_ui_state.status_message =
+                        format!("Failed to open {}: {}", video_path.display(), e);
+                }
+            }
+            Task::none()
+        }
+        UIMessage::RevealVideo => {
+            if let Some(video_dir) = downloader_ui_state
+                .video_path
+                .as_ref()
+                .and_then(|video_path| video_path.parent())
+            {
+                if let Err(e) = open::that_detached(video_dir) {
+                    downloader_ui_state.status_message =
+                        format!("Failed to open {}: {}", video_dir.display(), e);
+                }
+            }
+            Task::none()
+        }
+        UIMessage::PostDownloadHookChanged(post_download_hook) => {
+            downloader_ui_state.post_download_hook = post_download_hook;
+            Task::none()
+        }
+        UIMessage::SavePostDownloadHook => {
+            let post_download_hook = Some(downloader_ui_state.post_download_hook.trim())
+                .filter(|post_download_hook| !post_download_hook.is_empty())
+                .map(str::to_string);
+            let mut settings = Settings::load_from(&downloader_ui_state.settings_path);
+            settings.post_download_hook = post_download_hook.clone();
+            downloader_ui_state.status_message =
+                match settings.save_to(&downloader_ui_state.settings_path) {
+                    Ok(()) => "Post-download hook saved.".to_string(),
+                    Err(e) => format!("Failed to save settings: {}", e),
+                };
+            worker_commands.push(WorkerCommand::SetPostDownloadHook(post_download_hook));
+            Task::none()
+        }
+        UIMessage::HookFinished(outcome) => {
+            downloader_ui_state.status_message = outcome.to_string();
+            downloader_ui_state.hook_outcome = Some(outcome);
+            Task::none()
+        }
+        UIMessage::NotificationSettingsChanged(notification_settings) => {
+            downloader_ui_state.notification_settings = notification_settings.clone();
+            let mut settings = Settings::load_from(&downloader_ui_state.settings_path);
+            settings.notifications = notification_settings.clone();
+            if let Err(e) = settings.save_to(&downloader_ui_state.settings_path) {
+                downloader_ui_state.status_message = format!("Failed to save settings: {}", e);
+            }
+            worker_commands.push(WorkerCommand::SetNotificationSettings(
+                notification_settings,
+            ));
+            Task::none()
+        }
+        UIMessage::CancelDownload => {
+            if let Some(job_id) = downloader_ui_state.download_job {
+                downloader_ui_state.status_message = "Cancelling download...".to_string();
+                worker_commands.push(WorkerCommand::Cancel(job_id));
+            }
+            Task::none()
+        }
+        UIMessage::DownloadFailed(reason) => {
+            downloader_ui_state.status_message = format!("Failed to download video: {}", reason);
+            downloader_ui_state.disabled = false;
+            downloader_ui_state.is_video_downloading = false;
+            downloader_ui_state.download_job = None;
+            downloader_ui_state.progress = 0.0;
+            Task::none()
+        }
         UIMessage::ProgressUpdated(downloaded_size, progress) => {
             downloader_ui_state.downloaded_size = downloaded_size;
             downloader_ui_state.progress = progress;
             Task::none()
         }
+        UIMessage::WatchClipboardToggled(watch_clipboard) => {
+            downloader_ui_state.watch_clipboard = watch_clipboard;
+            if !watch_clipboard {
+                downloader_ui_state.clipboard_url = None;
+            }
+            Task::none()
+        }
+        UIMessage::PollClipboard => iced::clipboard::read().map(UIMessage::ClipboardRead),
+        UIMessage::ClipboardRead(clipboard_text) => {
+            let clipboard_text = clipboard_text.unwrap_or_default();
+            if clipboard_text == downloader_ui_state.last_clipboard_text {
+                return Task::none();
+            }
+            downloader_ui_state.last_clipboard_text = clipboard_text.clone();
+            if let Some(url) = find_video_urls(&clipboard_text).into_iter().next() {
+                if url != downloader_ui_state.video_url {
+                    downloader_ui_state.clipboard_url = Some(url);
+                }
+            }
+            Task::none()
+        }
+        UIMessage::LoadClipboardUrl => match downloader_ui_state.clipboard_url.take() {
+            Some(url) => load_url(url),
+            None => Task::none(),
+        },
+        UIMessage::DismissClipboardUrl => {
+            downloader_ui_state.clipboard_url = None;
+            Task::none()
+        }
+        UIMessage::FileDropped(path) if is_batch_file(&path) => {
+            Task::done(UIMessage::ImportBatch(path))
+        }
+        UIMessage::FileDropped(path) => match urls_from_dropped_file(&path) {
+            Ok(urls) if urls.is_empty() => {
+                downloader_ui_state.status_message =
+                    format!("No video links found in {}.", path.display());
+                Task::none()
+            }
+            Ok(urls) => {
+                downloader_ui_state.status_message = format!(
+                    "Imported {} video link(s) from {}.",
+                    urls.len(),
+                    path.display()
+                );
+                downloader_ui_state.queued_urls.extend(urls);
+                if downloader_ui_state.disabled {
+                    Task::none()
+                } else {
+                    Task::done(UIMessage::LoadNextQueuedUrl)
+                }
+            }
+            Err(e) => {
+                downloader_ui_state.status_message =
+                    format!("Failed to read dropped file {}: {}", path.display(), e);
+                Task::none()
+            }
+        },
+        UIMessage::QueueUrls(urls) => {
+            let urls: Vec<String> = urls
+                .iter()
+                .filter_map(|url| parse_video_url(url).ok())
+                .map(|video_url| video_url.normalized())
+                .collect();
+            if urls.is_empty() {
+                downloader_ui_state.status_message = "No valid video links received.".to_string();
+                return Task::none();
+            }
+            downloader_ui_state.status_message = format!("Queued {} video link(s).", urls.len());
+            downloader_ui_state.queued_urls.extend(urls);
+            if downloader_ui_state.disabled {
+                Task::none()
+            } else {
+                Task::done(UIMessage::LoadNextQueuedUrl)
+            }
+        }
+        UIMessage::FocusWindow => iced::window::get_oldest().and_then(iced::window::gain_focus),
+        UIMessage::LoadNextQueuedUrl => match downloader_ui_state.queued_urls.pop_front() {
+            Some(url) => load_url(url),
+            None => Task::none(),
+        },
+        UIMessage::ImportBatch(path) => {
+            if downloader_ui_state.disabled {
+                downloader_ui_state.status_message =
+                    format!("Busy, cannot import {} right now.", path.display());
+                return Task::none();
+            }
+            downloader_ui_state.status_message = format!("Importing {}...", path.display());
+            downloader_ui_state.disabled = true;
+            downloader_ui_state.is_batch_running = true;
+            downloader_ui_state.batch_report = None;
+            worker_commands.push(WorkerCommand::ImportBatch {
+                path,
+                refresh: false,
+            });
+            Task::none()
+        }
+        UIMessage::BatchFinished(report) => {
+            downloader_ui_state.status_message = report.to_string();
+            downloader_ui_state.disabled = false;
+            downloader_ui_state.is_batch_running = false;
+            downloader_ui_state.download_job = None;
+            downloader_ui_state.batch_report = Some(report);
+            Task::none()
+        }
+        UIMessage::TabSelected(tab) => {
+            downloader_ui_state.active_tab = tab;
+            Task::none()
+        }
+        UIMessage::SubscribeToChannel => {
+            let subscription = Subscription::new(
+                &downloader_ui_state.video_channel_id,
+                &downloader_ui_state.video_channel,
+                &downloader_ui_state.video_channel_url,
+            );
+            edit_subscriptions(downloader_ui_state, |store| {
+                match store.subscribe(subscription) {
+                    true => "Subscribed. New uploads will be downloaded from now on.",
+                    false => "Already subscribed to this channel.",
+                }
+            })
+        }
+        UIMessage::Unsubscribe(channel_id) => edit_subscriptions(downloader_ui_state, |store| {
+            store.unsubscribe(&channel_id);
+            "Unsubscribed."
+        }),
+        UIMessage::SubscriptionPolicyChanged(channel_id, format_policy) => {
+            edit_subscriptions(downloader_ui_state, |store| {
+                if let Some(subscription) = store.get_mut(&channel_id) {
+                    subscription.format_policy = format_policy;
+                }
+                "Subscription updated."
+            })
+        }
+        UIMessage::SubscriptionOutputDirChanged(channel_id, output_dir) => {
+            edit_subscriptions(downloader_ui_state, |store| {
+                if let Some(subscription) = store.get_mut(&channel_id) {
+                    subscription.output_dir =
+                        (!output_dir.trim().is_empty()).then(|| output_dir.into());
+                }
+                "Subscription updated."
+            })
+        }
+        UIMessage::AutoCheckSubscriptionsToggled(enabled) => {
+            downloader_ui_state.auto_check_subscriptions = enabled;
+            Task::none()
+        }
+        UIMessage::CheckSubscriptions => {
+            // Periodic checks simply skip a beat while another job is running.
+            if downloader_ui_state.disabled {
+                return Task::none();
+            }
+            downloader_ui_state.status_message = "Checking subscriptions...".to_string();
+            downloader_ui_state.disabled = true;
+            downloader_ui_state.is_checking_subscriptions = true;
+            worker_commands.push(WorkerCommand::CheckSubscriptions(
+                downloader_ui_state.subscriptions_path.clone(),
+            ));
+            Task::none()
+        }
+        UIMessage::SubscriptionsChecked(report) => {
+            downloader_ui_state.status_message = match report.total {
+                0 => "No new uploads.".to_string(),
+                _ => report.to_string(),
+            };
+            downloader_ui_state.disabled = false;
+            downloader_ui_state.is_checking_subscriptions = false;
+            downloader_ui_state.download_job = None;
+            downloader_ui_state.subscriptions =
+                SubscriptionStore::load_from(&downloader_ui_state.subscriptions_path).subscriptions;
+            downloader_ui_state.batch_report = Some(report);
+            Task::none()
+        }
+        UIMessage::WorkerEvent(job_event) => {
+            if downloader_ui_state.own_jobs.contains(&job_event.job_id) {
+                return handle(
+                    downloader_ui_state,
+                    UIMessage::from(job_event.event),
+                    worker_commands,
+                );
+            }
+            match background_activity(&job_event.event) {
+                Some(activity) => handle(
+                    downloader_ui_state,
+                    UIMessage::BackgroundActivity(format!(
+                        "Background job {}: {}",
+                        job_event.job_id, activity
+                    )),
+                    worker_commands,
+                ),
+                None => Task::none(),
+            }
+        }
+        UIMessage::BackgroundActivity(activity) => {
+            downloader_ui_state.background_activity = Some(activity);
+            Task::none()
+        }
         UIMessage::Number(num) => {
             println!("UI Recieved number: {}", num);
             Task::none()
@@ -272,3 +691,1099 @@ pub fn update(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) -
         _ => Task::none(),
     }
 }
+
+/// Summary of an event from a job started by the API or the CLI, `None` for events
+/// not worth showing.
+fn background_activity(event: &WorkerEvent) -> Option<String> {
+    match event {
+        WorkerEvent::Status(status) | WorkerEvent::Failed(status) => Some(status.clone()),
+        WorkerEvent::Progress { percent, .. } => Some(format!("downloading, {:.1}%", percent)),
+        WorkerEvent::InfoFetched(video_info) => Some(format!("fetched {}", video_info.title)),
+        WorkerEvent::InfoFetchFailed(reason) | WorkerEvent::DownloadFailed(reason) => {
+            Some(format!("failed: {}", reason))
+        }
+        WorkerEvent::InsufficientDiskSpace(shortage) => Some(shortage.to_string()),
+        WorkerEvent::VideoDownloaded(path) => Some(format!("downloaded {}", path.display())),
+        WorkerEvent::BatchFinished(report) | WorkerEvent::SubscriptionsChecked(report) => {
+            Some(report.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Applies `edit` to the stored subscriptions and saves them.
+fn edit_subscriptions(
+    downloader_ui_state: &mut DownloaderUIState,
+    edit: impl FnOnce(&mut SubscriptionStore) -> &'static str,
+) -> Task<UIMessage> {
+    let mut store = SubscriptionStore::load_from(&downloader_ui_state.subscriptions_path);
+    let status_message = edit(&mut store);
+    downloader_ui_state.status_message =
+        match store.save_to(&downloader_ui_state.subscriptions_path) {
+            Ok(()) => status_message.to_string(),
+            Err(e) => format!("Failed to save subscriptions: {}", e),
+        };
+    downloader_ui_state.subscriptions = store.subscriptions;
+    Task::none()
+}
+
+fn format_table(
+    downloader_ui_state: &mut DownloaderUIState,
+    kind: FormatKind,
+) -> &mut FormatTableState {
+    match kind {
+        FormatKind::Video => &mut downloader_ui_state.video_format_table,
+        FormatKind::Audio => &mut downloader_ui_state.audio_format_table,
+        FormatKind::AudioVideo => &mut downloader_ui_state.audio_video_format_table,
+    }
+}
+
+fn load_url(url: String) -> Task<UIMessage> {
+    Task::done(UIMessage::UrlChanged(url)).chain(Task::done(UIMessage::FetchInfo))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+    use crate::backend::{Format, Thumbnail, Video};
+    use crate::batch::BatchReport;
+    use crate::disk_space::DiskSpaceShortage;
+    use crate::file_size::FileSize;
+    use crate::hook::HookOutcome;
+    use crate::live::{RecordingMode, RecordingOptions};
+    use crate::notifications::NotificationSettings;
+    use crate::thumbnail::{ThumbnailFormat, ThumbnailRequest};
+    use crate::ui::format_table::FormatColumn;
+    use crate::ui::state::Tab;
+    use crate::worker::JobEvent;
+
+    const VIDEO_URL: &str = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+    const CHANNEL_ID: &str = "UCmockchannel000000000000";
+
+    /// One state transition: `setup` prepares the state, `message` is applied, then the
+    /// worker commands are compared with `commands` and `check` inspects the state.
+    /// All functions get the case's scratch dir.
+    struct Case {
+        name: &'static str,
+        setup: fn(&Path, &mut DownloaderUIState),
+        message: fn(&Path) -> UIMessage,
+        commands: fn(&Path) -> Vec<WorkerCommand>,
+        check: fn(&Path, &DownloaderUIState),
+    }
+
+    fn no_setup(_: &Path, _: &mut DownloaderUIState) {}
+
+    fn no_commands(_: &Path) -> Vec<WorkerCommand> {
+        Vec::new()
+    }
+
+    fn test_video() -> Video {
+        let audio = Format {
+            format_id: "140".to_string(),
+            format_note: Some("medium".to_string()),
+            url: "http://mock.invalid/audio.m4a".to_string(),
+            video_codec: Some("none".to_string()),
+            audio_codec: Some("mp4a.40.2".to_string()),
+            video_ext: Some("none".to_string()),
+            audio_ext: Some("m4a".to_string()),
+            quality: Some(3.0),
+            filesize: Some(65536),
+            total_bitrate: Some(129.5),
+            ..Format::default()
+        };
+        let video = Format {
+            format_id: "137".to_string(),
+            format_note: Some("1080p".to_string()),
+            url: "http://mock.invalid/video.mp4".to_string(),
+            video_codec: Some("avc1.640028".to_string()),
+            audio_codec: Some("none".to_string()),
+            video_ext: Some("mp4".to_string()),
+            audio_ext: Some("none".to_string()),
+            width: Some(1920),
+            height: Some(1080),
+            quality: Some(9.0),
+            filesize: Some(131072),
+            total_bitrate: Some(2500.0),
+            ..Format::default()
+        };
+        let thumbnail = Thumbnail {
+            id: "0".to_string(),
+            url: "http://mock.invalid/thumbnail.jpg".to_string(),
+            width: Some(1280),
+            height: Some(720),
+        };
+        Video {
+            id: "dQw4w9WgXcQ".to_string(),
+            title: "Test Video".to_string(),
+            channel: "Mock Channel".to_string(),
+            channel_id: CHANNEL_ID.to_string(),
+            thumbnail: thumbnail.url.clone(),
+            thumbnails: vec![thumbnail],
+            formats: vec![audio, video],
+            live_status: "not_live".to_string(),
+            extractor: "youtube".to_string(),
+            extractor_key: "Youtube".to_string(),
+            ..Video::default()
+        }
+    }
+
+    fn test_format(format_id: &str) -> FormatListItem {
+        test_video()
+            .formats
+            .iter()
+            .find(|format| format.format_id == format_id)
+            .map(FormatListItem::new)
+            .unwrap()
+    }
+
+    fn thumbnail_request() -> ThumbnailRequest {
+        ThumbnailRequest {
+            url: "http://mock.invalid/thumbnail.jpg".to_string(),
+            format: ThumbnailFormat::Jpeg,
+            crop_square: false,
+        }
+    }
+
+    fn subscribed(dir: &Path, downloader_ui_state: &mut DownloaderUIState) {
+        let mut store = SubscriptionStore::default();
+        store.subscribe(Subscription::new(
+            CHANNEL_ID,
+            "Mock Channel",
+            "https://www.youtube.com/channel/UCmockchannel000000000000",
+        ));
+        store.save_to(&dir.join("subscriptions.json")).unwrap();
+        downloader_ui_state.subscriptions = store.subscriptions;
+    }
+
+    fn saved_settings(downloader_ui_state: &DownloaderUIState) -> Settings {
+        Settings::load_from(&downloader_ui_state.settings_path)
+    }
+
+    fn cases() -> Vec<Case> {
+        vec![
+            Case {
+                name: "number is only logged",
+                setup: no_setup,
+                message: |_| UIMessage::Number(7),
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.status_message, "Ready"),
+            },
+            Case {
+                name: "status message is shown",
+                setup: no_setup,
+                message: |_| UIMessage::StatusMessage("Working".to_string()),
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.status_message, "Working"),
+            },
+            Case {
+                name: "disabled toggles the controls",
+                setup: no_setup,
+                message: |_| UIMessage::Disabled(true),
+                commands: no_commands,
+                check: |_, state| assert!(state.disabled),
+            },
+            Case {
+                name: "install libraries disables the controls",
+                setup: no_setup,
+                message: |_| UIMessage::InstallLibraries,
+                commands: |_| vec![WorkerCommand::InstallLibraries],
+                check: |_, state| {
+                    assert!(state.disabled);
+                    assert_eq!(state.status_message, "Installing libraries...");
+                },
+            },
+            Case {
+                name: "libraries installed enables the controls",
+                setup: |_, state| state.disabled = true,
+                message: |_| UIMessage::LibrariesInstalled,
+                commands: no_commands,
+                check: |_, state| assert!(!state.disabled),
+            },
+            Case {
+                name: "update libraries disables the controls",
+                setup: no_setup,
+                message: |_| UIMessage::UpdateLibraries,
+                commands: |_| vec![WorkerCommand::UpdateLibraries],
+                check: |_, state| assert!(state.disabled),
+            },
+            Case {
+                name: "libraries updated enables the controls",
+                setup: |_, state| state.disabled = true,
+                message: |_| UIMessage::LibrariesUpdated,
+                commands: no_commands,
+                check: |_, state| assert!(!state.disabled),
+            },
+            Case {
+                name: "ui updated is ignored",
+                setup: no_setup,
+                message: |_| UIMessage::UIUpdated,
+                commands: no_commands,
+                check: |_, state| assert!(!state.disabled),
+            },
+            Case {
+                name: "url changed parses the url",
+                setup: |_, state| state.is_video_downloaded = true,
+                message: |_| UIMessage::UrlChanged("https://youtu.be/dQw4w9WgXcQ".to_string()),
+                commands: no_commands,
+                check: |_, state| {
+                    assert_eq!(state.video_url, "https://youtu.be/dQw4w9WgXcQ");
+                    assert!(state.parsed_video_url.is_ok());
+                    assert!(!state.is_video_downloaded);
+                },
+            },
+            Case {
+                name: "url changed keeps invalid input",
+                setup: no_setup,
+                message: |_| UIMessage::UrlChanged("not a url".to_string()),
+                commands: no_commands,
+                check: |_, state| assert!(state.parsed_video_url.is_err()),
+            },
+            Case {
+                name: "fetch info sends the normalized url",
+                setup: |_, state| {
+                    update_state(state, UIMessage::UrlChanged(VIDEO_URL.to_string()));
+                    state.video_title = "Previous".to_string();
+                },
+                message: |_| UIMessage::FetchInfo,
+                commands: |_| {
+                    vec![WorkerCommand::FetchInfo {
+                        url: VIDEO_URL.to_string(),
+                        refresh: false,
+                    }]
+                },
+                check: |_, state| {
+                    assert!(state.disabled);
+                    assert!(state.video_title.is_empty());
+                },
+            },
+            Case {
+                name: "fetch info without a valid url reports the problem",
+                setup: no_setup,
+                message: |_| UIMessage::FetchInfo,
+                commands: no_commands,
+                check: |_, state| {
+                    assert!(!state.disabled);
+                    assert_eq!(state.status_message, "Enter a video URL");
+                },
+            },
+            Case {
+                name: "refresh info bypasses the cache in the worker",
+                setup: |_, state| {
+                    update_state(state, UIMessage::UrlChanged(VIDEO_URL.to_string()));
+                },
+                message: |_| UIMessage::RefreshInfo,
+                commands: |_| {
+                    vec![WorkerCommand::FetchInfo {
+                        url: VIDEO_URL.to_string(),
+                        refresh: true,
+                    }]
+                },
+                check: |_, state| assert!(state.disabled),
+            },
+            Case {
+                name: "info fetched fills the panel and selects formats",
+                setup: |_, state| state.disabled = true,
+                message: |_| UIMessage::InfoFetched(test_video()),
+                commands: |_| {
+                    vec![
+                        WorkerCommand::SetDownloadOutputDir(None),
+                        WorkerCommand::SelectVideoFormat("137".to_string()),
+                        WorkerCommand::SelectAudioFormat("140".to_string()),
+                    ]
+                },
+                check: |_, state| {
+                    assert!(!state.disabled);
+                    assert_eq!(state.video_title, "Test Video");
+                    assert_eq!(state.video_extractor, "youtube");
+                    assert_eq!(state.video_channel_id, CHANNEL_ID);
+                    assert_eq!(
+                        state
+                            .selected_format_video
+                            .as_ref()
+                            .map(|format| format.format_id.as_str()),
+                        Some("137")
+                    );
+                    assert_eq!(
+                        state
+                            .selected_format_audio
+                            .as_ref()
+                            .map(|format| format.format_id.as_str()),
+                        Some("140")
+                    );
+                    assert!(state.selected_format_audio_video.is_none());
+                    assert_eq!(state.thumbnails.len(), 1);
+                    // The download button waits for the thumbnail.
+                    assert!(!state.show_download_button);
+                },
+            },
+            Case {
+                name: "info fetch failure enables the controls",
+                setup: |_, state| state.disabled = true,
+                message: |_| UIMessage::InfoFetchFailed("Video unavailable".to_string()),
+                commands: no_commands,
+                check: |_, state| {
+                    assert!(!state.disabled);
+                    assert!(state.status_message.contains("Video unavailable"));
+                },
+            },
+            Case {
+                name: "fetch thumbnail disables the controls",
+                setup: no_setup,
+                message: |_| UIMessage::FetchThumbnail,
+                commands: |_| vec![WorkerCommand::FetchThumbnail],
+                check: |_, state| assert!(state.disabled),
+            },
+            Case {
+                name: "thumbnail fetched shows the download button",
+                setup: |_, state| state.disabled = true,
+                message: |dir| UIMessage::ThumbnailFetched(Some(dir.join("thumbnail.jpg"))),
+                commands: no_commands,
+                check: |dir, state| {
+                    assert!(!state.disabled);
+                    assert!(state.show_download_button);
+                    assert_eq!(state.thumbnail_path, Some(dir.join("thumbnail.jpg")));
+                },
+            },
+            Case {
+                name: "thumbnail selected",
+                setup: no_setup,
+                message: |_| UIMessage::ThumbnailSelected(list_thumbnails(&test_video()).remove(0)),
+                commands: no_commands,
+                check: |_, state| assert!(state.selected_thumbnail.is_some()),
+            },
+            Case {
+                name: "thumbnail format selected",
+                setup: no_setup,
+                message: |_| UIMessage::ThumbnailFormatSelected(ThumbnailFormat::Png),
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.thumbnail_format, ThumbnailFormat::Png),
+            },
+            Case {
+                name: "crop thumbnail toggled",
+                setup: no_setup,
+                message: |_| UIMessage::CropThumbnailToggled(true),
+                commands: no_commands,
+                check: |_, state| assert!(state.crop_thumbnail),
+            },
+            Case {
+                name: "save thumbnail is done by the worker",
+                setup: no_setup,
+                message: |_| UIMessage::SaveThumbnail(thumbnail_request()),
+                commands: |_| vec![WorkerCommand::SaveThumbnail(thumbnail_request())],
+                check: |_, state| assert_eq!(state.status_message, "Saving Thumbnail..."),
+            },
+            Case {
+                name: "thumbnail saved",
+                setup: no_setup,
+                message: |dir| UIMessage::ThumbnailSaved(dir.join("cover.png")),
+                commands: no_commands,
+                check: |dir, state| assert_eq!(state.thumbnail_path, Some(dir.join("cover.png"))),
+            },
+            Case {
+                name: "download video disables the controls",
+                setup: |dir, state| {
+                    state.disk_space_warning = Some(DiskSpaceShortage {
+                        dir: dir.to_path_buf(),
+                        required: FileSize::exact(2048),
+                        available: 1024,
+                    })
+                },
+                message: |_| UIMessage::DownloadVideo,
+                commands: |_| {
+                    vec![WorkerCommand::Download {
+                        check_disk_space: true,
+                    }]
+                },
+                check: |_, state| {
+                    assert!(state.disabled);
+                    assert!(state.is_video_downloading);
+                    assert!(state.disk_space_warning.is_none());
+                },
+            },
+            Case {
+                name: "download anyway skips the disk space check in the worker",
+                setup: no_setup,
+                message: |_| UIMessage::DownloadVideoAnyway,
+                commands: |_| {
+                    vec![WorkerCommand::Download {
+                        check_disk_space: false,
+                    }]
+                },
+                check: |_, state| assert!(state.is_video_downloading),
+            },
+            Case {
+                name: "recording mode selected",
+                setup: no_setup,
+                message: |_| UIMessage::RecordingModeSelected(RecordingMode::FromStart),
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.recording_mode, RecordingMode::FromStart),
+            },
+            Case {
+                name: "recording max minutes changed",
+                setup: no_setup,
+                message: |_| UIMessage::RecordingMaxMinutesChanged("30".to_string()),
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.recording_max_minutes, "30"),
+            },
+            Case {
+                name: "recording max size changed",
+                setup: no_setup,
+                message: |_| UIMessage::RecordingMaxSizeChanged("500".to_string()),
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.recording_max_size_mb, "500"),
+            },
+            Case {
+                name: "wait for start toggled",
+                setup: no_setup,
+                message: |_| UIMessage::WaitForStartToggled(true),
+                commands: no_commands,
+                check: |_, state| assert!(state.wait_for_start),
+            },
+            Case {
+                name: "record live is done by the worker",
+                setup: no_setup,
+                message: |_| {
+                    UIMessage::RecordLive(RecordingOptions {
+                        wait_for_start: true,
+                        ..RecordingOptions::default()
+                    })
+                },
+                commands: |_| {
+                    vec![WorkerCommand::RecordLive(RecordingOptions {
+                        wait_for_start: true,
+                        ..RecordingOptions::default()
+                    })]
+                },
+                check: |_, state| {
+                    assert!(state.disabled);
+                    assert!(state.is_video_downloading);
+                    assert_eq!(state.status_message, "Waiting for the stream to start...");
+                },
+            },
+            Case {
+                name: "insufficient disk space shows the warning",
+                setup: |_, state| {
+                    state.disabled = true;
+                    state.is_video_downloading = true;
+                },
+                message: |dir| {
+                    UIMessage::InsufficientDiskSpace(DiskSpaceShortage {
+                        dir: dir.to_path_buf(),
+                        required: FileSize::exact(2048),
+                        available: 1024,
+                    })
+                },
+                commands: no_commands,
+                check: |_, state| {
+                    assert!(!state.disabled);
+                    assert!(!state.is_video_downloading);
+                    assert!(state.disk_space_warning.is_some());
+                },
+            },
+            Case {
+                name: "dismissing the disk space warning cancels the download",
+                setup: |dir, state| {
+                    state.disk_space_warning = Some(DiskSpaceShortage {
+                        dir: dir.to_path_buf(),
+                        required: FileSize::exact(2048),
+                        available: 1024,
+                    })
+                },
+                message: |_| UIMessage::DismissDiskSpaceWarning,
+                commands: no_commands,
+                check: |_, state| {
+                    assert!(state.disk_space_warning.is_none());
+                    assert_eq!(state.status_message, "Download cancelled.");
+                },
+            },
+            Case {
+                name: "choose output dir only opens a dialog",
+                setup: no_setup,
+                message: |_| UIMessage::ChooseOutputDir,
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.output_dir, "output"),
+            },
+            Case {
+                name: "output dir chosen is saved and sent to the worker",
+                setup: no_setup,
+                message: |dir| UIMessage::OutputDirChosen(Some(dir.join("videos"))),
+                commands: |dir| vec![WorkerCommand::SetOutputDir(dir.join("videos"))],
+                check: |dir, state| {
+                    assert_eq!(state.output_dir, dir.join("videos").display().to_string());
+                    assert_eq!(saved_settings(state).output_dir, dir.join("videos"));
+                },
+            },
+            Case {
+                name: "choose download output dir only opens a dialog",
+                setup: no_setup,
+                message: |_| UIMessage::ChooseDownloadOutputDir,
+                commands: no_commands,
+                check: |_, state| assert!(state.download_output_dir.is_none()),
+            },
+            Case {
+                name: "download output dir chosen is sent to the worker",
+                setup: no_setup,
+                message: |dir| UIMessage::DownloadOutputDirChosen(Some(dir.join("elsewhere"))),
+                commands: |dir| {
+                    vec![WorkerCommand::SetDownloadOutputDir(Some(
+                        dir.join("elsewhere"),
+                    ))]
+                },
+                check: |dir, state| {
+                    assert_eq!(state.download_output_dir, Some(dir.join("elsewhere")))
+                },
+            },
+            Case {
+                name: "set download output dir back to the default",
+                setup: |dir, state| state.download_output_dir = Some(dir.join("elsewhere")),
+                message: |_| UIMessage::SetDownloadOutputDir(None),
+                commands: |_| vec![WorkerCommand::SetDownloadOutputDir(None)],
+                check: |_, state| assert!(state.download_output_dir.is_none()),
+            },
+            Case {
+                name: "video downloaded enables the controls",
+                setup: |_, state| {
+                    state.disabled = true;
+                    state.is_video_downloading = true;
+                },
+                message: |dir| UIMessage::VideoDownloaded(Some(dir.join("video.mp4"))),
+                commands: no_commands,
+                check: |dir, state| {
+                    assert!(!state.disabled);
+                    assert!(!state.is_video_downloading);
+                    assert!(state.is_video_downloaded);
+                    assert_eq!(state.video_path, Some(dir.join("video.mp4")));
+                },
+            },
+            Case {
+                name: "open video without a download does nothing",
+                setup: no_setup,
+                message: |_| UIMessage::OpenVideo,
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.status_message, "Ready"),
+            },
+            Case {
+                name: "reveal video without a download does nothing",
+                setup: no_setup,
+                message: |_| UIMessage::RevealVideo,
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.status_message, "Ready"),
+            },
+            Case {
+                name: "post-download hook edited",
+                setup: no_setup,
+                message: |_| UIMessage::PostDownloadHookChanged("ffprobe {path}".to_string()),
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.post_download_hook, "ffprobe {path}"),
+            },
+            Case {
+                name: "post-download hook saved and sent to the worker",
+                setup: |_, state| state.post_download_hook = "  ffprobe {path} ".to_string(),
+                message: |_| UIMessage::SavePostDownloadHook,
+                commands: |_| {
+                    vec![WorkerCommand::SetPostDownloadHook(Some(
+                        "ffprobe {path}".to_string(),
+                    ))]
+                },
+                check: |_, state| {
+                    assert_eq!(state.status_message, "Post-download hook saved.");
+                    assert_eq!(
+                        saved_settings(state).post_download_hook.as_deref(),
+                        Some("ffprobe {path}")
+                    );
+                },
+            },
+            Case {
+                name: "hook finished shows the outcome",
+                setup: no_setup,
+                message: |_| {
+                    UIMessage::HookFinished(HookOutcome {
+                        command: "false".to_string(),
+                        exit_code: Some(1),
+                        success: false,
+                        timed_out: false,
+                        output: String::new(),
+                    })
+                },
+                commands: no_commands,
+                check: |_, state| assert!(state.hook_outcome.is_some()),
+            },
+            Case {
+                name: "notification settings saved and sent to the worker",
+                setup: no_setup,
+                message: |_| {
+                    UIMessage::NotificationSettingsChanged(NotificationSettings {
+                        enabled: false,
+                        ..NotificationSettings::default()
+                    })
+                },
+                commands: |_| {
+                    vec![WorkerCommand::SetNotificationSettings(
+                        NotificationSettings {
+                            enabled: false,
+                            ..NotificationSettings::default()
+                        },
+                    )]
+                },
+                check: |_, state| {
+                    assert!(!state.notification_settings.enabled);
+                    assert!(!saved_settings(state).notifications.enabled);
+                },
+            },
+            Case {
+                name: "cancel download cancels the download job",
+                setup: |_, state| {
+                    state.is_video_downloading = true;
+                    state.download_job = Some(7);
+                },
+                message: |_| UIMessage::CancelDownload,
+                commands: |_| vec![WorkerCommand::Cancel(7)],
+                check: |_, state| assert_eq!(state.status_message, "Cancelling download..."),
+            },
+            Case {
+                name: "download failed resets the progress",
+                setup: |_, state| {
+                    state.disabled = true;
+                    state.is_video_downloading = true;
+                    state.progress = 40.0;
+                },
+                message: |_| UIMessage::DownloadFailed("HTTP 403".to_string()),
+                commands: no_commands,
+                check: |_, state| {
+                    assert!(!state.disabled);
+                    assert!(!state.is_video_downloading);
+                    assert_eq!(state.progress, 0.0);
+                },
+            },
+            Case {
+                name: "progress updated",
+                setup: no_setup,
+                message: |_| UIMessage::ProgressUpdated(1024.0, 50.0),
+                commands: no_commands,
+                check: |_, state| {
+                    assert_eq!(state.downloaded_size, 1024.0);
+                    assert_eq!(state.progress, 50.0);
+                },
+            },
+            Case {
+                name: "audio format selected replaces the combined format",
+                setup: |_, state| state.selected_format_audio_video = Some(test_format("137")),
+                message: |_| UIMessage::SelectAudioFormat(test_format("140")),
+                commands: |_| vec![WorkerCommand::SelectAudioFormat("140".to_string())],
+                check: |_, state| {
+                    assert!(state.selected_format_audio.is_some());
+                    assert!(state.selected_format_audio_video.is_none());
+                },
+            },
+            Case {
+                name: "video format selected replaces the combined format",
+                setup: |_, state| state.selected_format_audio_video = Some(test_format("140")),
+                message: |_| UIMessage::SelectVideoFormat(test_format("137")),
+                commands: |_| vec![WorkerCommand::SelectVideoFormat("137".to_string())],
+                check: |_, state| {
+                    assert!(state.selected_format_video.is_some());
+                    assert!(state.selected_format_audio_video.is_none());
+                },
+            },
+            Case {
+                name: "combined format selected",
+                setup: no_setup,
+                message: |_| UIMessage::SelectAudioVideoFormat(test_format("137")),
+                commands: |_| vec![WorkerCommand::SelectAudioVideoFormat("137".to_string())],
+                check: |_, state| assert!(state.selected_format_audio_video.is_some()),
+            },
+            Case {
+                name: "sorting by a new column sorts ascending",
+                setup: no_setup,
+                message: |_| UIMessage::SortFormats(FormatKind::Video, FormatColumn::Resolution),
+                commands: no_commands,
+                check: |_, state| {
+                    assert_eq!(
+                        state.video_format_table.sort_column,
+                        FormatColumn::Resolution
+                    );
+                    assert!(state.video_format_table.sort_ascending);
+                },
+            },
+            Case {
+                name: "filter formats",
+                setup: no_setup,
+                message: |_| UIMessage::FilterFormats(FormatKind::Audio, "m4a".to_string()),
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.audio_format_table.filter, "m4a"),
+            },
+            Case {
+                name: "turning off clipboard watching drops the suggestion",
+                setup: |_, state| {
+                    state.watch_clipboard = true;
+                    state.clipboard_url = Some(VIDEO_URL.to_string());
+                },
+                message: |_| UIMessage::WatchClipboardToggled(false),
+                commands: no_commands,
+                check: |_, state| {
+                    assert!(!state.watch_clipboard);
+                    assert!(state.clipboard_url.is_none());
+                },
+            },
+            Case {
+                name: "poll clipboard only reads the clipboard",
+                setup: no_setup,
+                message: |_| UIMessage::PollClipboard,
+                commands: no_commands,
+                check: |_, state| assert!(state.clipboard_url.is_none()),
+            },
+            Case {
+                name: "clipboard with a video link suggests it",
+                setup: no_setup,
+                message: |_| {
+                    UIMessage::ClipboardRead(Some("watch https://youtu.be/dQw4w9WgXcQ".to_string()))
+                },
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.clipboard_url.as_deref(), Some(VIDEO_URL)),
+            },
+            Case {
+                name: "load clipboard url takes the suggestion",
+                setup: |_, state| state.clipboard_url = Some(VIDEO_URL.to_string()),
+                message: |_| UIMessage::LoadClipboardUrl,
+                commands: no_commands,
+                check: |_, state| assert!(state.clipboard_url.is_none()),
+            },
+            Case {
+                name: "dismiss clipboard url",
+                setup: |_, state| state.clipboard_url = Some(VIDEO_URL.to_string()),
+                message: |_| UIMessage::DismissClipboardUrl,
+                commands: no_commands,
+                check: |_, state| assert!(state.clipboard_url.is_none()),
+            },
+            Case {
+                name: "dropped link file queues its url",
+                setup: |dir, _| {
+                    fs::write(
+                        dir.join("video.url"),
+                        "[InternetShortcut]\nURL=https://youtu.be/dQw4w9WgXcQ\n",
+                    )
+                    .unwrap();
+                },
+                message: |dir| UIMessage::FileDropped(dir.join("video.url")),
+                commands: no_commands,
+                check: |_, state| {
+                    assert_eq!(state.queued_urls, vec![VIDEO_URL.to_string()]);
+                },
+            },
+            Case {
+                name: "load next queued url",
+                setup: |_, state| {
+                    state.queued_urls.push_back(VIDEO_URL.to_string());
+                    state
+                        .queued_urls
+                        .push_back("https://www.youtube.com/watch?v=9bZkp7q19f0".to_string());
+                },
+                message: |_| UIMessage::LoadNextQueuedUrl,
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.queued_urls.len(), 1),
+            },
+            Case {
+                name: "queue urls skips invalid links",
+                setup: no_setup,
+                message: |_| {
+                    UIMessage::QueueUrls(vec![
+                        "https://youtu.be/dQw4w9WgXcQ".to_string(),
+                        "not a url".to_string(),
+                    ])
+                },
+                commands: no_commands,
+                check: |_, state| {
+                    assert_eq!(state.queued_urls, vec![VIDEO_URL.to_string()]);
+                    assert_eq!(state.status_message, "Queued 1 video link(s).");
+                },
+            },
+            Case {
+                name: "focus window only raises the window",
+                setup: no_setup,
+                message: |_| UIMessage::FocusWindow,
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.status_message, "Ready"),
+            },
+            Case {
+                name: "import batch is run by the worker",
+                setup: no_setup,
+                message: |dir| UIMessage::ImportBatch(dir.join("batch.csv")),
+                commands: |dir| {
+                    vec![WorkerCommand::ImportBatch {
+                        path: dir.join("batch.csv"),
+                        refresh: false,
+                    }]
+                },
+                check: |_, state| {
+                    assert!(state.disabled);
+                    assert!(state.is_batch_running);
+                },
+            },
+            Case {
+                name: "batch finished enables the controls",
+                setup: |_, state| {
+                    state.disabled = true;
+                    state.is_batch_running = true;
+                },
+                message: |_| UIMessage::BatchFinished(BatchReport::default()),
+                commands: no_commands,
+                check: |_, state| {
+                    assert!(!state.disabled);
+                    assert!(!state.is_batch_running);
+                    assert!(state.batch_report.is_some());
+                },
+            },
+            Case {
+                name: "tab selected",
+                setup: no_setup,
+                message: |_| UIMessage::TabSelected(Tab::Subscriptions),
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.active_tab, Tab::Subscriptions),
+            },
+            Case {
+                name: "subscribe to the channel of the video",
+                setup: |_, state| {
+                    state.video_channel_id = CHANNEL_ID.to_string();
+                    state.video_channel = "Mock Channel".to_string();
+                },
+                message: |_| UIMessage::SubscribeToChannel,
+                commands: no_commands,
+                check: |_, state| {
+                    assert_eq!(state.subscriptions.len(), 1);
+                    assert_eq!(
+                        SubscriptionStore::load_from(&state.subscriptions_path)
+                            .subscriptions
+                            .len(),
+                        1
+                    );
+                },
+            },
+            Case {
+                name: "unsubscribe",
+                setup: subscribed,
+                message: |_| UIMessage::Unsubscribe(CHANNEL_ID.to_string()),
+                commands: no_commands,
+                check: |_, state| assert!(state.subscriptions.is_empty()),
+            },
+            Case {
+                name: "subscription policy changed",
+                setup: subscribed,
+                message: |_| {
+                    UIMessage::SubscriptionPolicyChanged(
+                        CHANNEL_ID.to_string(),
+                        "height<=720".to_string(),
+                    )
+                },
+                commands: no_commands,
+                check: |_, state| assert_eq!(state.subscriptions[0].format_policy, "height<=720"),
+            },
+            Case {
+                name: "clearing the subscription output dir uses the default",
+                setup: |dir, state| {
+                    subscribed(dir, state);
+                    update_state(
+                        state,
+                        UIMessage::SubscriptionOutputDirChanged(
+                            CHANNEL_ID.to_string(),
+                            "channel".to_string(),
+                        ),
+                    );
+                },
+                message: |_| {
+                    UIMessage::SubscriptionOutputDirChanged(CHANNEL_ID.to_string(), " ".to_string())
+                },
+                commands: no_commands,
+                check: |_, state| assert!(state.subscriptions[0].output_dir.is_none()),
+            },
+            Case {
+                name: "auto check subscriptions toggled",
+                setup: no_setup,
+                message: |_| UIMessage::AutoCheckSubscriptionsToggled(true),
+                commands: no_commands,
+                check: |_, state| assert!(state.auto_check_subscriptions),
+            },
+            Case {
+                name: "check subscriptions is run by the worker",
+                setup: no_setup,
+                message: |_| UIMessage::CheckSubscriptions,
+                commands: |dir| {
+                    vec![WorkerCommand::CheckSubscriptions(
+                        dir.join("subscriptions.json"),
+                    )]
+                },
+                check: |_, state| {
+                    assert!(state.disabled);
+                    assert!(state.is_checking_subscriptions);
+                },
+            },
+            Case {
+                name: "check subscriptions waits while busy",
+                setup: |_, state| state.disabled = true,
+                message: |_| UIMessage::CheckSubscriptions,
+                commands: no_commands,
+                check: |_, state| assert!(!state.is_checking_subscriptions),
+            },
+            Case {
+                name: "subscriptions checked reloads the subscriptions",
+                setup: |dir, state| {
+                    subscribed(dir, state);
+                    state.subscriptions.clear();
+                    state.disabled = true;
+                    state.is_checking_subscriptions = true;
+                },
+                message: |_| UIMessage::SubscriptionsChecked(BatchReport::default()),
+                commands: no_commands,
+                check: |_, state| {
+                    assert!(!state.disabled);
+                    assert!(!state.is_checking_subscriptions);
+                    assert_eq!(state.status_message, "No new uploads.");
+                    assert_eq!(state.subscriptions.len(), 1);
+                },
+            },
+            Case {
+                name: "events of the ui's own jobs update the panel",
+                setup: |_, state| {
+                    state.own_jobs.insert(3);
+                },
+                message: |_| {
+                    UIMessage::WorkerEvent(JobEvent {
+                        job_id: 3,
+                        event: WorkerEvent::Progress {
+                            downloaded_bytes: 1024.0,
+                            percent: 50.0,
+                        },
+                    })
+                },
+                commands: no_commands,
+                check: |_, state| {
+                    assert_eq!(state.progress, 50.0);
+                    assert_eq!(state.background_activity, None);
+                },
+            },
+            Case {
+                name: "events of other jobs are only reported as background activity",
+                setup: |_, state| {
+                    state.own_jobs.insert(3);
+                },
+                message: |_| {
+                    UIMessage::WorkerEvent(JobEvent {
+                        job_id: 9,
+                        event: WorkerEvent::InfoFetched(test_video()),
+                    })
+                },
+                commands: no_commands,
+                check: |_, state| {
+                    assert!(state.video_title.is_empty());
+                    assert!(state.video_formats.is_empty());
+                    assert!(state.selected_format_video.is_none());
+                    assert_eq!(
+                        state.background_activity.as_deref(),
+                        Some("Background job 9: fetched Test Video")
+                    );
+                },
+            },
+            Case {
+                name: "progress of other jobs leaves the download progress alone",
+                setup: |_, state| {
+                    state.progress = 10.0;
+                },
+                message: |_| {
+                    UIMessage::WorkerEvent(JobEvent {
+                        job_id: 9,
+                        event: WorkerEvent::Progress {
+                            downloaded_bytes: 1024.0,
+                            percent: 50.0,
+                        },
+                    })
+                },
+                commands: no_commands,
+                check: |_, state| {
+                    assert_eq!(state.progress, 10.0);
+                    assert_eq!(
+                        state.background_activity.as_deref(),
+                        Some("Background job 9: downloading, 50.0%")
+                    );
+                },
+            },
+        ]
+    }
+
+    fn case_dir(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("youtube_downloader-ui-{}", std::process::id()))
+            .join(name.replace(' ', "-"))
+    }
+
+    /// Applies a message during setup, dropping its worker commands.
+    fn update_state(downloader_ui_state: &mut DownloaderUIState, message: UIMessage) {
+        apply(downloader_ui_state, message);
+    }
+
+    #[test]
+    fn state_transitions() {
+        for case in cases() {
+            let dir = case_dir(case.name);
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let mut downloader_ui_state = DownloaderUIState {
+                settings_path: dir.join("settings.json"),
+                subscriptions_path: dir.join("subscriptions.json"),
+                ..DownloaderUIState::default()
+            };
+            (case.setup)(&dir, &mut downloader_ui_state);
+
+            let update = apply(&mut downloader_ui_state, (case.message)(&dir));
+
+            assert_eq!(
+                update.worker_commands,
+                (case.commands)(&dir),
+                "commands of case '{}'",
+                case.name
+            );
+            (case.check)(&dir, &downloader_ui_state);
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn info_fetched_without_thumbnail_or_separate_streams() {
+        let mut video = test_video();
+        video.thumbnail = String::new();
+        video.thumbnails.clear();
+        video.extractor = "generic".to_string();
+        video.formats.retain(|format| format.format_id == "137");
+        video.formats[0].audio_codec = Some("mp4a.40.2".to_string());
+        let mut downloader_ui_state = DownloaderUIState::default();
+
+        let update = apply(&mut downloader_ui_state, UIMessage::InfoFetched(video));
+
+        assert_eq!(downloader_ui_state.video_extractor, "generic");
+        assert!(downloader_ui_state.thumbnails.is_empty());
+        // No thumbnail to wait for.
+        assert!(downloader_ui_state.show_download_button);
+        assert_eq!(
+            downloader_ui_state
+                .selected_format_audio_video
+                .as_ref()
+                .map(|format| format.format_id.as_str()),
+            Some("137")
+        );
+        assert_eq!(
+            update.worker_commands,
+            vec![
+                WorkerCommand::SetDownloadOutputDir(None),
+                WorkerCommand::SelectAudioVideoFormat("137".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn update_without_worker_does_not_panic() {
+        let mut downloader_ui_state = DownloaderUIState::default();
+        let _ = update(&mut downloader_ui_state, UIMessage::InstallLibraries);
+        assert!(downloader_ui_state.disabled);
+    }
+}
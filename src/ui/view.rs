@@ -1,10 +1,19 @@
-use super::{message::Message as UIMessage, state::DownloaderUIState};
+use super::{
+    format_table::{self, FormatKind},
+    message::Message as UIMessage,
+    state::{DownloaderUIState, Tab},
+    subscriptions_tab,
+};
+use crate::file_size::{format_bytes, FileSize};
+use crate::live::{LiveStatus, RecordingMode, RecordingOptions};
+use crate::notifications::NotificationSettings;
+use crate::thumbnail::{ThumbnailFormat, ThumbnailRequest};
 use iced::{
     widget::{
-        button, column, combo_box, container, progress_bar, row, text, text_input, Column, Image,
-        Row, Scrollable, Text,
+        button, checkbox, column, container, pick_list, progress_bar, row, text, text_input,
+        Column, Image, Row, Scrollable, Text,
     },
-    Alignment, Element, Length,
+    Alignment, Color, Element, Length,
 };
 
 pub fn view(downloader_ui_state: &DownloaderUIState) -> Element<UIMessage> {
@@ -19,9 +28,12 @@ pub fn view(downloader_ui_state: &DownloaderUIState) -> Element<UIMessage> {
                     downloader_ui_state.executables_dir
                 )))
                 .push(text(format!(
-                    "output dir: ./{}",
+                    "output dir: {}",
                     downloader_ui_state.output_dir
                 )))
+                .push(button("Change Folder").on_press_maybe(
+                    (!downloader_ui_state.disabled).then_some(UIMessage::ChooseOutputDir),
+                ))
                 .push(match &downloader_ui_state.disabled {
                     true => button("Install Libraries"),
                     false => button("Install Libraries").on_press(UIMessage::InstallLibraries),
@@ -43,9 +55,68 @@ pub fn view(downloader_ui_state: &DownloaderUIState) -> Element<UIMessage> {
                         .size(16)
                         .on_input(|entered_text| UIMessage::UrlChanged(entered_text)),
                 })
-                .push(match &downloader_ui_state.disabled {
-                    true => button("Get Info"),
-                    false => button("Get Info").on_press(UIMessage::FetchInfo),
+                .push(
+                    match !downloader_ui_state.disabled
+                        && downloader_ui_state.parsed_video_url.is_ok()
+                    {
+                        true => button("Get Info").on_press(UIMessage::FetchInfo),
+                        false => button("Get Info"),
+                    },
+                )
+                .push(
+                    match !downloader_ui_state.disabled
+                        && downloader_ui_state.parsed_video_url.is_ok()
+                    {
+                        true => button("Refresh Info").on_press(UIMessage::RefreshInfo),
+                        false => button("Refresh Info"),
+                    },
+                ),
+        )
+        .push(match &downloader_ui_state.parsed_video_url {
+            Ok(video_url) => text(format!("Recognised {}", video_url))
+                .size(12)
+                .color(Color::from_rgb(0.5, 0.8, 0.5)),
+            Err(e) => text(e.to_string())
+                .size(12)
+                .color(Color::from_rgb(0.9, 0.4, 0.4)),
+        })
+        .push(
+            Row::new()
+                .spacing(10.0)
+                .push(
+                    checkbox(
+                        "Watch clipboard for video links",
+                        downloader_ui_state.watch_clipboard,
+                    )
+                    .on_toggle(UIMessage::WatchClipboardToggled),
+                )
+                .push_maybe(match downloader_ui_state.queued_urls.is_empty() {
+                    true => None,
+                    false => Some(
+                        button(text(format!(
+                            "Load Next Queued URL ({} left)",
+                            downloader_ui_state.queued_urls.len()
+                        )))
+                        .on_press_maybe(
+                            (!downloader_ui_state.disabled).then_some(UIMessage::LoadNextQueuedUrl),
+                        ),
+                    ),
+                })
+                .align_y(Alignment::Center),
+        )
+        .push_maybe(
+            downloader_ui_state
+                .clipboard_url
+                .as_ref()
+                .map(|clipboard_url| {
+                    Row::new()
+                        .spacing(10.0)
+                        .push(text(format!("Video link in clipboard: {}", clipboard_url)).size(14))
+                        .push(button("Load").on_press_maybe(
+                            (!downloader_ui_state.disabled).then_some(UIMessage::LoadClipboardUrl),
+                        ))
+                        .push(button("Dismiss").on_press(UIMessage::DismissClipboardUrl))
+                        .align_y(Alignment::Center)
                 }),
         )
         .push(
@@ -67,13 +138,34 @@ pub fn view(downloader_ui_state: &DownloaderUIState) -> Element<UIMessage> {
                             Row::new()
                                 .spacing(10.0)
                                 .push(text(&downloader_ui_state.video_title).size(25))
-                                .push(text(&downloader_ui_state.video_id).size(10)),
+                                .push(text(&downloader_ui_state.video_id).size(10))
+                                .push_maybe(
+                                    (!downloader_ui_state.video_extractor.is_empty()).then(|| {
+                                        text(format!("via {}", downloader_ui_state.video_extractor))
+                                            .size(10)
+                                    }),
+                                ),
                         )
                         .push(
                             Row::new()
                                 .spacing(10.0)
                                 .push(text(&downloader_ui_state.video_channel).size(14))
-                                .push(text(&downloader_ui_state.video_channel_id).size(14)),
+                                .push(text(&downloader_ui_state.video_channel_id).size(14))
+                                .push_maybe(
+                                    // Subscriptions follow YouTube channel feeds.
+                                    (!downloader_ui_state.video_channel_id.is_empty()
+                                        && downloader_ui_state.video_extractor == "youtube")
+                                        .then(|| {
+                                            button(text("Subscribe to Channel").size(12))
+                                                .style(button::secondary)
+                                                .on_press(UIMessage::SubscribeToChannel)
+                                        }),
+                                )
+                                .align_y(Alignment::Center),
+                        )
+                        .push_maybe(
+                            (!downloader_ui_state.thumbnails.is_empty())
+                                .then(|| thumbnail_picker_view(downloader_ui_state)),
                         ),
                 ),
         )
@@ -81,23 +173,72 @@ pub fn view(downloader_ui_state: &DownloaderUIState) -> Element<UIMessage> {
             Scrollable::new(Text::new(&downloader_ui_state.video_description).size(14))
                 .height(150.0),
         )
-        .push(combo_box(
-            &downloader_ui_state.format_selection_list_video,
-            "Select Video format...",
+        .push(format_table::view(
+            FormatKind::Video,
+            &downloader_ui_state.video_format_table,
             downloader_ui_state.selected_format_video.as_ref(),
-            |format| UIMessage::SelectVideoFormat(format),
+            downloader_ui_state.disabled,
         ))
-        .push(
+        .push(format_table::view(
+            FormatKind::Audio,
+            &downloader_ui_state.audio_format_table,
+            downloader_ui_state.selected_format_audio.as_ref(),
+            downloader_ui_state.disabled,
+        ))
+        .push_maybe(
+            match downloader_ui_state
+                .audio_video_format_table
+                .formats
+                .is_empty()
+            {
+                true => None,
+                false => Some(format_table::view(
+                    FormatKind::AudioVideo,
+                    &downloader_ui_state.audio_video_format_table,
+                    downloader_ui_state.selected_format_audio_video.as_ref(),
+                    downloader_ui_state.disabled,
+                )),
+            },
+        )
+        .push_maybe(
+            (downloader_ui_state.show_download_button
+                && downloader_ui_state.live_status.needs_recording())
+            .then(|| live_recording_view(downloader_ui_state)),
+        )
+        .push_maybe(downloader_ui_state.show_download_button.then(|| {
             Row::new()
                 .spacing(10.0)
-                .push(combo_box(
-                    &downloader_ui_state.format_selection_list_audio,
-                    "Select Audio format...",
-                    downloader_ui_state.selected_format_audio.as_ref(),
-                    |format| UIMessage::SelectAudioFormat(format),
+                .push(
+                    text(format!(
+                        "Save to: {}",
+                        downloader_ui_state
+                            .download_output_dir
+                            .as_ref()
+                            .map(|output_dir| output_dir.display().to_string())
+                            .unwrap_or(downloader_ui_state.output_dir.clone())
+                    ))
+                    .size(14),
+                )
+                .push(button("Save Elsewhere...").on_press_maybe(
+                    (!downloader_ui_state.disabled).then_some(UIMessage::ChooseDownloadOutputDir),
                 ))
+                .push_maybe(downloader_ui_state.download_output_dir.as_ref().map(|_| {
+                    button("Use Default Folder")
+                        .style(button::secondary)
+                        .on_press_maybe(
+                            (!downloader_ui_state.disabled)
+                                .then_some(UIMessage::SetDownloadOutputDir(None)),
+                        )
+                }))
+                .align_y(Alignment::Center)
+        }))
+        .push(
+            Row::new()
+                .spacing(10.0)
+                .push(text(estimated_size_text(downloader_ui_state)).size(14))
                 .push(
                     if downloader_ui_state.show_download_button
+                        && !downloader_ui_state.live_status.needs_recording()
                         && !downloader_ui_state.is_video_downloading
                         && !downloader_ui_state.is_video_downloaded
                     {
@@ -112,22 +253,526 @@ pub fn view(downloader_ui_state: &DownloaderUIState) -> Element<UIMessage> {
                     } else {
                         button("Download Video").height(0.0)
                     },
-                ),
+                )
+                .push_maybe(downloader_ui_state.is_video_downloading.then(|| {
+                    button("Cancel")
+                        .style(button::secondary)
+                        .on_press(UIMessage::CancelDownload)
+                }))
+                .align_y(Alignment::Center),
+        )
+        .push_maybe(
+            downloader_ui_state
+                .disk_space_warning
+                .as_ref()
+                .map(|shortage| {
+                    container(
+                        Column::new()
+                            .spacing(10.0)
+                            .push(
+                                text(shortage.to_string())
+                                    .size(14)
+                                    .color(Color::from_rgb(0.9, 0.4, 0.4)),
+                            )
+                            .push(
+                                Row::new()
+                                    .spacing(10.0)
+                                    .push(
+                                        button("Choose Different Folder")
+                                            .on_press(UIMessage::ChooseDownloadOutputDir),
+                                    )
+                                    .push(
+                                        button("Download Anyway")
+                                            .style(button::secondary)
+                                            .on_press(UIMessage::DownloadVideoAnyway),
+                                    )
+                                    .push(
+                                        button("Cancel")
+                                            .style(button::secondary)
+                                            .on_press(UIMessage::DismissDiskSpaceWarning),
+                                    ),
+                            ),
+                    )
+                    .padding(10.0)
+                    .style(container::bordered_box)
+                }),
+        )
+        .push_maybe(
+            downloader_ui_state
+                .video_path
+                .as_ref()
+                .filter(|_| downloader_ui_state.is_video_downloaded)
+                .map(|_| {
+                    Row::new()
+                        .spacing(10.0)
+                        .push(button("Open File").on_press(UIMessage::OpenVideo))
+                        .push(button("Show in Folder").on_press(UIMessage::RevealVideo))
+                }),
         )
+        .push_maybe(downloader_ui_state.hook_outcome.as_ref().map(|outcome| {
+            Column::new()
+                .spacing(4.0)
+                .push(
+                    text(outcome.to_string())
+                        .size(14)
+                        .color(match outcome.success {
+                            true => Color::from_rgb(0.5, 0.8, 0.5),
+                            false => Color::from_rgb(0.9, 0.4, 0.4),
+                        }),
+                )
+                .push_maybe(
+                    (!outcome.output.is_empty())
+                        .then(|| Scrollable::new(text(&outcome.output).size(12)).height(80.0)),
+                )
+        }))
         .push(
             Row::new()
                 .spacing(10.0)
-                .push(progress_bar(0.0..=100.0, downloader_ui_state.progress)),
+                .push(
+                    text_input(
+                        "Post-download hook, e.g. ffprobe {path}",
+                        &downloader_ui_state.post_download_hook,
+                    )
+                    .size(14)
+                    .on_input(UIMessage::PostDownloadHookChanged)
+                    .on_submit(UIMessage::SavePostDownloadHook),
+                )
+                .push(button("Save Hook").on_press(UIMessage::SavePostDownloadHook))
+                .align_y(Alignment::Center),
+        )
+        .push(notification_settings_view(
+            &downloader_ui_state.notification_settings,
+        ))
+        .push(
+            Row::new()
+                .spacing(10.0)
+                .push(progress_bar(0.0..=100.0, downloader_ui_state.progress))
+                .push_maybe(downloader_ui_state.is_batch_running.then(|| {
+                    button("Cancel Batch")
+                        .style(button::secondary)
+                        .on_press(UIMessage::CancelDownload)
+                }))
+                .align_y(Alignment::Center),
+        )
+        .push_maybe(
+            downloader_ui_state
+                .batch_report
+                .as_ref()
+                .filter(|report| !report.failed.is_empty())
+                .map(|report| {
+                    Scrollable::new(
+                        report
+                            .failed
+                            .iter()
+                            .fold(Column::new().spacing(4.0), |failures, failure| {
+                                failures.push(text(failure.to_string()).size(12))
+                            }),
+                    )
+                    .height(100.0)
+                }),
         );
-    let main_container = container(main_column)
-        .width(Length::Fill)
-        .height(Length::Fill);
+    let main_container = match downloader_ui_state.active_tab {
+        Tab::Download => container(main_column),
+        Tab::Subscriptions => container(subscriptions_tab::view(downloader_ui_state)),
+    }
+    .width(Length::Fill)
+    .height(Length::Fill);
+
+    let tab_button = |label: &'static str, tab: Tab| {
+        button(text(label).size(14))
+            .style(match downloader_ui_state.active_tab == tab {
+                true => button::primary,
+                false => button::text,
+            })
+            .on_press(UIMessage::TabSelected(tab))
+    };
+    let tab_bar = Row::new()
+        .spacing(10.0)
+        .padding([8, 20])
+        .push(tab_button("Download", Tab::Download))
+        .push(tab_button("Subscriptions", Tab::Subscriptions));
 
     let status_bar: iced::widget::Row<'_, UIMessage, _, _> =
         row![text(downloader_ui_state.status_message.clone()).size(12)]
+            .push_maybe(
+                downloader_ui_state
+                    .background_activity
+                    .clone()
+                    .map(|activity| text(activity).size(12)),
+            )
             .spacing(10)
             .align_y(Alignment::Center);
     let status_bar_container = container(status_bar).padding(8).width(Length::Fill);
 
-    column![main_container, status_bar_container].into()
+    column![tab_bar, main_container, status_bar_container].into()
+}
+
+fn estimated_size_text(downloader_ui_state: &DownloaderUIState) -> String {
+    let selected_formats: Vec<_> = match &downloader_ui_state.selected_format_audio_video {
+        Some(format) => vec![format],
+        None => [
+            downloader_ui_state.selected_format_video.as_ref(),
+            downloader_ui_state.selected_format_audio.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+    };
+    let estimated_sizes: Vec<FileSize> = selected_formats
+        .iter()
+        .filter_map(|format| format.estimated_size())
+        .collect();
+
+    if estimated_sizes.is_empty() {
+        return String::new();
+    }
+    let is_complete = estimated_sizes.len() == selected_formats.len();
+    let total_size: FileSize = estimated_sizes.into_iter().sum();
+    match (is_complete, total_size.approximate) {
+        (false, _) => format!(
+            "This download will be at least {}",
+            format_bytes(total_size.bytes)
+        ),
+        (true, true) => format!(
+            "This download will be about {}",
+            format_bytes(total_size.bytes)
+        ),
+        (true, false) => format!("This download will be {}", format_bytes(total_size.bytes)),
+    }
+}
+
+fn notification_settings_view(
+    notification_settings: &NotificationSettings,
+) -> Row<'static, UIMessage> {
+    let toggle =
+        |label: &'static str, enabled: bool, apply: fn(&mut NotificationSettings, bool)| {
+            let notification_settings = notification_settings.clone();
+            checkbox(label, enabled).on_toggle(move |enabled| {
+                let mut notification_settings = notification_settings.clone();
+                apply(&mut notification_settings, enabled);
+                UIMessage::NotificationSettingsChanged(notification_settings)
+            })
+        };
+
+    Row::new()
+        .spacing(20.0)
+        .push(toggle(
+            "Desktop notifications",
+            notification_settings.enabled,
+            |settings, enabled| settings.enabled = enabled,
+        ))
+        .push_maybe(notification_settings.enabled.then(|| {
+            Row::new()
+                .spacing(20.0)
+                .push(toggle(
+                    "Download finished",
+                    notification_settings.download_finished,
+                    |settings, enabled| settings.download_finished = enabled,
+                ))
+                .push(toggle(
+                    "Download failed",
+                    notification_settings.download_failed,
+                    |settings, enabled| settings.download_failed = enabled,
+                ))
+                .push(toggle(
+                    "Batch finished",
+                    notification_settings.queue_drained,
+                    |settings, enabled| settings.queue_drained = enabled,
+                ))
+        }))
+        .align_y(Alignment::Center)
+}
+
+fn thumbnail_picker_view(downloader_ui_state: &DownloaderUIState) -> Row<UIMessage> {
+    let save_request = downloader_ui_state
+        .selected_thumbnail
+        .as_ref()
+        .map(|thumbnail| ThumbnailRequest {
+            url: thumbnail.url.clone(),
+            format: downloader_ui_state.thumbnail_format,
+            crop_square: downloader_ui_state.crop_thumbnail,
+        });
+
+    Row::new()
+        .spacing(10.0)
+        .push(
+            pick_list(
+                downloader_ui_state.thumbnails.as_slice(),
+                downloader_ui_state.selected_thumbnail.clone(),
+                UIMessage::ThumbnailSelected,
+            )
+            .text_size(12),
+        )
+        .push(
+            pick_list(
+                ThumbnailFormat::ALL,
+                Some(downloader_ui_state.thumbnail_format),
+                UIMessage::ThumbnailFormatSelected,
+            )
+            .text_size(12),
+        )
+        .push(
+            checkbox(
+                "Crop to square cover art",
+                downloader_ui_state.crop_thumbnail,
+            )
+            .text_size(12)
+            .on_toggle(UIMessage::CropThumbnailToggled),
+        )
+        .push(
+            button(text("Save Thumbnail").size(12)).on_press_maybe(
+                save_request
+                    .filter(|_| !downloader_ui_state.disabled)
+                    .map(UIMessage::SaveThumbnail),
+            ),
+        )
+        .align_y(Alignment::Center)
+}
+
+fn live_recording_view(downloader_ui_state: &DownloaderUIState) -> Row<UIMessage> {
+    let max_duration = downloader_ui_state
+        .recording_max_minutes
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|minutes| std::time::Duration::from_secs(minutes * 60));
+    let max_size = downloader_ui_state
+        .recording_max_size_mb
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|megabytes| megabytes * 1024 * 1024);
+    let options = RecordingOptions {
+        mode: downloader_ui_state.recording_mode,
+        max_duration,
+        max_size,
+        wait_for_start: downloader_ui_state.wait_for_start,
+    };
+
+    Row::new()
+        .spacing(10.0)
+        .push(
+            text(downloader_ui_state.live_status.to_string())
+                .size(14)
+                .color(Color::from_rgb(0.9, 0.4, 0.4)),
+        )
+        .push(
+            pick_list(
+                RecordingMode::ALL,
+                Some(downloader_ui_state.recording_mode),
+                UIMessage::RecordingModeSelected,
+            )
+            .text_size(14),
+        )
+        .push(
+            text_input("Max minutes", &downloader_ui_state.recording_max_minutes)
+                .size(14)
+                .width(110.0)
+                .on_input(UIMessage::RecordingMaxMinutesChanged),
+        )
+        .push(
+            text_input("Max size (MB)", &downloader_ui_state.recording_max_size_mb)
+                .size(14)
+                .width(110.0)
+                .on_input(UIMessage::RecordingMaxSizeChanged),
+        )
+        .push_maybe(
+            (downloader_ui_state.live_status == LiveStatus::IsUpcoming).then(|| {
+                checkbox(
+                    "Wait for the stream to start",
+                    downloader_ui_state.wait_for_start,
+                )
+                .on_toggle(UIMessage::WaitForStartToggled)
+            }),
+        )
+        .push(
+            button("Start Recording").on_press_maybe(
+                (!downloader_ui_state.disabled && !downloader_ui_state.is_video_downloading)
+                    .then_some(UIMessage::RecordLive(options)),
+            ),
+        )
+        .align_y(Alignment::Center)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use iced::advanced::layout::{Layout, Limits};
+    use iced::advanced::widget::{Id, Operation, Tree};
+    use iced::{Font, Pixels, Rectangle, Size};
+
+    use super::*;
+    use crate::backend::Format;
+    use crate::subscriptions::Subscription;
+    use crate::ui::format_table::FormatTableState;
+    use crate::ui::state::FormatListItem;
+    use crate::video_url::parse_video_url;
+
+    const CHANNEL_ID: &str = "UCmockchannel000000000000";
+
+    /// Collects every text in the widget tree, in layout order.
+    #[derive(Default)]
+    struct TextCollector {
+        lines: Vec<String>,
+    }
+
+    impl Operation for TextCollector {
+        fn container(
+            &mut self,
+            _id: Option<&Id>,
+            _bounds: Rectangle,
+            operate_on_children: &mut dyn FnMut(&mut dyn Operation),
+        ) {
+            operate_on_children(self);
+        }
+
+        fn text(&mut self, _id: Option<&Id>, _bounds: Rectangle, text: &str) {
+            self.lines.push(format!("{:?}", text));
+        }
+    }
+
+    /// Lays the view out with the software renderer, without opening a window.
+    fn render_texts(downloader_ui_state: &DownloaderUIState) -> String {
+        let renderer =
+            iced::Renderer::Secondary(iced_tiny_skia::Renderer::new(Font::DEFAULT, Pixels(16.0)));
+        let element = view(downloader_ui_state);
+        let mut tree = Tree::new(&element);
+        let node = element.as_widget().layout(
+            &mut tree,
+            &renderer,
+            &Limits::new(Size::ZERO, Size::new(1280.0, 800.0)),
+        );
+        let mut collector = TextCollector::default();
+        element
+            .as_widget()
+            .operate(&mut tree, Layout::new(&node), &renderer, &mut collector);
+        collector.lines.join("\n") + "\n"
+    }
+
+    /// Compares against `src/ui/snapshots/<name>.snap`; set `UPDATE_SNAPSHOTS=1` to
+    /// rewrite the snapshot after an intended change.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/ui/snapshots")
+            .join(format!("{}.snap", name));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_default();
+        assert_eq!(
+            actual,
+            expected,
+            "view snapshot {} changed, rerun with UPDATE_SNAPSHOTS=1 if intended",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn download_tab() {
+        assert_snapshot("download_tab", &render_texts(&DownloaderUIState::default()));
+    }
+
+    /// The download tab once a video's info and formats were fetched.
+    fn fetched_state() -> DownloaderUIState {
+        let video_format = FormatListItem::new(&Format {
+            format_id: "137".to_string(),
+            container: Some("mp4".to_string()),
+            video_codec: Some("avc1.640028".to_string()),
+            audio_codec: Some("none".to_string()),
+            width: Some(1920),
+            height: Some(1080),
+            filesize: Some(131072),
+            total_bitrate: Some(2500.0),
+            ..Format::default()
+        });
+        let audio_format = FormatListItem::new(&Format {
+            format_id: "140".to_string(),
+            container: Some("m4a".to_string()),
+            video_codec: Some("none".to_string()),
+            audio_codec: Some("mp4a.40.2".to_string()),
+            filesize: Some(65536),
+            total_bitrate: Some(128.0),
+            sample_rate: Some(44100),
+            audio_channels: Some(2),
+            ..Format::default()
+        });
+        let video_url = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        DownloaderUIState {
+            status_message: "Video Info Fetched.".to_string(),
+            video_url: video_url.to_string(),
+            parsed_video_url: parse_video_url(video_url),
+            video_id: "dQw4w9WgXcQ".to_string(),
+            video_title: "Test Video".to_string(),
+            video_description: "A video to test the view with.".to_string(),
+            video_channel: "Mock Channel".to_string(),
+            video_channel_id: CHANNEL_ID.to_string(),
+            video_extractor: "youtube".to_string(),
+            show_download_button: true,
+            selected_format_video: Some(video_format.clone()),
+            selected_format_audio: Some(audio_format.clone()),
+            video_format_table: FormatTableState::new(vec![video_format]),
+            audio_format_table: FormatTableState::new(vec![audio_format]),
+            ..DownloaderUIState::default()
+        }
+    }
+
+    #[test]
+    fn download_tab_with_fetched_formats() {
+        assert_snapshot("download_tab_fetched", &render_texts(&fetched_state()));
+    }
+
+    #[test]
+    fn download_tab_while_downloading() {
+        let downloader_ui_state = DownloaderUIState {
+            status_message: "Downloading Video...".to_string(),
+            disabled: true,
+            is_video_downloading: true,
+            progress: 42.0,
+            ..fetched_state()
+        };
+        assert_snapshot(
+            "download_tab_downloading",
+            &render_texts(&downloader_ui_state),
+        );
+    }
+
+    #[test]
+    fn subscriptions_tab_with_subscriptions() {
+        let checked_subscription = Subscription {
+            seen_video_ids: vec!["dQw4w9WgXcQ".to_string(), "9bZkp7q19f0".to_string()],
+            last_checked: Some(1_700_000_000),
+            ..Subscription::new(
+                CHANNEL_ID,
+                "Mock Channel",
+                "https://www.youtube.com/channel/UCmockchannel000000000000",
+            )
+        };
+        let downloader_ui_state = DownloaderUIState {
+            active_tab: Tab::Subscriptions,
+            subscriptions: vec![
+                checked_subscription,
+                Subscription::new(
+                    "UCotherchannel00000000000",
+                    "Other Channel",
+                    "https://www.youtube.com/channel/UCotherchannel00000000000",
+                ),
+            ],
+            ..DownloaderUIState::default()
+        };
+        assert_snapshot("subscriptions_tab", &render_texts(&downloader_ui_state));
+    }
+
+    #[test]
+    fn subscriptions_tab_without_subscriptions() {
+        let downloader_ui_state = DownloaderUIState {
+            active_tab: Tab::Subscriptions,
+            ..DownloaderUIState::default()
+        };
+        assert_snapshot(
+            "subscriptions_tab_empty",
+            &render_texts(&downloader_ui_state),
+        );
+    }
 }
@@ -0,0 +1,479 @@
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use url::Url;
+
+const VIDEO_URL_PATTERN: &str = r"(?:https?://)?(?:(?:www|m|music)\.)?(?:youtube(?:-nocookie)?\.com/(?:watch\?[^\s]*v=|shorts/|embed/|live/)|youtu\.be/)[A-Za-z0-9_-]{11}[^\s<>\x22]*";
+const MAX_DROPPED_FILE_SIZE: u64 = 1024 * 1024;
+// Query parameters that only track where a link was shared, besides every `utm_*` one.
+const TRACKING_PARAMS: [&str; 8] = [
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VideoUrlKind {
+    Watch,
+    Shorts,
+    ShortLink,
+    Embed,
+    Live,
+    Music,
+    /// A page on any other site, left for yt-dlp's extractors to resolve.
+    Other,
+}
+
+impl Display for VideoUrlKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VideoUrlKind::Watch => write!(f, "video"),
+            VideoUrlKind::Shorts => write!(f, "short"),
+            VideoUrlKind::ShortLink => write!(f, "short link"),
+            VideoUrlKind::Embed => write!(f, "embedded video"),
+            VideoUrlKind::Live => write!(f, "live stream"),
+            VideoUrlKind::Music => write!(f, "music track"),
+            VideoUrlKind::Other => write!(f, "video page"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoUrl {
+    pub kind: VideoUrlKind,
+    /// Empty for other sites, whose ids are only known once yt-dlp extracted the page.
+    pub video_id: String,
+    pub timestamp: Option<u32>,
+    /// The URL as entered, kept for other sites.
+    pub url: String,
+}
+
+impl VideoUrl {
+    /// Canonical watch URL without tracking or playlist parameters. Links to other
+    /// sites are kept as they are, minus tracking parameters.
+    pub fn normalized(&self) -> String {
+        match self.kind {
+            VideoUrlKind::Music => format!("https://music.youtube.com/watch?v={}", self.video_id),
+            VideoUrlKind::Other => self.url.clone(),
+            _ => format!("https://www.youtube.com/watch?v={}", self.video_id),
+        }
+    }
+
+    pub fn is_youtube(&self) -> bool {
+        self.kind != VideoUrlKind::Other
+    }
+}
+
+impl Display for VideoUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.kind == VideoUrlKind::Other {
+            return write!(f, "{} {}", self.kind, self.url);
+        }
+        write!(f, "{} {}", self.kind, self.video_id)?;
+        if let Some(timestamp) = self.timestamp {
+            write!(
+                f,
+                " from {}:{:02}:{:02}",
+                timestamp / 3600,
+                timestamp % 3600 / 60,
+                timestamp % 60
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UrlError {
+    Empty,
+    Malformed(String),
+    UnsupportedHost(String),
+    UnsupportedPath(String),
+    MissingVideoId,
+    InvalidVideoId(String),
+}
+
+impl Display for UrlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UrlError::Empty => write!(f, "Enter a video URL"),
+            UrlError::Malformed(reason) => write!(f, "Not a valid URL: {}", reason),
+            UrlError::UnsupportedHost(host) => write!(f, "Unsupported site: {}", host),
+            UrlError::UnsupportedPath(path) => write!(f, "Not a video link: {}", path),
+            UrlError::MissingVideoId => write!(f, "The URL has no video id"),
+            UrlError::InvalidVideoId(video_id) => write!(f, "Invalid video id: {}", video_id),
+        }
+    }
+}
+
+impl std::error::Error for UrlError {}
+
+pub fn parse_video_url(input: &str) -> Result<VideoUrl, UrlError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(UrlError::Empty);
+    }
+
+    let with_scheme = match input.contains("://") {
+        true => input.to_string(),
+        false => format!("https://{}", input),
+    };
+    let mut parsed_url =
+        Url::parse(&with_scheme).map_err(|e| UrlError::Malformed(e.to_string()))?;
+    if parsed_url.scheme() != "http" && parsed_url.scheme() != "https" {
+        return Err(UrlError::Malformed(format!(
+            "unsupported scheme {}",
+            parsed_url.scheme()
+        )));
+    }
+    strip_tracking_params(&mut parsed_url);
+
+    let host = parsed_url
+        .host_str()
+        .ok_or_else(|| UrlError::Malformed("missing host".to_string()))?
+        .to_ascii_lowercase();
+    let host = host
+        .strip_prefix("www.")
+        .or_else(|| host.strip_prefix("m."))
+        .unwrap_or(host.as_str());
+
+    let path_segments: Vec<&str> = parsed_url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default();
+    let query_value = |name: &str| {
+        parsed_url
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    let (kind, video_id) = match host {
+        "youtu.be" => (
+            VideoUrlKind::ShortLink,
+            path_segments.first().map(|segment| segment.to_string()),
+        ),
+        "music.youtube.com" => match path_segments.as_slice() {
+            ["watch"] => (VideoUrlKind::Music, query_value("v")),
+            _ => return Err(UrlError::UnsupportedPath(parsed_url.path().to_string())),
+        },
+        "youtube.com" | "youtube-nocookie.com" => match path_segments.as_slice() {
+            ["watch"] => (VideoUrlKind::Watch, query_value("v")),
+            ["shorts", video_id, ..] => (VideoUrlKind::Shorts, Some(video_id.to_string())),
+            ["embed", video_id, ..] => (VideoUrlKind::Embed, Some(video_id.to_string())),
+            ["live", video_id, ..] => (VideoUrlKind::Live, Some(video_id.to_string())),
+            _ => return Err(UrlError::UnsupportedPath(parsed_url.path().to_string())),
+        },
+        // Hosts without a dot are typos rather than sites yt-dlp could extract from.
+        _ if !host.contains('.') => return Err(UrlError::UnsupportedHost(host.to_string())),
+        _ => {
+            return Ok(VideoUrl {
+                kind: VideoUrlKind::Other,
+                video_id: String::new(),
+                timestamp: None,
+                url: parsed_url.to_string(),
+            })
+        }
+    };
+
+    let video_id = video_id.ok_or(UrlError::MissingVideoId)?;
+    if video_id.len() != 11
+        || !video_id.chars().all(|character| {
+            character.is_ascii_alphanumeric() || character == '_' || character == '-'
+        })
+    {
+        return Err(UrlError::InvalidVideoId(video_id));
+    }
+
+    let fragment_timestamp = parsed_url
+        .fragment()
+        .and_then(|fragment| fragment.strip_prefix("t="))
+        .map(|value| value.to_string());
+    let timestamp = query_value("t")
+        .or_else(|| query_value("start"))
+        .or(fragment_timestamp)
+        .and_then(|value| parse_timestamp(&value));
+
+    Ok(VideoUrl {
+        kind,
+        video_id,
+        timestamp,
+        url: parsed_url.to_string(),
+    })
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+/// Drops tracking parameters. Queries without any are left exactly as entered.
+fn strip_tracking_params(url: &mut Url) {
+    if !url.query_pairs().any(|(key, _)| is_tracking_param(&key)) {
+        return;
+    }
+    let kept_pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_param(key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    match kept_pairs.is_empty() {
+        true => url.set_query(None),
+        false => {
+            url.query_pairs_mut().clear().extend_pairs(kept_pairs);
+        }
+    }
+}
+
+/// Accepts `90`, `90s`, `1m30s` and `1h2m3s`.
+fn parse_timestamp(value: &str) -> Option<u32> {
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(seconds) = value.parse::<u32>() {
+        return Some(seconds);
+    }
+
+    let mut total_seconds: u32 = 0;
+    let mut number = String::new();
+    for character in value.chars() {
+        if character.is_ascii_digit() {
+            number.push(character);
+            continue;
+        }
+        let amount: u32 = number.parse().ok()?;
+        number.clear();
+        let seconds = match character {
+            'h' => amount.checked_mul(3600)?,
+            'm' => amount.checked_mul(60)?,
+            's' => amount,
+            _ => return None,
+        };
+        total_seconds = total_seconds.checked_add(seconds)?;
+    }
+
+    match number.is_empty() {
+        true => Some(total_seconds),
+        false => None,
+    }
+}
+
+/// Only YouTube links are picked out of free text; any other URL in the clipboard or a
+/// dropped file is too likely not to be a video.
+pub fn find_video_urls(text: &str) -> Vec<String> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(VIDEO_URL_PATTERN).unwrap());
+    re.find_iter(text)
+        .filter_map(|found| parse_video_url(found.as_str()).ok())
+        .map(|video_url| video_url.normalized())
+        .collect()
+}
+
+/// Reads video links out of a dropped file: a `.txt` list of URLs, or a browser link
+/// shortcut (`.url`, `.webloc`, `.desktop`) which all keep the URL as plain text.
+pub fn urls_from_dropped_file(path: &Path) -> anyhow::Result<Vec<String>> {
+    let metadata = fs::metadata(path)?;
+    if metadata.len() > MAX_DROPPED_FILE_SIZE {
+        anyhow::bail!("{} is too large to be a link file", path.display());
+    }
+
+    let content = fs::read_to_string(path)?;
+    Ok(find_video_urls(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_video_urls() {
+        let cases = [
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL123",
+                VideoUrlKind::Watch,
+                None,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "youtube.com/watch?v=dQw4w9WgXcQ",
+                VideoUrlKind::Watch,
+                None,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "https://m.youtube.com/watch?v=dQw4w9WgXcQ&t=90",
+                VideoUrlKind::Watch,
+                Some(90),
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "https://music.youtube.com/watch?v=dQw4w9WgXcQ",
+                VideoUrlKind::Music,
+                None,
+                "https://music.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?start=30",
+                VideoUrlKind::Embed,
+                Some(30),
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "https://youtu.be/dQw4w9WgXcQ?t=1h2m3s",
+                VideoUrlKind::ShortLink,
+                Some(3723),
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ#t=1m30s",
+                VideoUrlKind::Watch,
+                Some(90),
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "https://www.youtube.com/shorts/dQw4w9WgXcQ",
+                VideoUrlKind::Shorts,
+                None,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "https://www.youtube.com/live/dQw4w9WgXcQ",
+                VideoUrlKind::Live,
+                None,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "https://vimeo.com/76979871",
+                VideoUrlKind::Other,
+                None,
+                "https://vimeo.com/76979871",
+            ),
+        ];
+        for (input, kind, timestamp, normalized) in cases {
+            let video_url = parse_video_url(input).unwrap();
+            assert_eq!(video_url.kind, kind, "{}", input);
+            assert_eq!(video_url.timestamp, timestamp, "{}", input);
+            assert_eq!(video_url.normalized(), normalized, "{}", input);
+        }
+    }
+
+    #[test]
+    fn strips_tracking_params_for_every_host() {
+        let cases = [
+            (
+                "https://vimeo.com/76979871?utm_source=newsletter&utm_medium=email",
+                "https://vimeo.com/76979871",
+            ),
+            (
+                "https://example.com/video?id=42&fbclid=IwAR0abc&utm_campaign=spring",
+                "https://example.com/video?id=42",
+            ),
+            (
+                "https://example.com/video?id=42&page=2",
+                "https://example.com/video?id=42&page=2",
+            ),
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&gclid=abc&t=90",
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=90",
+            ),
+        ];
+        for (input, url) in cases {
+            let video_url = parse_video_url(input).unwrap();
+            assert_eq!(video_url.url, url, "{}", input);
+        }
+        assert_eq!(
+            parse_video_url("https://example.com/clip?utm_source=x")
+                .unwrap()
+                .normalized(),
+            "https://example.com/clip"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_video_urls() {
+        let cases = [
+            ("  ", UrlError::Empty),
+            (
+                "https://www.youtube.com/watch?v=short",
+                UrlError::InvalidVideoId("short".to_string()),
+            ),
+            (
+                "https://youtu.be/dQw4w9WgXc!",
+                UrlError::InvalidVideoId("dQw4w9WgXc!".to_string()),
+            ),
+            ("https://www.youtube.com/watch", UrlError::MissingVideoId),
+            ("https://youtu.be/", UrlError::MissingVideoId),
+            (
+                "https://www.youtube.com/feed/trending",
+                UrlError::UnsupportedPath("/feed/trending".to_string()),
+            ),
+            (
+                "https://music.youtube.com/browse/x",
+                UrlError::UnsupportedPath("/browse/x".to_string()),
+            ),
+            (
+                "https://localhost/watch?v=dQw4w9WgXcQ",
+                UrlError::UnsupportedHost("localhost".to_string()),
+            ),
+        ];
+        for (input, error) in cases {
+            assert_eq!(parse_video_url(input), Err(error), "{}", input);
+        }
+        assert!(matches!(
+            parse_video_url("ftp://youtube.com/watch?v=dQw4w9WgXcQ"),
+            Err(UrlError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        let cases = [
+            ("90", Some(90)),
+            ("90s", Some(90)),
+            ("1m30s", Some(90)),
+            ("1h2m3s", Some(3723)),
+            ("2h", Some(7200)),
+            ("", None),
+            ("1x", None),
+            ("1m30", None),
+            ("h", None),
+            ("4294967296", None),
+            ("4294967295s", Some(u32::MAX)),
+            ("1193047h", None),
+            ("71582789m", None),
+            ("4294967295s1s", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_timestamp(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn finds_video_urls_in_text() {
+        let cases = [
+            (
+                "see https://youtu.be/dQw4w9WgXcQ and www.youtube.com/watch?v=9bZkp7q19f0&t=5",
+                vec![
+                    "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                    "https://www.youtube.com/watch?v=9bZkp7q19f0",
+                ],
+            ),
+            (
+                "<a href=\"https://m.youtube.com/shorts/dQw4w9WgXcQ\">short</a>",
+                vec!["https://www.youtube.com/watch?v=dQw4w9WgXcQ"],
+            ),
+            (
+                "https://music.youtube.com/watch?v=dQw4w9WgXcQ",
+                vec!["https://music.youtube.com/watch?v=dQw4w9WgXcQ"],
+            ),
+            (
+                "https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ",
+                vec!["https://www.youtube.com/watch?v=dQw4w9WgXcQ"],
+            ),
+            ("https://vimeo.com/76979871 and youtu.be/short", vec![]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(find_video_urls(text), expected, "{}", text);
+        }
+    }
+}
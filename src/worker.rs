@@ -0,0 +1,861 @@
+//! The background worker that runs the downloads, and the protocol to talk to it.
+//!
+//! Clients send [`WorkerCommand`]s through a [`WorkerHandle`], which gives each one a
+//! job id. Every [`WorkerEvent`] the command causes comes back tagged with that id, so
+//! the app, the control API and the CLI can share one worker and still tell their
+//! replies apart.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, warn};
+
+use crate::backend::Video;
+use crate::batch::{self, BatchReport};
+use crate::disk_space::{self, DiskSpaceShortage};
+use crate::downloader::{
+    auto_select_formats, change_video_url, download_video, get_video_info, get_video_thumbnail,
+    save_thumbnail, VideoDownloader,
+};
+use crate::hook::{self, HookOutcome};
+use crate::installer;
+use crate::live::{self, RecordingOptions};
+use crate::notifications::{NotificationEvent, NotificationSettings, Notifier};
+use crate::subscriptions;
+use crate::thumbnail::ThumbnailRequest;
+
+pub type JobId = u64;
+
+// Progress events come in bursts, a slow client should not miss the final event.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkerCommand {
+    InstallLibraries,
+    UpdateLibraries,
+    /// Fetches the info of `url`, which the following commands then work on.
+    FetchInfo {
+        url: String,
+        refresh: bool,
+    },
+    FetchThumbnail,
+    SaveThumbnail(ThumbnailRequest),
+    SelectVideoFormat(String),
+    SelectAudioFormat(String),
+    SelectAudioVideoFormat(String),
+    SetOutputDir(PathBuf),
+    /// Saves the next download somewhere else, or back in the output dir with `None`.
+    SetDownloadOutputDir(Option<PathBuf>),
+    SetPostDownloadHook(Option<String>),
+    SetNotificationSettings(NotificationSettings),
+    Download {
+        check_disk_space: bool,
+    },
+    /// Fetches and downloads `url` on a copy of the downloader, leaving the video and
+    /// formats the app has selected alone. Formats left `None` are picked automatically.
+    DownloadUrl {
+        url: String,
+        video_format: Option<String>,
+        audio_format: Option<String>,
+        output_dir: Option<PathBuf>,
+        check_disk_space: bool,
+    },
+    RecordLive(RecordingOptions),
+    /// Cancels the download or recording started by the given job.
+    Cancel(JobId),
+    ImportBatch {
+        path: PathBuf,
+        refresh: bool,
+    },
+    /// Checks the subscriptions stored in the given file.
+    CheckSubscriptions(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub enum WorkerEvent {
+    Status(String),
+    Progress {
+        downloaded_bytes: f64,
+        percent: f32,
+    },
+    LibrariesInstalled,
+    LibrariesUpdated,
+    InfoFetched(Video),
+    InfoFetchFailed(String),
+    ThumbnailFetched(PathBuf),
+    ThumbnailSaved(PathBuf),
+    InsufficientDiskSpace(DiskSpaceShortage),
+    VideoDownloaded(PathBuf),
+    DownloadFailed(String),
+    HookFinished(HookOutcome),
+    BatchFinished(BatchReport),
+    SubscriptionsChecked(BatchReport),
+    /// A job without a failure event of its own failed.
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: JobId,
+    pub command: WorkerCommand,
+}
+
+#[derive(Debug, Clone)]
+pub struct JobEvent {
+    pub job_id: JobId,
+    pub event: WorkerEvent,
+}
+
+/// Sends the events of one job.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    job_id: JobId,
+    events: broadcast::Sender<JobEvent>,
+}
+
+impl EventSender {
+    pub fn new(job_id: JobId, events: broadcast::Sender<JobEvent>) -> Self {
+        Self { job_id, events }
+    }
+
+    pub fn job_id(&self) -> JobId {
+        self.job_id
+    }
+
+    pub fn send(&self, event: WorkerEvent) {
+        let _ = self.events.send(JobEvent {
+            job_id: self.job_id,
+            event,
+        });
+    }
+
+    pub fn status(&self, status_message: impl Into<String>) {
+        self.send(WorkerEvent::Status(status_message.into()));
+    }
+
+    pub fn progress(&self, downloaded_bytes: f64, percent: f32) {
+        self.send(WorkerEvent::Progress {
+            downloaded_bytes,
+            percent,
+        });
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerHandle {
+    commands: mpsc::UnboundedSender<Job>,
+    events: broadcast::Sender<JobEvent>,
+    next_job_id: Arc<AtomicU64>,
+}
+
+impl WorkerHandle {
+    /// Queues `command` and returns the id its events will carry.
+    pub fn send(&self, command: WorkerCommand) -> JobId {
+        let id = self.next_job_id.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.commands.send(Job { id, command }).is_err() {
+            warn!("Worker stopped, dropping job {}", id);
+        }
+        id
+    }
+
+    /// Queues `command` and returns a receiver for its events only.
+    pub fn start(&self, command: WorkerCommand) -> JobEvents {
+        // Subscribe before sending so no event can be missed.
+        let events = self.events.subscribe();
+        let job_id = self.send(command);
+        JobEvents { job_id, events }
+    }
+
+    /// Events of every job, including the ones started by other clients.
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+}
+
+/// Stands in for the worker in tests: receives the jobs sent through the handle and
+/// sends events for them.
+#[cfg(test)]
+pub struct FakeWorker {
+    pub jobs: mpsc::UnboundedReceiver<Job>,
+    pub events: broadcast::Sender<JobEvent>,
+}
+
+#[cfg(test)]
+impl FakeWorker {
+    pub fn new() -> (WorkerHandle, Self) {
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        let (events_tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let handle = WorkerHandle {
+            commands: commands_tx,
+            events: events_tx.clone(),
+            next_job_id: Arc::new(AtomicU64::new(0)),
+        };
+        (
+            handle,
+            Self {
+                jobs: commands_rx,
+                events: events_tx,
+            },
+        )
+    }
+}
+
+/// The events of a single job.
+pub struct JobEvents {
+    pub job_id: JobId,
+    events: broadcast::Receiver<JobEvent>,
+}
+
+impl JobEvents {
+    /// The next event of the job, or `None` once the worker is gone.
+    pub async fn next(&mut self) -> Option<WorkerEvent> {
+        loop {
+            match self.events.recv().await {
+                Ok(job_event) if job_event.job_id == self.job_id => return Some(job_event.event),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    debug!("Job {} skipped {} worker events", self.job_id, skipped)
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// Runs a worker for `video_downloader` on its own thread.
+pub fn spawn(
+    video_downloader: VideoDownloader,
+    notification_settings: NotificationSettings,
+) -> WorkerHandle {
+    let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+    let (events_tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+    let worker_events_tx = events_tx.clone();
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async move {
+                let worker = Worker {
+                    video_downloader,
+                    notifier: Notifier::session(notification_settings).await,
+                    commands: commands_rx,
+                    deferred: VecDeque::new(),
+                    events: worker_events_tx,
+                };
+                worker.run().await;
+            })
+    });
+    WorkerHandle {
+        commands: commands_tx,
+        events: events_tx,
+        next_job_id: Arc::new(AtomicU64::new(0)),
+    }
+}
+
+struct Worker {
+    video_downloader: VideoDownloader,
+    notifier: Notifier,
+    commands: mpsc::UnboundedReceiver<Job>,
+    /// Commands that arrived during a download, run once it is done.
+    deferred: VecDeque<Job>,
+    events: broadcast::Sender<JobEvent>,
+}
+
+impl Worker {
+    async fn run(mut self) {
+        loop {
+            let job = match self.deferred.pop_front() {
+                Some(job) => job,
+                None => match self.commands.recv().await {
+                    Some(job) => job,
+                    None => return,
+                },
+            };
+            debug!("Worker running job {}: {:?}", job.id, job.command);
+            let events = EventSender::new(job.id, self.events.clone());
+            self.handle(job.command, &events).await;
+        }
+    }
+
+    async fn handle(&mut self, command: WorkerCommand, events: &EventSender) {
+        let video_downloader = &mut self.video_downloader;
+        match command {
+            WorkerCommand::InstallLibraries => {
+                match installer::install(&video_downloader.executables_dir).await {
+                    Ok((_ffmpeg_path, _yt_dlp_path)) => {
+                        events.send(WorkerEvent::LibrariesInstalled)
+                    }
+                    Err(e) => events.send(WorkerEvent::Failed(format!(
+                        "Failed to install libraries: {}",
+                        e
+                    ))),
+                }
+            }
+            WorkerCommand::UpdateLibraries => {
+                match installer::update(&video_downloader.executables_dir).await {
+                    Ok(_yt_dlp_path) => events.send(WorkerEvent::LibrariesUpdated),
+                    Err(e) => events.send(WorkerEvent::Failed(format!(
+                        "Failed to update libraries: {}",
+                        e
+                    ))),
+                }
+            }
+            WorkerCommand::FetchInfo { url, refresh } => {
+                change_video_url(video_downloader, url);
+                match get_video_info(video_downloader, events, refresh).await {
+                    Ok(video_info) => events.send(WorkerEvent::InfoFetched(video_info)),
+                    Err(e) => events.send(WorkerEvent::InfoFetchFailed(e.to_string())),
+                }
+            }
+            WorkerCommand::FetchThumbnail => {
+                match get_video_thumbnail(video_downloader, events).await {
+                    Ok(path) => events.send(WorkerEvent::ThumbnailFetched(path)),
+                    Err(e) => events.send(WorkerEvent::Failed(format!(
+                        "Failed to fetch video thumbnail: {}",
+                        e
+                    ))),
+                }
+            }
+            WorkerCommand::SaveThumbnail(request) => {
+                match save_thumbnail(video_downloader, &request, events).await {
+                    Ok(path) => events.send(WorkerEvent::ThumbnailSaved(path)),
+                    Err(e) => events.send(WorkerEvent::Failed(format!(
+                        "Failed to save thumbnail: {:#}",
+                        e
+                    ))),
+                }
+            }
+            WorkerCommand::SelectVideoFormat(format_id) => {
+                video_downloader.selected_video_format = Some(format_id);
+                video_downloader.selected_audio_video_format = None;
+            }
+            WorkerCommand::SelectAudioFormat(format_id) => {
+                video_downloader.selected_audio_format = Some(format_id);
+                video_downloader.selected_audio_video_format = None;
+            }
+            WorkerCommand::SelectAudioVideoFormat(format_id) => {
+                video_downloader.selected_audio_video_format = Some(format_id);
+            }
+            WorkerCommand::SetOutputDir(output_dir) => {
+                video_downloader.set_output_dir(output_dir);
+            }
+            WorkerCommand::SetDownloadOutputDir(output_dir) => {
+                video_downloader.output_dir_override = output_dir;
+            }
+            WorkerCommand::SetPostDownloadHook(post_download_hook) => {
+                video_downloader.post_download_hook = post_download_hook;
+            }
+            WorkerCommand::SetNotificationSettings(notification_settings) => {
+                self.notifier.settings = notification_settings;
+            }
+            WorkerCommand::Download { check_disk_space } => {
+                if check_disk_space && !has_disk_space(video_downloader, events) {
+                    return;
+                }
+                self.download(None, None, events).await;
+            }
+            WorkerCommand::DownloadUrl {
+                url,
+                video_format,
+                audio_format,
+                output_dir,
+                check_disk_space,
+            } => {
+                let mut job_downloader = video_downloader.clone();
+                change_video_url(&mut job_downloader, url);
+                job_downloader.output_dir_override = output_dir;
+                let video_info = match get_video_info(&mut job_downloader, events, false).await {
+                    Ok(video_info) => video_info,
+                    Err(e) => {
+                        events.send(WorkerEvent::InfoFetchFailed(e.to_string()));
+                        return;
+                    }
+                };
+                events.send(WorkerEvent::InfoFetched(video_info.clone()));
+
+                // A side with neither a requested nor an automatic format stays empty, so
+                // `download_video` falls back to a progressive format.
+                let (auto_video_format, auto_audio_format) = auto_select_formats(&video_info);
+                job_downloader.selected_video_format = video_format.or(auto_video_format);
+                job_downloader.selected_audio_format = audio_format.or(auto_audio_format);
+                job_downloader.selected_audio_video_format = None;
+                if check_disk_space && !has_disk_space(&job_downloader, events) {
+                    return;
+                }
+                self.download(Some(job_downloader), None, events).await;
+            }
+            WorkerCommand::RecordLive(options) => {
+                self.download(None, Some(options), events).await;
+            }
+            WorkerCommand::Cancel(job_id) => {
+                debug!("Job {} is not running, nothing to cancel", job_id);
+            }
+            WorkerCommand::ImportBatch { path, refresh } => match batch::parse_batch_file(&path) {
+                Ok((entries, invalid_entries)) => {
+                    let mut cancel_check = CancelCheck::new(
+                        &mut self.commands,
+                        &mut self.deferred,
+                        &self.events,
+                        events.job_id(),
+                    );
+                    let report = batch::run_batch(
+                        video_downloader,
+                        entries,
+                        invalid_entries,
+                        events,
+                        refresh,
+                        &mut cancel_check,
+                    )
+                    .await;
+                    self.notifier
+                        .notify(
+                            NotificationEvent::QueueDrained,
+                            "Batch finished",
+                            &report.to_string(),
+                        )
+                        .await;
+                    events.send(WorkerEvent::BatchFinished(report));
+                }
+                Err(e) => events.send(WorkerEvent::Failed(format!(
+                    "Failed to import {}: {}",
+                    path.display(),
+                    e
+                ))),
+            },
+            WorkerCommand::CheckSubscriptions(store_path) => {
+                let mut cancel_check = CancelCheck::new(
+                    &mut self.commands,
+                    &mut self.deferred,
+                    &self.events,
+                    events.job_id(),
+                );
+                let report = subscriptions::check_subscriptions(
+                    video_downloader,
+                    &store_path,
+                    events,
+                    &mut cancel_check,
+                )
+                .await;
+                if report.total > 0 {
+                    self.notifier
+                        .notify(
+                            NotificationEvent::QueueDrained,
+                            "New uploads downloaded",
+                            &report.to_string(),
+                        )
+                        .await;
+                }
+                events.send(WorkerEvent::SubscriptionsChecked(report));
+            }
+        }
+    }
+
+    /// Downloads the selected formats, or records the live stream with `recording`,
+    /// until done or cancelled. Works on `job_downloader` if given, otherwise on the
+    /// app's downloader.
+    async fn download(
+        &mut self,
+        mut job_downloader: Option<VideoDownloader>,
+        recording: Option<RecordingOptions>,
+        events: &EventSender,
+    ) {
+        let video_downloader = match &mut job_downloader {
+            Some(job_downloader) => job_downloader,
+            None => &mut self.video_downloader,
+        };
+        // Dropping the download future on cancel also drops its job temp dir, which
+        // removes the partial streams.
+        let download = async {
+            match &recording {
+                Some(options) => live::record_live_stream(video_downloader, options, events).await,
+                None => download_video(video_downloader, events).await,
+            }
+        };
+        let download_result = tokio::select! {
+            download_result = download => download_result,
+            _ = wait_for_cancel(
+                &mut self.commands,
+                &mut self.deferred,
+                &self.events,
+                events.job_id(),
+            ) => {
+                Err(anyhow::anyhow!("Download cancelled"))
+            }
+        };
+
+        let video_downloader = &*video_downloader;
+        let path = match download_result {
+            Ok(path) => path,
+            Err(e) => {
+                self.notifier
+                    .notify(
+                        NotificationEvent::DownloadFailed,
+                        "Download failed",
+                        &format!("{}: {:#}", video_downloader.video_info.title, e),
+                    )
+                    .await;
+                events.send(WorkerEvent::DownloadFailed(format!("{:#}", e)));
+                return;
+            }
+        };
+        events.send(WorkerEvent::VideoDownloaded(path.clone()));
+        self.notifier
+            .notify(
+                NotificationEvent::DownloadFinished,
+                "Download finished",
+                &format!(
+                    "{} was saved to {}",
+                    video_downloader.video_info.title,
+                    path.display()
+                ),
+            )
+            .await;
+        if let Some(post_download_hook) = &video_downloader.post_download_hook {
+            match hook::run_post_download_hook(
+                post_download_hook,
+                &path,
+                &video_downloader.video_info.id,
+                &video_downloader.video_info.title,
+            )
+            .await
+            {
+                Ok(outcome) => events.send(WorkerEvent::HookFinished(outcome)),
+                Err(e) => events.send(WorkerEvent::Failed(format!(
+                    "Post-download hook failed: {:#}",
+                    e
+                ))),
+            }
+        }
+    }
+}
+
+/// Reports an [`WorkerEvent::InsufficientDiskSpace`] and returns `false` when the
+/// selected formats do not fit.
+fn has_disk_space(video_downloader: &VideoDownloader, events: &EventSender) -> bool {
+    match disk_space::check_disk_space(video_downloader) {
+        Ok(Some(shortage)) => {
+            warn!("{}", shortage);
+            events.send(WorkerEvent::InsufficientDiskSpace(shortage));
+            false
+        }
+        Ok(None) => true,
+        Err(e) => {
+            warn!("Could not check free disk space: {}", e);
+            true
+        }
+    }
+}
+
+/// Waits for a [`WorkerCommand::Cancel`] of `job_id`, keeping every other command for
+/// after the download.
+async fn wait_for_cancel(
+    commands: &mut mpsc::UnboundedReceiver<Job>,
+    deferred: &mut VecDeque<Job>,
+    events: &broadcast::Sender<JobEvent>,
+    job_id: JobId,
+) {
+    loop {
+        match commands.recv().await {
+            Some(job) => {
+                if take_command(job, deferred, events, job_id) {
+                    return;
+                }
+            }
+            None => std::future::pending::<()>().await,
+        }
+    }
+}
+
+/// Handles a command that arrived while `job_id` is running: returns `true` if it
+/// cancels that job, otherwise cancels a deferred job or defers the command.
+fn take_command(
+    job: Job,
+    deferred: &mut VecDeque<Job>,
+    events: &broadcast::Sender<JobEvent>,
+    job_id: JobId,
+) -> bool {
+    match job.command {
+        WorkerCommand::Cancel(cancelled_job_id) if cancelled_job_id == job_id => true,
+        WorkerCommand::Cancel(cancelled_job_id)
+            if deferred.iter().any(|job| job.id == cancelled_job_id) =>
+        {
+            deferred.retain(|job| job.id != cancelled_job_id);
+            // Its client waits for a final event like for any other cancelled download.
+            EventSender::new(cancelled_job_id, events.clone()).send(WorkerEvent::DownloadFailed(
+                "Download cancelled".to_string(),
+            ));
+            false
+        }
+        _ => {
+            debug!("Deferring job {} until job {} is done", job.id, job_id);
+            deferred.push_back(job);
+            false
+        }
+    }
+}
+
+/// Lets jobs made of several downloads, like batches, check between them whether they
+/// were cancelled. Other commands are deferred like during a download.
+pub struct CancelCheck<'a> {
+    commands: &'a mut mpsc::UnboundedReceiver<Job>,
+    deferred: &'a mut VecDeque<Job>,
+    events: &'a broadcast::Sender<JobEvent>,
+    job_id: JobId,
+    cancelled: bool,
+}
+
+impl<'a> CancelCheck<'a> {
+    pub fn new(
+        commands: &'a mut mpsc::UnboundedReceiver<Job>,
+        deferred: &'a mut VecDeque<Job>,
+        events: &'a broadcast::Sender<JobEvent>,
+        job_id: JobId,
+    ) -> Self {
+        Self {
+            commands,
+            deferred,
+            events,
+            job_id,
+            cancelled: false,
+        }
+    }
+
+    /// Whether a [`WorkerCommand::Cancel`] of the job has arrived by now.
+    pub fn is_cancelled(&mut self) -> bool {
+        while !self.cancelled {
+            match self.commands.try_recv() {
+                Ok(job) => {
+                    self.cancelled = take_command(job, self.deferred, self.events, self.job_id)
+                }
+                Err(_) => break,
+            }
+        }
+        self.cancelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use crate::backend::Format;
+
+    const VIDEO_URL: &str = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+    const MISSING_VIDEO_URL: &str = "https://www.youtube.com/watch?v=9bZkp7q19f0";
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-worker-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn fake_format(format_id: &str, vcodec: &str, acodec: &str) -> Format {
+        Format {
+            format_id: format_id.to_string(),
+            format_note: Some("medium".to_string()),
+            url: format!("https://example.invalid/{}", format_id),
+            video_codec: Some(vcodec.to_string()),
+            audio_codec: Some(acodec.to_string()),
+            video_ext: Some("mp4".to_string()),
+            audio_ext: Some("none".to_string()),
+            ..Format::default()
+        }
+    }
+
+    fn fake_video(id: &str, title: &str, formats: Vec<Format>) -> Video {
+        Video {
+            id: id.to_string(),
+            title: title.to_string(),
+            formats,
+            ..Video::default()
+        }
+    }
+
+    fn quiet() -> NotificationSettings {
+        NotificationSettings {
+            enabled: false,
+            ..NotificationSettings::default()
+        }
+    }
+
+    async fn next_result(events: &mut JobEvents) -> WorkerEvent {
+        loop {
+            match events.next().await.unwrap() {
+                WorkerEvent::Status(_) | WorkerEvent::Progress { .. } => {}
+                event => return event,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn events_carry_the_job_id_of_their_command() {
+        let dir = test_dir("job-ids");
+        let video = fake_video("dQw4w9WgXcQ", "Fake Video", Vec::new());
+        let mut video_downloader =
+            VideoDownloader::new("libs", &dir.join("output").display().to_string(), "")
+                .unwrap()
+                .with_backend(Arc::new(
+                    FakeBackend::default().with_video(VIDEO_URL, video),
+                ));
+        video_downloader.metadata_cache.cache_dir = dir.join("cache");
+        video_downloader.temp_root = dir.join("temp");
+        video_downloader.retry_policy.max_attempts = 1;
+        let worker = spawn(video_downloader, quiet());
+
+        let mut missing = worker.start(WorkerCommand::FetchInfo {
+            url: MISSING_VIDEO_URL.to_string(),
+            refresh: true,
+        });
+        let mut found = worker.start(WorkerCommand::FetchInfo {
+            url: VIDEO_URL.to_string(),
+            refresh: true,
+        });
+
+        assert_ne!(missing.job_id, found.job_id);
+        assert!(matches!(
+            next_result(&mut missing).await,
+            WorkerEvent::InfoFetchFailed(_)
+        ));
+        match next_result(&mut found).await {
+            WorkerEvent::InfoFetched(video_info) => assert_eq!(video_info.title, "Fake Video"),
+            event => panic!("unexpected event {:?}", event),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn download_url_leaves_the_app_selection_alone() {
+        let dir = test_dir("download-url");
+        let app_video = fake_video(
+            "dQw4w9WgXcQ",
+            "App Video",
+            vec![
+                fake_format("137", "avc1.640028", "none"),
+                fake_format("140", "none", "mp4a.40.2"),
+            ],
+        );
+        let api_video = fake_video(
+            "9bZkp7q19f0",
+            "Api Video",
+            vec![fake_format("18", "avc1.42001E", "mp4a.40.2")],
+        );
+        let backend = FakeBackend::default()
+            .with_video(VIDEO_URL, app_video)
+            .with_video(MISSING_VIDEO_URL, api_video)
+            .with_stream("https://example.invalid/137", b"video|")
+            .with_stream("https://example.invalid/140", b"audio|")
+            .with_stream("https://example.invalid/18", b"progressive|");
+        let mut video_downloader =
+            VideoDownloader::new("libs", &dir.join("output").display().to_string(), "")
+                .unwrap()
+                .with_backend(Arc::new(backend));
+        video_downloader.metadata_cache.cache_dir = dir.join("cache");
+        video_downloader.temp_root = dir.join("temp");
+        video_downloader.retry_policy.max_attempts = 1;
+        let worker = spawn(video_downloader, quiet());
+
+        let mut app_info = worker.start(WorkerCommand::FetchInfo {
+            url: VIDEO_URL.to_string(),
+            refresh: true,
+        });
+        assert!(matches!(
+            next_result(&mut app_info).await,
+            WorkerEvent::InfoFetched(_)
+        ));
+        worker.send(WorkerCommand::SelectVideoFormat("137".to_string()));
+        worker.send(WorkerCommand::SelectAudioFormat("140".to_string()));
+
+        // The app's formats do not exist in the other video and must not be reused.
+        let mut api_download = worker.start(WorkerCommand::DownloadUrl {
+            url: MISSING_VIDEO_URL.to_string(),
+            video_format: None,
+            audio_format: None,
+            output_dir: None,
+            check_disk_space: false,
+        });
+        match next_result(&mut api_download).await {
+            WorkerEvent::InfoFetched(video_info) => assert_eq!(video_info.title, "Api Video"),
+            event => panic!("unexpected event {:?}", event),
+        }
+        match next_result(&mut api_download).await {
+            WorkerEvent::VideoDownloaded(path) => {
+                assert_eq!(path, dir.join("output").join("Api Video.mp4"))
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        let mut app_download = worker.start(WorkerCommand::Download {
+            check_disk_space: false,
+        });
+        match next_result(&mut app_download).await {
+            WorkerEvent::VideoDownloaded(path) => {
+                assert_eq!(path, dir.join("output").join("App Video.mp4"));
+                assert_eq!(std::fs::read(&path).unwrap(), b"video|audio|");
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_deferred_job_ends_its_events() {
+        let dir = test_dir("deferred-cancel");
+        let backend = FakeBackend::default()
+            .with_video(
+                VIDEO_URL,
+                fake_video(
+                    "dQw4w9WgXcQ",
+                    "Stalled Video",
+                    vec![fake_format("18", "avc1.42001E", "mp4a.40.2")],
+                ),
+            )
+            .with_stalled_stream("https://example.invalid/18");
+        let mut video_downloader =
+            VideoDownloader::new("libs", &dir.join("output").display().to_string(), "")
+                .unwrap()
+                .with_backend(Arc::new(backend));
+        video_downloader.metadata_cache.cache_dir = dir.join("cache");
+        video_downloader.temp_root = dir.join("temp");
+        let worker = spawn(video_downloader, quiet());
+        let download_url = || WorkerCommand::DownloadUrl {
+            url: VIDEO_URL.to_string(),
+            video_format: None,
+            audio_format: None,
+            output_dir: None,
+            check_disk_space: false,
+        };
+
+        let mut running = worker.start(download_url());
+        assert!(matches!(
+            next_result(&mut running).await,
+            WorkerEvent::InfoFetched(_)
+        ));
+        let mut deferred = worker.start(download_url());
+        worker.send(WorkerCommand::Cancel(deferred.job_id));
+
+        let event = tokio::time::timeout(Duration::from_secs(5), next_result(&mut deferred))
+            .await
+            .expect("the cancelled job never ended");
+        match event {
+            WorkerEvent::DownloadFailed(reason) => assert_eq!(reason, "Download cancelled"),
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        worker.send(WorkerCommand::Cancel(running.job_id));
+        assert!(matches!(
+            next_result(&mut running).await,
+            WorkerEvent::DownloadFailed(_)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
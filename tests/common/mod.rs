@@ -0,0 +1,310 @@
+//! Offline test harness: a local HTTP server serving fixture streams, thumbnails and
+//! captions, plus stub `yt-dlp` and `ffmpeg` executables.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::net::SocketAddr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use youtube_downloader::backend::yt_dlp_backend::YtDlpBackend;
+use youtube_downloader::cache::{MetadataCache, DEFAULT_CACHE_TTL};
+use youtube_downloader::downloader::VideoDownloader;
+use youtube_downloader::retry::RetryPolicy;
+use youtube_downloader::worker::{EventSender, JobEvent};
+
+pub const VIDEO_URL: &str = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+pub const VIDEO_FIXTURE: &str = include_str!("../fixtures/video.json");
+pub const CAPTIONS_FIXTURE: &str = include_str!("../fixtures/captions.vtt");
+// The fixture points its URLs here; the harness swaps in the mock server address.
+const FIXTURE_HOST: &str = "http://mock.invalid";
+pub const STALL_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// How a request should fail instead of being served normally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    NotFound,
+    /// Sends half the body, then closes the connection.
+    Truncated,
+    /// Sends the headers and half the body, then stops responding.
+    Stalled,
+}
+
+#[derive(Default)]
+struct ServerState {
+    files: HashMap<String, Vec<u8>>,
+    faults: HashMap<String, VecDeque<Fault>>,
+    hits: HashMap<String, usize>,
+}
+
+/// Minimal HTTP/1.1 server writing raw responses, so faults can break the protocol
+/// in ways a real server framework would not allow.
+#[derive(Clone)]
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<Mutex<ServerState>>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = Self {
+            address: listener.local_addr().unwrap(),
+            state: Arc::default(),
+        };
+        let state = server.state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_connection(stream, state.clone()));
+            }
+        });
+        server
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    pub fn serve(&self, path: &str, content: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        state.files.insert(path.to_string(), content.to_vec());
+    }
+
+    /// Makes the next `times` requests for `path` fail with `fault`.
+    pub fn fail(&self, path: &str, fault: Fault, times: usize) {
+        let mut state = self.state.lock().unwrap();
+        let faults = state.faults.entry(path.to_string()).or_default();
+        faults.extend(std::iter::repeat(fault).take(times));
+    }
+
+    pub fn hits(&self, path: &str) -> usize {
+        let state = self.state.lock().unwrap();
+        state.hits.get(path).copied().unwrap_or(0)
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<Mutex<ServerState>>) {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await.is_err() {
+        return;
+    }
+    // Skip the headers, the mock server does not need any of them.
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header).await {
+            Ok(0) | Err(_) => return,
+            Ok(_) if header.trim().is_empty() => break,
+            Ok(_) => {}
+        }
+    }
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .split('?')
+        .next()
+        .unwrap_or("/")
+        .to_string();
+
+    let (content, fault) = {
+        let mut state = state.lock().unwrap();
+        *state.hits.entry(path.clone()).or_default() += 1;
+        let fault = state
+            .faults
+            .get_mut(&path)
+            .and_then(|faults| faults.pop_front());
+        (state.files.get(&path).cloned(), fault)
+    };
+
+    let mut stream = reader.into_inner();
+    let content = match (content, fault) {
+        (Some(content), None | Some(Fault::Truncated) | Some(Fault::Stalled)) => content,
+        _ => {
+            let _ = stream
+                .write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await;
+            return;
+        }
+    };
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        content.len()
+    );
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+    match fault {
+        None => {
+            let _ = stream.write_all(&content).await;
+        }
+        Some(Fault::Truncated) => {
+            let _ = stream.write_all(&content[..content.len() / 2]).await;
+        }
+        Some(Fault::Stalled) => {
+            let _ = stream.write_all(&content[..content.len() / 2]).await;
+            let _ = stream.flush().await;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        }
+        Some(Fault::NotFound) => unreachable!(),
+    }
+    let _ = stream.shutdown().await;
+}
+
+/// Distinct byte patterns, so tests can tell which stream ended up where.
+pub fn fake_stream(marker: &str, size: usize) -> Vec<u8> {
+    marker.bytes().cycle().take(size).collect()
+}
+
+pub fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+pub struct Harness {
+    pub dir: PathBuf,
+    pub server: MockServer,
+    pub video_downloader: VideoDownloader,
+    pub video_stream: Vec<u8>,
+    pub audio_stream: Vec<u8>,
+    pub thumbnail: Vec<u8>,
+    pub events: EventSender,
+    _rx: tokio::sync::broadcast::Receiver<JobEvent>,
+}
+
+impl Harness {
+    /// Starts the mock server and points a downloader using the real yt-dlp backend at
+    /// stub executables in a fresh directory named after the test.
+    pub async fn start(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "youtube_downloader-it-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let executables_dir = dir.join("libs");
+        fs::create_dir_all(&executables_dir).unwrap();
+
+        let server = MockServer::start().await;
+        let video_stream = fake_stream("video-stream|", 128 * 1024);
+        let audio_stream = fake_stream("audio-stream|", 64 * 1024);
+        let thumbnail = fake_stream("thumbnail|", 4 * 1024);
+        server.serve("/video.mp4", &video_stream);
+        server.serve("/audio.m4a", &audio_stream);
+        server.serve("/thumbnail.jpg", &thumbnail);
+        server.serve("/captions.vtt", CAPTIONS_FIXTURE.as_bytes());
+
+        let metadata_path = dir.join("metadata.json");
+        fs::write(
+            &metadata_path,
+            VIDEO_FIXTURE.replace(FIXTURE_HOST, &server.base_url()),
+        )
+        .unwrap();
+        write_stub_yt_dlp(&executables_dir, &metadata_path);
+        write_stub_ffmpeg(&executables_dir);
+
+        let mut video_downloader = VideoDownloader::new(
+            &executables_dir.display().to_string(),
+            &dir.join("output").display().to_string(),
+            VIDEO_URL,
+        )
+        .unwrap();
+        let backend = YtDlpBackend::new(
+            video_downloader.yt_dlp_executable_path.clone(),
+            video_downloader.ffmpeg_executable_path.clone(),
+        )
+        .unwrap()
+        .with_stall_timeout(STALL_TIMEOUT);
+        video_downloader = video_downloader.with_backend(Arc::new(backend));
+        video_downloader.metadata_cache =
+            MetadataCache::new(&dir.join("cache").display().to_string(), DEFAULT_CACHE_TTL);
+        video_downloader.temp_root = dir.join("temp");
+        video_downloader.retry_policy = RetryPolicy {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        };
+
+        let (tx, rx) = tokio::sync::broadcast::channel(1024);
+        Self {
+            dir,
+            server,
+            video_downloader,
+            video_stream,
+            audio_stream,
+            thumbnail,
+            events: EventSender::new(1, tx),
+            _rx: rx,
+        }
+    }
+
+    pub fn output_dir(&self) -> PathBuf {
+        self.dir.join("output")
+    }
+
+    /// Makes the stub yt-dlp fail like it does for a removed video.
+    pub fn break_extractor(&self) {
+        write_executable(
+            &self.dir.join("libs").join("yt-dlp"),
+            "#!/bin/sh\necho 'ERROR: [youtube] dQw4w9WgXcQ: Video unavailable' >&2\nexit 1\n",
+        );
+    }
+
+    /// Job temp dirs left behind under the temp root.
+    pub fn leftover_temp_dirs(&self) -> usize {
+        fs::read_dir(self.dir.join("temp"))
+            .map(|entries| entries.count())
+            .unwrap_or(0)
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn write_executable(path: &Path, script: &str) {
+    fs::write(path, script).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Prints the fixture for any metadata request and answers `--version`.
+fn write_stub_yt_dlp(executables_dir: &Path, metadata_path: &Path) {
+    write_executable(
+        &executables_dir.join("yt-dlp"),
+        &format!(
+            "#!/bin/sh\ncase \"$*\" in\n  *--version*) echo 2024.08.06 ;;\n  *) cat '{}' ;;\nesac\n",
+            metadata_path.display()
+        ),
+    );
+}
+
+/// Concatenates every `-i` input into the output, the last argument.
+fn write_stub_ffmpeg(executables_dir: &Path) {
+    write_executable(
+        &executables_dir.join("ffmpeg"),
+        concat!(
+            "#!/bin/sh\n",
+            "inputs=''\n",
+            "output=''\n",
+            "while [ $# -gt 0 ]; do\n",
+            "  case \"$1\" in\n",
+            "    -i) inputs=\"$inputs $2\"; shift 2 ;;\n",
+            "    *) output=\"$1\"; shift ;;\n",
+            "  esac\n",
+            "done\n",
+            "cat $inputs > \"$output\"\n",
+        ),
+    );
+}
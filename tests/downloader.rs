@@ -0,0 +1,206 @@
+//! End-to-end downloader tests against the mock media server and stub executables.
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::{contains, Fault, Harness, CAPTIONS_FIXTURE};
+use youtube_downloader::downloader::{download_video, get_video_info, get_video_thumbnail};
+
+#[tokio::test]
+async fn get_video_info_reads_extractor_output() {
+    let mut harness = Harness::start("info").await;
+    harness.video_downloader.organize_by_extractor = true;
+
+    let video_info = get_video_info(&mut harness.video_downloader, &harness.events, true)
+        .await
+        .unwrap();
+
+    assert_eq!(video_info.title, "Mock Server: Test Video");
+    assert_eq!(video_info.extractor_key, "Youtube");
+    assert!(
+        video_info
+            .formats
+            .iter()
+            .all(|format| !format.is_storyboard()),
+        "storyboards are filtered out"
+    );
+    assert_eq!(
+        harness.video_downloader.output_file_name,
+        "Mock Server Test Video"
+    );
+    assert!(harness
+        .output_dir()
+        .join("Youtube")
+        .join("Mock Server Test Video.txt")
+        .exists());
+}
+
+#[tokio::test]
+async fn get_video_info_reports_extractor_failure() {
+    let mut harness = Harness::start("info-failure").await;
+    harness.break_extractor();
+
+    let error = get_video_info(&mut harness.video_downloader, &harness.events, true)
+        .await
+        .unwrap_err();
+
+    assert!(
+        format!("{:#}", error).contains("Video unavailable"),
+        "unexpected error: {:#}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn get_video_thumbnail_downloads_from_server() {
+    let mut harness = Harness::start("thumbnail").await;
+    harness.video_downloader.organize_by_extractor = true;
+    get_video_info(&mut harness.video_downloader, &harness.events, true)
+        .await
+        .unwrap();
+
+    let thumbnail_path = get_video_thumbnail(&mut harness.video_downloader, &harness.events)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        thumbnail_path,
+        harness
+            .output_dir()
+            .join("Youtube")
+            .join("Mock Server Test Video.jpg")
+    );
+    assert_eq!(fs::read(&thumbnail_path).unwrap(), harness.thumbnail);
+    assert_eq!(harness.server.hits("/thumbnail.jpg"), 1);
+}
+
+#[tokio::test]
+async fn download_video_merges_streams_and_saves_captions() {
+    let mut harness = Harness::start("download").await;
+    harness.video_downloader.organize_by_extractor = true;
+    get_video_info(&mut harness.video_downloader, &harness.events, true)
+        .await
+        .unwrap();
+
+    let video_path = download_video(&mut harness.video_downloader, &harness.events)
+        .await
+        .unwrap();
+
+    let target_dir = harness.output_dir().join("Youtube");
+    assert_eq!(video_path, target_dir.join("Mock Server Test Video.mp4"));
+    let merged = fs::read(&video_path).unwrap();
+    assert_eq!(
+        merged.len(),
+        harness.video_stream.len() + harness.audio_stream.len()
+    );
+    assert!(contains(&merged, b"video-stream|"));
+    assert!(contains(&merged, b"audio-stream|"));
+    assert_eq!(
+        fs::read_to_string(target_dir.join("Mock Server Test Video.en.vtt")).unwrap(),
+        CAPTIONS_FIXTURE
+    );
+    assert_eq!(harness.leftover_temp_dirs(), 0);
+}
+
+#[tokio::test]
+async fn download_video_merges_streams_under_a_relative_temp_root() {
+    let mut harness = Harness::start("relative-temp").await;
+    // Integration tests run from the package root.
+    let temp_root = PathBuf::from("target").join(format!(
+        "youtube_downloader-relative-temp-{}",
+        std::process::id()
+    ));
+    harness.video_downloader.temp_root = temp_root.clone();
+    get_video_info(&mut harness.video_downloader, &harness.events, true)
+        .await
+        .unwrap();
+
+    let result = download_video(&mut harness.video_downloader, &harness.events).await;
+    let leftover_temp_dirs = fs::read_dir(&temp_root)
+        .map(|entries| entries.count())
+        .unwrap_or(0);
+    let _ = fs::remove_dir_all(&temp_root);
+
+    let video_path = result.unwrap();
+    assert_eq!(
+        fs::read(&video_path).unwrap().len(),
+        harness.video_stream.len() + harness.audio_stream.len()
+    );
+    assert_eq!(leftover_temp_dirs, 0);
+}
+
+#[tokio::test]
+async fn download_video_retries_truncated_stream() {
+    let mut harness = Harness::start("truncated").await;
+    harness.server.fail("/video.mp4", Fault::Truncated, 1);
+    get_video_info(&mut harness.video_downloader, &harness.events, true)
+        .await
+        .unwrap();
+
+    let video_path = download_video(&mut harness.video_downloader, &harness.events)
+        .await
+        .unwrap();
+
+    assert_eq!(harness.server.hits("/video.mp4"), 2);
+    assert_eq!(
+        fs::read(&video_path).unwrap().len(),
+        harness.video_stream.len() + harness.audio_stream.len()
+    );
+}
+
+#[tokio::test]
+async fn download_video_gives_up_on_missing_stream() {
+    let mut harness = Harness::start("not-found").await;
+    harness.server.fail("/audio.m4a", Fault::NotFound, 1);
+    get_video_info(&mut harness.video_downloader, &harness.events, true)
+        .await
+        .unwrap();
+
+    let error = download_video(&mut harness.video_downloader, &harness.events)
+        .await
+        .unwrap_err();
+
+    assert!(
+        format!("{:#}", error).contains("404"),
+        "unexpected error: {:#}",
+        error
+    );
+    // A 404 is not worth retrying.
+    assert_eq!(harness.server.hits("/audio.m4a"), 1);
+    assert!(!harness
+        .output_dir()
+        .join("Mock Server Test Video.mp4")
+        .exists());
+    assert_eq!(harness.leftover_temp_dirs(), 0);
+}
+
+#[tokio::test]
+async fn download_video_times_out_on_stalled_stream() {
+    let mut harness = Harness::start("stalled").await;
+    let max_attempts = harness.video_downloader.retry_policy.max_attempts as usize;
+    harness
+        .server
+        .fail("/video.mp4", Fault::Stalled, max_attempts);
+    get_video_info(&mut harness.video_downloader, &harness.events, true)
+        .await
+        .unwrap();
+
+    let error = download_video(&mut harness.video_downloader, &harness.events)
+        .await
+        .unwrap_err();
+
+    assert!(
+        format!("{:#}", error).contains("timed out"),
+        "unexpected error: {:#}",
+        error
+    );
+    assert_eq!(harness.server.hits("/video.mp4"), max_attempts);
+    assert!(!harness
+        .output_dir()
+        .join("Mock Server Test Video.mp4")
+        .exists());
+    assert_eq!(harness.leftover_temp_dirs(), 0);
+}